@@ -16,9 +16,28 @@ fn return_type_error(node: impl syn::spanned::Spanned,
 
 #[proc_macro_attribute]
 pub fn paragen(
-  _args: proc_macro::TokenStream,
+  args: proc_macro::TokenStream,
   input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+  // `validate = true` checks generated models with GLTF::validate() before
+  // writing them out. `chunked = true` leaves the output for the host to pull
+  // in pieces with next_chunk(), instead of writing it all at once
+  let mut validate = false;
+  let mut chunked = false;
+  let arg_parser = syn::meta::parser(|meta| {
+    if meta.path.is_ident("validate") {
+      validate = meta.value()?.parse::<syn::LitBool>()?.value;
+      Ok(())
+    } else if meta.path.is_ident("chunked") {
+      chunked = meta.value()?.parse::<syn::LitBool>()?.value;
+      Ok(())
+    } else {
+      Err(meta.error("unsupported paragen argument, expected `validate` or \
+        `chunked`"))
+    }
+  });
+  syn::parse_macro_input!(args with arg_parser);
+  
   let input_fn = syn::parse_macro_input!(input as syn::ItemFn);
   let signature = input_fn.sig.clone();
   let base_name = signature.ident.clone();
@@ -61,6 +80,24 @@ pub fn paragen(
     _ => return return_type_error(signature.clone()),
   }
   
+  let validation = match validate {
+    false => quote::quote! {},
+    true => quote::quote! {
+      let errors: Vec<String> = gltf.validate().into_iter()
+        .filter(|message| message.severity == paragen::Severity::Error)
+        .map(|message| message.to_string()).collect();
+      if !errors.is_empty() {
+        paragen::set_error_message(&errors.join("\n"));
+        return ErrorCode::ValidationFailed as i32;
+      }
+    },
+  };
+  
+  let output = match chunked {
+    false => quote::quote! { paragen::write_gltf(&mut guard, gltf); },
+    true => quote::quote! { paragen::write_gltf_chunked(gltf); },
+  };
+  
   proc_macro::TokenStream::from(quote::quote! {
     #input_fn
     
@@ -68,16 +105,29 @@ pub fn paragen(
     #[no_mangle]
     pub extern "C" fn #full_name(#args) -> i32 {
       match paragen::MUTEX_TEST.try_lock() {
+        // Leaves the error message alone, since it belongs to whichever
+        // generation holds the lock
         Err(_) => return ErrorCode::Mutex as i32,
         Ok(mut guard) => {
           *guard = Vec::new();
+          paragen::clear_error_message();
           
           let gltf = match #base_name(#arg_names) {
-            Err(code) => return code as i32,
+            Err(code) => {
+              // Models and library functions can leave a more specific
+              // message, but there should always be something
+              if paragen::last_error_message_size() == 0 {
+                paragen::set_error_message(&format!("model `{}` returned \
+                  {:?}", stringify!(#base_name), code));
+              }
+              return code as i32;
+            },
             Ok(gltf) => gltf,
           };
           
-          paragen::write_gltf(&mut guard, gltf);
+          #validation
+          
+          #output
         },
       }
       
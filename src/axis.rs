@@ -0,0 +1,272 @@
+use crate::*;
+
+// Which way is up in a coordinate system. glTF itself is always Y-up, with
+// +Z toward the viewer, while Blender and most CAD tools are Z-up with -Y
+// toward the viewer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+  YUp,
+  ZUp,
+}
+
+// How GLTF::convert_axis() applies the conversion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisConversion {
+  // Parents each scene's root nodes under a new node rotated by 90 degrees
+  // about X. Quick, and leaves the data alone, but viewers that ignore the
+  // root transform (or tools that flatten it) see the old axes
+  RootNode,
+  // Rewrites vertex data, node transforms, and animations in the new axes
+  Bake,
+}
+
+// What a baked conversion does to an accessor's elements
+#[derive(Clone, Copy, PartialEq)]
+enum Bake {
+  // Positions, normals, tangents, translations, and the vector part of
+  // rotation quaternions, which all turn with the axes
+  Vector,
+  // Scale factors, which only trade places
+  Scale,
+  // Rotation quaternions of nodes that aim along their -Z axis, which are
+  // turned by the rotation instead of conjugated so they keep pointing the
+  // same way in the world
+  Aim,
+  // Column-major 4x4 matrices, such as inverse bind matrices, which are
+  // conjugated by the rotation so they work in the new axes
+  Matrix,
+}
+
+// Maps (x, y, z) to (x, z, -y) for sign 1, or back for sign -1
+fn rotate(sign: f64, [x, y, z]: [f64; 3]) -> [f64; 3] {
+  [x, sign * z, -sign * y]
+}
+
+// Turns quaternion (x, y, z, w) by the rotation rotate() applies, on top of
+// its own
+fn rotate_quaternion(sign: f64, [x, y, z, w]: [f64; 4]) -> [f64; 4] {
+  let a = -sign * std::f64::consts::FRAC_1_SQRT_2;
+  let c = std::f64::consts::FRAC_1_SQRT_2;
+  [c * x + a * w, c * y - a * z, c * z + a * y, c * w - a * x]
+}
+
+// Rotates a column-major affine matrix's axes and translation as rotate()
+// does, after swapping its Y and Z columns to take input in the new axes
+fn conjugate(sign: f64, m: &mut [f64]) {
+  let column = |c: usize| [m[4 * c], m[4 * c + 1], m[4 * c + 2]];
+  let columns = [column(0), column(2).map(|x| sign * x),
+    column(1).map(|x| -sign * x), column(3)];
+  for (c, v) in columns.into_iter().enumerate() {
+    m[4 * c..4 * c + 3].copy_from_slice(&rotate(sign, v));
+  }
+}
+
+impl GLTF {
+  // Converts from `from` to `to` up axis, as a rotation about X: -90 degrees
+  // for Z-up to Y-up, and 90 degrees back. Baking transforms every POSITION,
+  // NORMAL, and TANGENT accessor (keeping tangent handedness), each node's
+  // translation, rotation, and scale, the outputs of animations on those,
+  // EXT_mesh_gpu_instancing instance transforms, and skins' inverse bind
+  // matrices, then recomputes any declared min and max. Baked data must be
+  // float VEC3 or VEC4, or MAT4 for inverse bind matrices. Nodes outside
+  // every scene are only reached by baking. Fails without changing anything
+  // if data is missing or not float, if an accessor is baked in two
+  // different ways, or (for RootNode) if a root node is shared by several
+  // scenes, as it would get several parents. Cameras and spot and
+  // directional lights point along their node's -Z axis, so baking turns
+  // their nodes by the rotation instead, to keep them aimed at the same
+  // things. Baking fails if such a node also has a mesh or children or is a
+  // skin joint, as those would be turned too; use RootNode for those
+  pub fn convert_axis(&mut self, from: Axis, to: Axis,
+    mode: AxisConversion, buffers: &mut [Vec<u8>],
+  ) -> Result<(), ErrorCode> {
+    // Z-up to Y-up maps (x, y, z) to (x, z, -y), and Y-up to Z-up undoes it
+    let sign = match (from, to) {
+      (Axis::ZUp, Axis::YUp) => 1.0,
+      (Axis::YUp, Axis::ZUp) => -1.0,
+      _ => return Ok(()),
+    };
+    
+    match mode {
+      AxisConversion::RootNode => self.add_axis_root(sign),
+      AxisConversion::Bake => self.bake_axis(sign, buffers),
+    }
+  }
+  
+  fn add_axis_root(&mut self, sign: f64) -> Result<(), ErrorCode> {
+    let mut roots = std::collections::HashSet::new();
+    for &node in self.scenes.iter().flat_map(|scene| scene.nodes.iter()) {
+      if !roots.insert(node) {
+        return Err(ErrorCode::Value);
+      }
+    }
+    
+    for i in 0..self.scenes.len() {
+      let mut root = Node::new();
+      root.r = Rotation {
+        x: -sign * std::f64::consts::FRAC_1_SQRT_2,
+        y: 0.0,
+        z: 0.0,
+        w: std::f64::consts::FRAC_1_SQRT_2,
+      };
+      root.children = std::mem::take(&mut self.scenes[i].nodes);
+      self.nodes.push(root);
+      self.scenes[i].nodes.push(self.nodes.len() as u32 - 1);
+    }
+    
+    Ok(())
+  }
+  
+  fn bake_axis(&mut self, sign: f64, buffers: &mut [Vec<u8>],
+  ) -> Result<(), ErrorCode> {
+    let aims: Vec<bool> = self.nodes.iter().map(|node| node.camera.is_some()
+      || matches!(self.node_light_type(node), Some("spot" | "directional")))
+      .collect();
+    let joints: std::collections::HashSet<u32> = self.skins.iter()
+      .flat_map(|skin| skin.joints.iter().copied()).collect();
+    for (i, node) in self.nodes.iter().enumerate() {
+      if aims[i] && (node.mesh.is_some() || !node.children.is_empty()
+        || joints.contains(&(i as u32))) {
+        return Err(ErrorCode::Value);
+      }
+    }
+    
+    let mut jobs = std::collections::BTreeMap::new();
+    let mut add_job = |accessor: u32, bake: Bake| {
+      match *jobs.entry(accessor).or_insert(bake) == bake {
+        true => Ok(()),
+        false => Err(ErrorCode::Value),
+      }
+    };
+    for primitive in self.meshes.iter().flat_map(|m| m.primitives.iter()) {
+      let attributes = &primitive.attributes;
+      for accessor in [attributes.position, attributes.normal,
+        attributes.tangent].into_iter().flatten() {
+        add_job(accessor, Bake::Vector)?;
+      }
+    }
+    for animation in self.animations.iter() {
+      for channel in animation.channels.iter() {
+        let aim = channel.target.node
+          .is_some_and(|node| aims.get(node as usize) == Some(&true));
+        let bake = match (&channel.target.path, aim) {
+          (AnimationPath::Translation, _) => Bake::Vector,
+          (AnimationPath::Rotation, false) => Bake::Vector,
+          (AnimationPath::Rotation, true) => Bake::Aim,
+          (AnimationPath::Scale, false) => Bake::Scale,
+          (AnimationPath::Scale, true) | (AnimationPath::Weights, _)
+            | (AnimationPath::Pointer, _) => continue,
+        };
+        let sampler = animation.samplers.get(channel.sampler as usize)
+          .ok_or(ErrorCode::Index)?;
+        add_job(sampler.output, bake)?;
+      }
+    }
+    for node in self.nodes.iter() {
+      for (name, bake) in [("TRANSLATION", Bake::Vector),
+        ("ROTATION", Bake::Vector), ("SCALE", Bake::Scale)] {
+        if let Some(accessor) = instancing::instance_attribute(node, name) {
+          add_job(accessor, bake)?;
+        }
+      }
+    }
+    for skin in self.skins.iter() {
+      if let Some(accessor) = skin.inverse_bind_matrices {
+        add_job(accessor, Bake::Matrix)?;
+      }
+    }
+    
+    // Everything is checked before anything is written. Each job keeps the
+    // buffer, start of its data, and stride
+    let mut located = Vec::with_capacity(jobs.len());
+    for (&index, &bake) in jobs.iter() {
+      let (accessor, view) = self.accessor_and_view(index)?;
+      let type_valid = match bake {
+        Bake::Vector | Bake::Scale => matches!(accessor.type_,
+          Type::VEC3 | Type::VEC4),
+        Bake::Aim => accessor.type_ == Type::VEC4,
+        Bake::Matrix => accessor.type_ == Type::MAT4,
+      };
+      if accessor.component_type != ComponentType::Float || !type_valid {
+        return Err(ErrorCode::Value);
+      }
+      let (_, stride) = accessor.element_bytes(buffers, view)?;
+      let start = view.byte_offset as usize + accessor.byte_offset as usize;
+      located.push((index, bake, view.buffer as usize, start, stride));
+    }
+    
+    for &(index, bake, buffer, start, stride) in located.iter() {
+      let bytes = &mut buffers[buffer];
+      // Only the first three components of a VEC4 change
+      let length = match bake {
+        Bake::Vector | Bake::Scale => 3,
+        Bake::Aim => 4,
+        Bake::Matrix => 16,
+      };
+      for element in 0..self.accessors[index as usize].count as usize {
+        let offset = start + element * stride;
+        let mut v = [0.0; 16];
+        let v = &mut v[..length];
+        for (k, component) in v.iter_mut().enumerate() {
+          let at = offset + 4 * k;
+          // .unwrap() acceptable here because element_bytes() checked the
+          // accessor's range lies within the buffer
+          *component = f32::from_le_bytes(bytes[at..at + 4].try_into()
+            .unwrap()) as f64;
+        }
+        
+        match bake {
+          Bake::Vector => v.copy_from_slice(&rotate(sign, [v[0], v[1],
+            v[2]])),
+          Bake::Scale => v.swap(1, 2),
+          Bake::Aim => v.copy_from_slice(&rotate_quaternion(sign, [v[0],
+            v[1], v[2], v[3]])),
+          Bake::Matrix => conjugate(sign, v),
+        }
+        for (k, component) in v.iter().enumerate() {
+          let at = offset + 4 * k;
+          bytes[at..at + 4].copy_from_slice(&(*component as f32)
+            .to_le_bytes());
+        }
+      }
+    }
+    
+    for &(index, _, _, _, _) in located.iter() {
+      let accessor = &self.accessors[index as usize];
+      if accessor.min.is_empty() && accessor.max.is_empty() {
+        continue;
+      }
+      let (min, max) = self.compute_bounds(index, buffers)?;
+      let accessor = &mut self.accessors[index as usize];
+      accessor.min = min;
+      accessor.max = max;
+    }
+    
+    for (node, aim) in self.nodes.iter_mut().zip(aims) {
+      let [x, y, z] = rotate(sign, [node.t.x, node.t.y, node.t.z]);
+      node.t = Translation { x, y, z };
+      
+      if aim {
+        let [x, y, z, w] = rotate_quaternion(sign, [node.r.x, node.r.y,
+          node.r.z, node.r.w]);
+        node.r = Rotation { x, y, z, w };
+        if let Some(m) = &mut node.matrix {
+          for c in 0..4 {
+            let v = rotate(sign, [m[4 * c], m[4 * c + 1], m[4 * c + 2]]);
+            m[4 * c..4 * c + 3].copy_from_slice(&v);
+          }
+        }
+        continue;
+      }
+      
+      let [x, y, z] = rotate(sign, [node.r.x, node.r.y, node.r.z]);
+      node.r = Rotation { x, y, z, w: node.r.w };
+      node.s = Scale { x: node.s.x, y: node.s.z, z: node.s.y };
+      if let Some(m) = &mut node.matrix {
+        conjugate(sign, m);
+      }
+    }
+    
+    Ok(())
+  }
+}
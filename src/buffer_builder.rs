@@ -0,0 +1,458 @@
+use crate::*;
+
+// Appends vertex and index data to one buffer, creating a buffer view and
+// accessor for each push. Views are 4-byte aligned, and by default get the
+// target glTF expects for what was pushed: ArrayBuffer for vertex attributes
+// and ElementArrayBuffer for indices (see TargetMode). The two kinds never
+// share a view
+pub struct BufferBuilder<'a> {
+  gltf: &'a mut GLTF,
+  buffer: u32,
+  bytes: &'a mut Vec<u8>,
+  // Set between begin_interleaved() and end_interleaved()
+  interleaved: Option<Interleaved>,
+  target_mode: TargetMode,
+  // See set_all_bounds()
+  all_bounds: bool,
+  // See set_debug_names()
+  debug_names: bool,
+  // Mesh and primitive number the next primitive is named after, where the
+  // mesh is the document's mesh count when it was last used
+  next_primitive: (usize, u32),
+}
+
+// Which buffer views BufferBuilder gives a target. The spec makes target
+// optional, and loaders differ: those that upload each view to the GPU as it
+// is (such as older CesiumJS) need it to pick the binding, while ones that
+// work from accessors (such as three.js) ignore it. Some readers and
+// pipelines reject or misread views whose target disagrees with how they
+// use the view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetMode {
+  // Every vertex and index view gets a target. Views of animation data never
+  // do, as the spec only allows targets for GPU buffers. The default
+  Always,
+  // No view gets a target
+  Never,
+  // Only views that could otherwise be mistaken for the other kind: index
+  // views, which must never be given a byteStride by tools guessing at
+  // vertex data, and interleaved vertex views. Views holding a single
+  // attribute are left without
+  Unambiguous,
+}
+
+// Attributes pushed since begin_interleaved(). Their accessors exist already,
+// but with no buffer view until end_interleaved() packs the data
+struct Interleaved {
+  accessors: Vec<u32>,
+  // Packed elements of each attribute
+  data: Vec<Vec<u8>>,
+}
+
+impl<'a> BufferBuilder<'a> {
+  // `bytes` is the binary contents of buffer `buffer`, which must already
+  // exist in `gltf`. Data is appended after anything already in it
+  pub fn new(gltf: &'a mut GLTF, buffer: u32, bytes: &'a mut Vec<u8>,
+  ) -> Result<Self, ErrorCode> {
+    if buffer as usize >= gltf.buffers.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    Ok(Self {
+      gltf,
+      buffer,
+      bytes,
+      interleaved: None,
+      target_mode: TargetMode::Always,
+      all_bounds: false,
+      debug_names: false,
+      next_primitive: (0, 0),
+    })
+  }
+  
+  // Adds a new buffer to `gltf` and returns a builder for it. `bytes`
+  // receives the buffer's contents, and is usually empty to begin with
+  pub fn with_new_buffer(gltf: &'a mut GLTF, bytes: &'a mut Vec<u8>) -> Self {
+    let mut buffer = Buffer::new();
+    buffer.byte_length = bytes.len() as u32;
+    gltf.buffers.push(buffer);
+    let buffer = gltf.buffers.len() as u32 - 1;
+    
+    Self {
+      gltf,
+      buffer,
+      bytes,
+      interleaved: None,
+      target_mode: TargetMode::Always,
+      all_bounds: false,
+      debug_names: false,
+      next_primitive: (0, 0),
+    }
+  }
+  
+  // Index of the buffer being written
+  pub fn buffer(&self) -> u32 {
+    self.buffer
+  }
+  
+  // Affects views pushed after this call
+  pub fn set_target_mode(&mut self, target_mode: TargetMode) {
+    self.target_mode = target_mode;
+  }
+  
+  // Whether accessors pushed after this call all get min and max, rather
+  // than only those glTF requires them on (positions and keyframe times).
+  // Bounds on other data are optional, but some tools use them, as for
+  // dequantizing or sizing textures of packed data. Off by default
+  pub fn set_all_bounds(&mut self, all_bounds: bool) {
+    self.all_bounds = all_bounds;
+  }
+  
+  // Whether primitives pushed after this call get their accessors and buffer
+  // views named after their role and owner, such as "POSITION_mesh0_prim1"
+  // or "indices_mesh0_prim1", for reading buffer layouts in the JSON. The
+  // mesh number is the index the mesh gets if pushed to gltf.meshes next,
+  // and primitives are numbered in the order pushed, starting over once a
+  // mesh has been added. Off by default, since the names only add size
+  pub fn set_debug_names(&mut self, debug_names: bool) {
+    self.debug_names = debug_names;
+  }
+  
+  // Names the accessors and buffer views of `primitive` as set_debug_names()
+  // describes, as primitive `index` of mesh `mesh`, if debug names are on.
+  // For primitives assembled from separate pushes
+  pub fn name_primitive(&mut self, primitive: &MeshPrimitive, mesh: usize,
+    index: u32,
+  ) {
+    if !self.debug_names {
+      return;
+    }
+    
+    let suffix = format!("mesh{}_prim{}", mesh, index);
+    let mut roles: Vec<(&str, u32)> = primitive.attributes.named()
+      .into_iter().filter_map(|(role, a)| a.map(|a| (role, a))).collect();
+    roles.extend(primitive.indices.map(|indices| ("indices", indices)));
+    
+    for (role, accessor) in roles {
+      let name = format!("{}_{}", role, suffix);
+      let accessors = &mut self.gltf.accessors;
+      let Some(accessor) = accessors.get_mut(accessor as usize) else {
+        continue;
+      };
+      accessor.name = name.clone();
+      if let Some(view) = accessor.buffer_view
+        .and_then(|view| self.gltf.buffer_views.get_mut(view as usize)) {
+        view.name = name;
+      }
+    }
+  }
+  
+  // Names `primitive` as the next primitive pushed, numbered as
+  // set_debug_names() describes
+  pub(crate) fn name_next_primitive(&mut self, primitive: &MeshPrimitive) {
+    let mesh = self.gltf.meshes.len();
+    if self.next_primitive.0 != mesh {
+      self.next_primitive = (mesh, 0);
+    }
+    let index = self.next_primitive.1;
+    self.next_primitive.1 += 1;
+    self.name_primitive(primitive, mesh, index);
+  }
+  
+  // The target a view holding `kind` of data gets under the current mode
+  fn target(&self, kind: Option<Target>, interleaved: bool) -> Option<Target> {
+    match (self.target_mode, kind) {
+      (TargetMode::Always, _) => kind,
+      (TargetMode::Never, _) => None,
+      (TargetMode::Unambiguous, Some(Target::ElementArrayBuffer)) => kind,
+      (TargetMode::Unambiguous, _) if interleaved => kind,
+      (TargetMode::Unambiguous, _) => None,
+    }
+  }
+  
+  // Float vertex attribute with N components per vertex (SCALAR for 1, VECN
+  // otherwise). Returns the new accessor's index
+  pub fn push_attributes<const N: usize>(&mut self, data: &[[f32; N]],
+  ) -> Result<u32, ErrorCode> {
+    self.push_f32(data, Some(Target::ArrayBuffer))
+  }
+  
+  // Keyframe times for an animation sampler's input, which glTF requires to
+  // have min and max. Returns the new accessor's index
+  pub fn push_times(&mut self, times: &[f32]) -> Result<u32, ErrorCode> {
+    let times: Vec<[f32; 1]> = times.iter().map(|&t| [t]).collect();
+    let accessor = self.push_f32(&times, None)?;
+    self.set_bounds(accessor, bounds(&times));
+    
+    Ok(accessor)
+  }
+  
+  // Keyframe values for an animation sampler's output. Unlike vertex data,
+  // these views get no target. Returns the new accessor's index
+  pub fn push_samples<const N: usize>(&mut self, samples: &[[f32; N]],
+  ) -> Result<u32, ErrorCode> {
+    self.push_f32(samples, None)
+  }
+  
+  fn push_f32<const N: usize>(&mut self, data: &[[f32; N]],
+    target: Option<Target>,
+  ) -> Result<u32, ErrorCode> {
+    let type_ = match N {
+      1 => Type::SCALAR,
+      2 => Type::VEC2,
+      3 => Type::VEC3,
+      4 => Type::VEC4,
+      _ => return Err(ErrorCode::Value),
+    };
+    
+    let packed: Vec<u8> = data.iter().flatten()
+      .flat_map(|x| x.to_le_bytes()).collect();
+    let view_target = self.target(target, false);
+    let buffer_view = match &mut self.interleaved {
+      None => Some(self.gltf.push_buffer_view(self.buffer, self.bytes,
+        &packed, None, view_target)?),
+      // Only vertex attributes can be interleaved
+      Some(_) if target != Some(Target::ArrayBuffer) => {
+        return Err(ErrorCode::Value);
+      },
+      Some(interleaved) => {
+        interleaved.accessors.push(self.gltf.accessors.len() as u32);
+        interleaved.data.push(packed);
+        None
+      },
+    };
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = buffer_view;
+    accessor.component_type = ComponentType::Float;
+    accessor.count = data.len() as u32;
+    accessor.type_ = type_;
+    self.gltf.accessors.push(accessor);
+    let accessor = self.gltf.accessors.len() as u32 - 1;
+    
+    if self.all_bounds {
+      self.set_bounds(accessor, bounds(data));
+    }
+    Ok(accessor)
+  }
+  
+  // Empty accessors are left without bounds, as there's nothing to bound
+  fn set_bounds(&mut self, accessor: u32,
+    bounds: Option<(Vec<f64>, Vec<f64>)>,
+  ) {
+    if let Some((min, max)) = bounds {
+      self.gltf.accessors[accessor as usize].min = min;
+      self.gltf.accessors[accessor as usize].max = max;
+    }
+  }
+  
+  // Like push_attributes(), but also fills in the min and max that glTF
+  // requires on POSITION accessors
+  pub fn push_positions(&mut self, positions: &[[f32; 3]],
+  ) -> Result<u32, ErrorCode> {
+    let accessor = self.push_attributes(positions)?;
+    self.set_bounds(accessor, bounds(positions));
+    Ok(accessor)
+  }
+  
+  // Like push_positions(), for positions known more precisely than f32 can
+  // store, as in large worlds. The data is stored as f32, but min and max are
+  // taken from the f64 positions (widened where needed to also cover the f32
+  // values), so culling isn't thrown off by rounding
+  pub fn push_positions_f64(&mut self, positions: &[[f64; 3]],
+  ) -> Result<u32, ErrorCode> {
+    let stored: Vec<[f32; 3]> = positions.iter()
+      .map(|p| p.map(|x| x as f32)).collect();
+    let accessor = self.push_attributes(&stored)?;
+    
+    let mut min = vec![f64::INFINITY; 3];
+    let mut max = vec![f64::NEG_INFINITY; 3];
+    for (position, stored) in positions.iter().zip(stored.iter()) {
+      for i in 0..3 {
+        min[i] = min[i].min(position[i]).min(stored[i] as f64);
+        max[i] = max[i].max(position[i]).max(stored[i] as f64);
+      }
+    }
+    if !positions.is_empty() {
+      self.set_bounds(accessor, Some((min, max)));
+    }
+    
+    Ok(accessor)
+  }
+  
+  // Index list, stored as 16-bit if every index fits and 32-bit otherwise.
+  // 65535 counts as not fitting, since some APIs reserve it for primitive
+  // restart. Returns the new accessor's index
+  pub fn push_indices(&mut self, indices: &[u32]) -> Result<u32, ErrorCode> {
+    if self.interleaved.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    
+    let (packed, component_type) = match indices.iter().max() {
+      Some(&max) if max >= 65535 => (indices.iter()
+        .flat_map(|&i| i.to_le_bytes()).collect::<Vec<u8>>(),
+        ComponentType::UnsignedInt),
+      _ => (indices.iter().flat_map(|&i| (i as u16).to_le_bytes()).collect(),
+        ComponentType::UnsignedShort),
+    };
+    let target = self.target(Some(Target::ElementArrayBuffer), false);
+    let buffer_view = self.gltf.push_buffer_view(self.buffer, self.bytes,
+      &packed, None, target)?;
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = component_type;
+    accessor.count = indices.len() as u32;
+    accessor.type_ = Type::SCALAR;
+    self.gltf.accessors.push(accessor);
+    let accessor = self.gltf.accessors.len() as u32 - 1;
+    
+    if self.all_bounds {
+      let min = indices.iter().min().map(|&i| vec![i as f64]);
+      let max = indices.iter().max().map(|&i| vec![i as f64]);
+      self.set_bounds(accessor, min.zip(max));
+    }
+    Ok(accessor)
+  }
+  
+  // Writes a whole primitive's geometry, returning a triangle primitive
+  // wired to the new accessors, for the caller to give a material and add to
+  // a mesh. Normals and UVs, if given, must have one entry per position, and
+  // indices, if given, must be in range. Fails without writing anything
+  // otherwise
+  pub fn push_primitive(&mut self, positions: &[[f32; 3]],
+    normals: Option<&[[f32; 3]]>, uvs: Option<&[[f32; 2]]>,
+    indices: Option<&[u32]>,
+  ) -> Result<MeshPrimitive, ErrorCode> {
+    let primitive = self.write_primitive(positions, normals, uvs, indices)?;
+    self.name_next_primitive(&primitive);
+    Ok(primitive)
+  }
+  
+  // push_primitive() without the naming, for callers that add more
+  // attributes first
+  pub(crate) fn write_primitive(&mut self, positions: &[[f32; 3]],
+    normals: Option<&[[f32; 3]]>, uvs: Option<&[[f32; 2]]>,
+    indices: Option<&[u32]>,
+  ) -> Result<MeshPrimitive, ErrorCode> {
+    if self.interleaved.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    if normals.is_some_and(|n| n.len() != positions.len())
+      || uvs.is_some_and(|uv| uv.len() != positions.len()) {
+      return Err(ErrorCode::Value);
+    }
+    if indices.is_some_and(|i| i.iter()
+      .any(|&index| index as usize >= positions.len())) {
+      return Err(ErrorCode::Index);
+    }
+    
+    let mut primitive = MeshPrimitive::new();
+    primitive.attributes.position = Some(self.push_positions(positions)?);
+    if let Some(normals) = normals {
+      primitive.attributes.normal = Some(self.push_attributes(normals)?);
+    }
+    if let Some(uvs) = uvs {
+      primitive.attributes.texcoord_0 = Some(self.push_attributes(uvs)?);
+    }
+    if let Some(indices) = indices {
+      primitive.indices = Some(self.push_indices(indices)?);
+    }
+    
+    Ok(primitive)
+  }
+  
+  // Starts a group of vertex attributes to be interleaved into one buffer
+  // view. Until end_interleaved(), push_attributes() and push_positions()
+  // add to the group, and other pushes fail
+  pub fn begin_interleaved(&mut self) -> Result<(), ErrorCode> {
+    if self.interleaved.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    
+    self.interleaved = Some(Interleaved {
+      accessors: Vec::new(),
+      data: Vec::new(),
+    });
+    Ok(())
+  }
+  
+  // Packs the attributes pushed since begin_interleaved() side by side in
+  // one strided buffer view, in the order they were pushed. The stride is
+  // the sum of their element sizes (each a multiple of 4, as all attributes
+  // here are floats). Fails if the group is empty, the attributes have
+  // different counts, or the stride would be over glTF's limit of 252 bytes.
+  // A failed group is discarded along with its accessors, so the document
+  // isn't left with accessors that have no data, and the builder is no
+  // longer interleaving. Returns the buffer view and the group's accessors
+  pub fn end_interleaved(&mut self) -> Result<(u32, Vec<u32>), ErrorCode> {
+    let group = self.interleaved.take().ok_or(ErrorCode::Value)?;
+    match self.pack_interleaved(&group) {
+      Ok(buffer_view) => Ok((buffer_view, group.accessors)),
+      Err(error) => {
+        // Nothing else can push accessors while a group is open, so the
+        // group's are the last ones
+        if let Some(&first) = group.accessors.first() {
+          self.gltf.accessors.truncate(first as usize);
+        }
+        Err(error)
+      },
+    }
+  }
+  
+  // Writes the group's buffer view and points its accessors into it.
+  // Changes nothing if it fails
+  fn pack_interleaved(&mut self, group: &Interleaved,
+  ) -> Result<u32, ErrorCode> {
+    let count = group.accessors.first()
+      .map_or(0, |&a| self.gltf.accessors[a as usize].count as usize);
+    if group.accessors.iter()
+      .any(|&a| self.gltf.accessors[a as usize].count as usize != count) {
+      return Err(ErrorCode::Value);
+    }
+    
+    let element_sizes: Vec<usize> = group.accessors.iter()
+      .map(|&a| self.gltf.accessors[a as usize].element_size()).collect();
+    let stride: usize = element_sizes.iter().sum();
+    if stride == 0 || stride > 252 {
+      return Err(ErrorCode::Value);
+    }
+    
+    let mut packed = Vec::with_capacity(stride * count);
+    for i in 0..count {
+      for (data, &size) in group.data.iter().zip(element_sizes.iter()) {
+        packed.extend_from_slice(&data[i * size..(i + 1) * size]);
+      }
+    }
+    let target = self.target(Some(Target::ArrayBuffer), true);
+    let buffer_view = self.gltf.push_buffer_view(self.buffer, self.bytes,
+      &packed, Some(stride as u32), target)?;
+    
+    let mut offset = 0;
+    for (&a, &size) in group.accessors.iter().zip(element_sizes.iter()) {
+      let accessor = &mut self.gltf.accessors[a as usize];
+      accessor.buffer_view = Some(buffer_view);
+      accessor.byte_offset = offset as u32;
+      offset += size;
+    }
+    
+    Ok(buffer_view)
+  }
+}
+
+// Per-component min and max of `data`, or None if it's empty
+fn bounds<const N: usize>(data: &[[f32; N]]) -> Option<(Vec<f64>, Vec<f64>)> {
+  if data.is_empty() {
+    return None;
+  }
+  
+  let mut min = vec![f64::INFINITY; N];
+  let mut max = vec![f64::NEG_INFINITY; N];
+  for element in data {
+    for i in 0..N {
+      min[i] = min[i].min(element[i] as f64);
+      max[i] = max[i].max(element[i] as f64);
+    }
+  }
+  Some((min, max))
+}
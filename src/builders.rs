@@ -0,0 +1,375 @@
+use crate::*;
+
+// Fluent wrappers around the glTF structs, for building them in one
+// expression. Anything not set keeps the value from the struct's new()
+
+pub struct NodeBuilder {
+  node: Node,
+}
+
+impl NodeBuilder {
+  pub fn new() -> Self {
+    Self { node: Node::new() }
+  }
+  
+  pub fn name(mut self, name: &str) -> Self {
+    self.node.name = String::from(name);
+    self
+  }
+  
+  pub fn mesh(mut self, mesh: u32) -> Self {
+    self.node.mesh = Some(mesh);
+    self
+  }
+  
+  pub fn camera(mut self, camera: u32) -> Self {
+    self.node.camera = Some(camera);
+    self
+  }
+  
+  pub fn translation(mut self, [x, y, z]: [f64; 3]) -> Self {
+    self.node.t = Translation { x, y, z };
+    self
+  }
+  
+  // Quaternion, in glTF's x, y, z, w order
+  pub fn rotation(mut self, [x, y, z, w]: [f64; 4]) -> Self {
+    self.node.r = Rotation { x, y, z, w };
+    self
+  }
+  
+  pub fn scale(mut self, [x, y, z]: [f64; 3]) -> Self {
+    self.node.s = Scale { x, y, z };
+    self
+  }
+  
+  // Replaces any translation, rotation, or scale set so far. See
+  // Node::set_matrix()
+  pub fn matrix(mut self, matrix: [f64; 16]) -> Self {
+    self.node.set_matrix(matrix);
+    self
+  }
+  
+  pub fn child(mut self, child: u32) -> Self {
+    self.node.children.push(child);
+    self
+  }
+  
+  pub fn build(self) -> Node {
+    self.node
+  }
+}
+
+pub struct MaterialBuilder {
+  material: Material,
+}
+
+impl MaterialBuilder {
+  pub fn new() -> Self {
+    Self { material: Material::new() }
+  }
+  
+  pub fn name(mut self, name: &str) -> Self {
+    self.material.name = String::from(name);
+    self
+  }
+  
+  pub fn base_color(mut self, [r, g, b, a]: [f64; 4]) -> Self {
+    self.material.pbr_metallic_roughness.base_color_factor =
+      Color4 { r, g, b, a };
+    self
+  }
+  
+  pub fn metallic(mut self, metallic: f64) -> Self {
+    self.material.pbr_metallic_roughness.metallic_factor = metallic;
+    self
+  }
+  
+  pub fn roughness(mut self, roughness: f64) -> Self {
+    self.material.pbr_metallic_roughness.roughness_factor = roughness;
+    self
+  }
+  
+  pub fn emissive(mut self, emissive: [f64; 3]) -> Self {
+    self.material.emissive_factor = emissive;
+    self
+  }
+  
+  pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+    self.material.alpha_mode = alpha_mode;
+    self
+  }
+  
+  pub fn alpha_cutoff(mut self, alpha_cutoff: f64) -> Self {
+    self.material.alpha_cutoff = alpha_cutoff;
+    self
+  }
+  
+  pub fn double_sided(mut self, double_sided: bool) -> Self {
+    self.material.double_sided = double_sided;
+    self
+  }
+  
+  pub fn base_color_texture(mut self, texture: u32) -> Self {
+    self.material.pbr_metallic_roughness.base_color_texture =
+      Some(TextureInfo::new(texture));
+    self
+  }
+  
+  pub fn metallic_roughness_texture(mut self, texture: u32) -> Self {
+    self.material.pbr_metallic_roughness.metallic_roughness_texture =
+      Some(TextureInfo::new(texture));
+    self
+  }
+  
+  pub fn normal_texture(mut self, texture: u32, scale: f64) -> Self {
+    let mut info = NormalTextureInfo::new(texture);
+    info.scale = scale;
+    self.material.normal_texture = Some(info);
+    self
+  }
+  
+  pub fn occlusion_texture(mut self, texture: u32, strength: f64) -> Self {
+    let mut info = OcclusionTextureInfo::new(texture);
+    info.strength = strength;
+    self.material.occlusion_texture = Some(info);
+    self
+  }
+  
+  pub fn emissive_texture(mut self, texture: u32) -> Self {
+    self.material.emissive_texture = Some(TextureInfo::new(texture));
+    self
+  }
+  
+  // Attaches `extension`, replacing any of the same kind. Fails if the
+  // extension's values are out of range. Add the material with
+  // GLTF::add_material() or intern_material() to list the extension in
+  // extensionsUsed
+  pub fn extension<E: Extension>(mut self, extension: &E,
+  ) -> Result<Self, ErrorCode> {
+    insert_extension(&mut self.material.extensions, extension)?;
+    Ok(self)
+  }
+  
+  pub fn unlit(self) -> Self {
+    // .unwrap() acceptable here because Unlit has no values to fail validation
+    self.extension(&khr_materials::Unlit::new()).unwrap()
+  }
+  
+  pub fn emissive_strength(self, emissive_strength: f64,
+  ) -> Result<Self, ErrorCode> {
+    self.extension(&khr_materials::EmissiveStrength { emissive_strength })
+  }
+  
+  pub fn ior(self, ior: f64) -> Result<Self, ErrorCode> {
+    self.extension(&khr_materials::Ior { ior })
+  }
+  
+  pub fn transmission(self, transmission_factor: f64,
+  ) -> Result<Self, ErrorCode> {
+    self.extension(&khr_materials::Transmission {
+      transmission_factor,
+      transmission_texture: None,
+    })
+  }
+  
+  pub fn build(self) -> Material {
+    self.material
+  }
+}
+
+// Vertex data and indices for one primitive, waiting for
+// MeshBuilder::build_shared() to pack it
+struct Submesh {
+  vertices: geometry::Vertices,
+  indices: Vec<u32>,
+  material: Option<u32>,
+}
+
+pub struct MeshBuilder {
+  mesh: Mesh,
+  submeshes: Vec<Submesh>,
+  debug_names: bool,
+}
+
+impl MeshBuilder {
+  pub fn new() -> Self {
+    Self { mesh: Mesh::new(), submeshes: Vec::new(), debug_names: false }
+  }
+  
+  pub fn name(mut self, name: &str) -> Self {
+    self.mesh.name = String::from(name);
+    self
+  }
+  
+  pub fn primitive(mut self, primitive: MeshPrimitive) -> Self {
+    self.mesh.primitives.push(primitive);
+    self
+  }
+  
+  // Default morph target weight
+  pub fn weight(mut self, weight: f64) -> Self {
+    self.mesh.weights.push(weight);
+    self
+  }
+  
+  // Adds a triangle list primitive from raw vertex data, to be packed along
+  // with the other submeshes by build_shared(). Fails if the indices don't
+  // form a triangle list into the positions, or a stream's length doesn't
+  // match the positions
+  pub fn add_submesh(mut self, positions: &[[f32; 3]],
+    normals: Option<&[[f32; 3]]>, uvs: Option<&[[f32; 2]]>, indices: &[u32],
+    material: Option<u32>,
+  ) -> Result<Self, ErrorCode> {
+    geometry::check_triangle_list(indices, positions.len(),
+      &[normals.map(|n| n.len()), uvs.map(|u| u.len())])?;
+    
+    self.submeshes.push(Submesh {
+      vertices: geometry::Vertices {
+        positions: positions.to_vec(),
+        normals: normals.map(|n| n.to_vec()),
+        uvs: uvs.map(|u| u.to_vec()),
+      },
+      indices: indices.to_vec(),
+      material,
+    });
+    Ok(self)
+  }
+  
+  // Makes build_shared() name each accessor and buffer view it creates after
+  // its role and owner, through BufferBuilder::set_debug_names(). Primitives
+  // added with primitive() already have their accessors, and build() writes
+  // none, so neither is named. Off by default
+  pub fn debug_names(mut self, debug_names: bool) -> Self {
+    self.debug_names = debug_names;
+    self
+  }
+  
+  pub fn build(self) -> Mesh {
+    self.mesh
+  }
+  
+  // Like build(), but also packs every submesh into one new buffer added to
+  // `gltf`, with a buffer view and accessor per attribute and per index list.
+  // Returns the mesh and the new buffer's contents. Indices are 16-bit for
+  // submeshes small enough, otherwise 32-bit (see BufferBuilder). Fails
+  // without changing `gltf` if the submeshes hold no data at all, since glTF
+  // doesn't allow empty buffers
+  pub fn build_shared(mut self, gltf: &mut GLTF,
+  ) -> Result<(Mesh, Vec<u8>), ErrorCode> {
+    if self.submeshes.iter().all(|submesh| {
+      submesh.vertices.positions.is_empty() && submesh.indices.is_empty()
+    }) {
+      return Err(ErrorCode::Value);
+    }
+    
+    let mut bytes = Vec::new();
+    
+    let mesh_index = gltf.meshes.len();
+    let mut builder = BufferBuilder::with_new_buffer(gltf, &mut bytes);
+    builder.set_debug_names(self.debug_names);
+    
+    for submesh in self.submeshes {
+      let vertices = &submesh.vertices;
+      let mut primitive = MeshPrimitive::new();
+      primitive.material = submesh.material;
+      
+      primitive.attributes.position = Some(builder.push_positions(
+        &vertices.positions)?);
+      if let Some(normals) = &vertices.normals {
+        primitive.attributes.normal = Some(builder.push_attributes(normals)?);
+      }
+      if let Some(uvs) = &vertices.uvs {
+        primitive.attributes.texcoord_0 = Some(builder.push_attributes(uvs)?);
+      }
+      primitive.indices = Some(builder.push_indices(&submesh.indices)?);
+      
+      builder.name_primitive(&primitive, mesh_index,
+        self.mesh.primitives.len() as u32);
+      self.mesh.primitives.push(primitive);
+    }
+    
+    Ok((self.mesh, bytes))
+  }
+}
+
+impl GLTF {
+  // A complete document holding one triangle list mesh, on one node, in the
+  // default scene. Returns the document and its one buffer's contents. Fails
+  // as MeshBuilder::add_submesh() does on mismatched data, and on empty data
+  // as build_shared() does
+  pub fn from_mesh(positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>,
+    uvs: Option<&[[f32; 2]]>, indices: &[u32], material: Option<Material>,
+  ) -> Result<(GLTF, Vec<u8>), ErrorCode> {
+    let mut gltf = GLTF::new();
+    
+    let material = material.map(|material| gltf.add_material(material));
+    
+    let (mesh, bytes) = MeshBuilder::new()
+      .add_submesh(positions, normals, uvs, indices, material)?
+      .build_shared(&mut gltf)?;
+    gltf.meshes.push(mesh);
+    
+    gltf.nodes.push(NodeBuilder::new().mesh(0).build());
+    let mut scene = Scene::new();
+    scene.nodes.push(0);
+    gltf.scenes.push(scene);
+    gltf.scene = Some(0);
+    
+    Ok((gltf, bytes))
+  }
+  
+  // Packs one triangle list, whose triangles use different materials, as a
+  // mesh with a primitive per material. The vertex attributes go in once and
+  // are shared by every primitive, which each get their own index list.
+  // `per_triangle_material` gives each triangle's material, and primitives
+  // come in order of material index, keeping their triangles' order. Data
+  // goes in a new buffer added to the document, as with
+  // MeshBuilder::build_shared(), and the mesh isn't added. Fails as
+  // MeshBuilder::add_submesh() does on mismatched data, if there isn't one
+  // material per triangle, or if a material doesn't exist
+  pub fn split_by_material(&mut self, positions: &[[f32; 3]],
+    normals: Option<&[[f32; 3]]>, uvs: Option<&[[f32; 2]]>, indices: &[u32],
+    per_triangle_material: &[u32],
+  ) -> Result<(Mesh, Vec<u8>), ErrorCode> {
+    geometry::check_triangle_list(indices, positions.len(),
+      &[normals.map(|n| n.len()), uvs.map(|u| u.len())])?;
+    if per_triangle_material.len() != indices.len() / 3 {
+      return Err(ErrorCode::Value);
+    }
+    if per_triangle_material.iter()
+      .any(|&material| material as usize >= self.materials.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    let mut by_material = std::collections::BTreeMap::new();
+    for (triangle, &material) in indices.chunks_exact(3)
+      .zip(per_triangle_material) {
+      by_material.entry(material).or_insert_with(Vec::new)
+        .extend_from_slice(triangle);
+    }
+    
+    let mut bytes = Vec::new();
+    let mut builder = BufferBuilder::with_new_buffer(self, &mut bytes);
+    
+    let mut attributes = Attributes::new();
+    attributes.position = Some(builder.push_positions(positions)?);
+    if let Some(normals) = normals {
+      attributes.normal = Some(builder.push_attributes(normals)?);
+    }
+    if let Some(uvs) = uvs {
+      attributes.texcoord_0 = Some(builder.push_attributes(uvs)?);
+    }
+    
+    let mut mesh = Mesh::new();
+    for (material, indices) in by_material {
+      let mut primitive = MeshPrimitive::new();
+      primitive.attributes = attributes.clone();
+      primitive.indices = Some(builder.push_indices(&indices)?);
+      primitive.material = Some(material);
+      mesh.primitives.push(primitive);
+    }
+    
+    Ok((mesh, bytes))
+  }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraType {
+  Perspective,
+  Orthographic,
+}
+
+// A viewpoint, placed by the nodes that use it. Cameras look down their
+// node's -Z axis, with +Y up
+#[derive(Clone, serde::Serialize)]
+pub struct Camera {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // Must match whichever of perspective or orthographic is set
+  #[serde(rename = "type")]
+  pub type_: CameraType,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub perspective: Option<CameraPerspective>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub orthographic: Option<CameraOrthographic>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Camera {
+  // Perspective camera with vertical field of view `yfov` (in radians). With
+  // no `zfar`, the far plane is at infinity
+  pub fn perspective(yfov: f64, znear: f64, zfar: Option<f64>) -> Self {
+    Self {
+      name: String::from(""),
+      type_: CameraType::Perspective,
+      perspective: Some(CameraPerspective {
+        aspect_ratio: None,
+        yfov,
+        zfar,
+        znear,
+      }),
+      orthographic: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Orthographic camera showing `xmag` either side of center horizontally,
+  // and `ymag` vertically
+  pub fn orthographic(xmag: f64, ymag: f64, znear: f64, zfar: f64) -> Self {
+    Self {
+      name: String::from(""),
+      type_: CameraType::Orthographic,
+      perspective: None,
+      orthographic: Some(CameraOrthographic { xmag, ymag, zfar, znear }),
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // The projection must be the one named by type, with znear > 0 (or >= 0
+  // for orthographic) and zfar beyond it, a positive field of view and
+  // aspect ratio, and nonzero magnifications
+  pub fn validate(&self) -> Result<(), ErrorCode> {
+    let valid = match (self.type_, &self.perspective, &self.orthographic) {
+      (CameraType::Perspective, Some(p), None) => p.yfov > 0.0
+        && p.znear > 0.0
+        && p.zfar.is_none_or(|zfar| zfar > p.znear)
+        && p.aspect_ratio.is_none_or(|ratio| ratio > 0.0),
+      (CameraType::Orthographic, None, Some(o)) => o.xmag != 0.0
+        && o.ymag != 0.0 && o.znear >= 0.0 && o.zfar > o.znear,
+      _ => false,
+    };
+    
+    match valid {
+      true => Ok(()),
+      false => Err(ErrorCode::Value),
+    }
+  }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CameraPerspective {
+  // Width over height. Viewers use the viewport's if not given
+  #[serde(rename = "aspectRatio")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub aspect_ratio: Option<f64>,
+  
+  // Vertical field of view, in radians
+  pub yfov: f64,
+  
+  // Infinite if not given
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub zfar: Option<f64>,
+  
+  pub znear: f64,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CameraOrthographic {
+  pub xmag: f64,
+  pub ymag: f64,
+  pub zfar: f64,
+  pub znear: f64,
+}
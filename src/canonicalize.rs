@@ -0,0 +1,306 @@
+use crate::*;
+
+impl GLTF {
+  // Renumbers accessors, buffer views, and buffers in order of first use, so
+  // regenerating the same model gives the same file however its data was added.
+  // Use is found by walking each scene's nodes depth first, then any nodes and
+  // meshes not in a scene, then animations, skins, instancing nodes, and
+  // images. Elements nothing uses go last, in their original order. Every
+  // reference is updated, and buffer data doesn't move within its buffer.
+  // Returns the old index of each buffer in its new order, so the caller can
+  // reorder the buffers' binary contents to match. Nothing is changed if any
+  // reference points at an element that doesn't exist, except that scene root
+  // lists are always sorted (see sort_scene_roots())
+  pub fn canonicalize(&mut self) -> Result<Vec<u32>, ErrorCode> {
+    // Sorted first so the walk below doesn't depend on root order
+    self.sort_scene_roots();
+    
+    let mut accessor_map = Remap::new(self.accessors.len());
+    let mut buffer_view_map = Remap::new(self.buffer_views.len());
+    let mut buffer_map = Remap::new(self.buffers.len());
+    
+    let mut meshes = Vec::new();
+    for node in self.node_order()? {
+      meshes.extend(self.nodes[node as usize].mesh);
+    }
+    meshes.extend(0..self.meshes.len() as u32);
+    for mesh in meshes {
+      let mesh = self.meshes.get(mesh as usize).ok_or(ErrorCode::Index)?;
+      for primitive in mesh.primitives.iter() {
+        for (_, accessor) in primitive.attributes.named() {
+          accessor_map.get_option(accessor)?;
+        }
+        accessor_map.get_option(primitive.indices)?;
+      }
+    }
+    for sampler in self.animations.iter().flat_map(|a| a.samplers.iter()) {
+      accessor_map.get(sampler.input)?;
+      accessor_map.get(sampler.output)?;
+    }
+    for skin in self.skins.iter() {
+      accessor_map.get_option(skin.inverse_bind_matrices)?;
+    }
+    for node in self.nodes.iter() {
+      for accessor in instancing::instance_accessors(node) {
+        accessor_map.get(accessor)?;
+      }
+    }
+    for i in 0..self.accessors.len() as u32 {
+      accessor_map.get(i)?;
+    }
+    
+    for &accessor in accessor_map.order.iter() {
+      let accessor = &self.accessors[accessor as usize];
+      buffer_view_map.get_option(accessor.buffer_view)?;
+      for view in accessor.sparse.iter().flat_map(|s| s.buffer_views()) {
+        buffer_view_map.get(view)?;
+      }
+    }
+    for image in self.images.iter() {
+      buffer_view_map.get_option(image.buffer_view)?;
+    }
+    for i in 0..self.buffer_views.len() as u32 {
+      buffer_view_map.get(i)?;
+    }
+    
+    for &buffer_view in buffer_view_map.order.iter() {
+      buffer_map.get(self.buffer_views[buffer_view as usize].buffer)?;
+    }
+    for i in 0..self.buffers.len() as u32 {
+      buffer_map.get(i)?;
+    }
+    
+    // Every reference has been mapped by now, so .get() can't fail below
+    for mesh in self.meshes.iter_mut() {
+      for primitive in mesh.primitives.iter_mut() {
+        for accessor in primitive.attributes.accessors_mut() {
+          *accessor = accessor_map.get_option(*accessor)?;
+        }
+        primitive.indices = accessor_map.get_option(primitive.indices)?;
+      }
+    }
+    for sampler in self.animations.iter_mut()
+      .flat_map(|a| a.samplers.iter_mut()) {
+      sampler.input = accessor_map.get(sampler.input)?;
+      sampler.output = accessor_map.get(sampler.output)?;
+    }
+    for skin in self.skins.iter_mut() {
+      skin.inverse_bind_matrices = accessor_map
+        .get_option(skin.inverse_bind_matrices)?;
+    }
+    for node in self.nodes.iter_mut() {
+      instancing::map_instance_accessors(node,
+        |accessor| accessor_map.get(accessor))?;
+    }
+    for image in self.images.iter_mut() {
+      image.buffer_view = buffer_view_map.get_option(image.buffer_view)?;
+    }
+    
+    let mut accessors = Vec::with_capacity(self.accessors.len());
+    for &index in accessor_map.order.iter() {
+      let mut accessor = self.accessors[index as usize].clone();
+      accessor.buffer_view = buffer_view_map.get_option(accessor.buffer_view)?;
+      for view in accessor.sparse.iter_mut()
+        .flat_map(|sparse| sparse.buffer_views_mut()) {
+        *view = buffer_view_map.get(*view)?;
+      }
+      accessors.push(accessor);
+    }
+    self.accessors = accessors;
+    
+    let mut buffer_views = Vec::with_capacity(self.buffer_views.len());
+    for &index in buffer_view_map.order.iter() {
+      let mut buffer_view = self.buffer_views[index as usize].clone();
+      buffer_view.buffer = buffer_map.get(buffer_view.buffer)?;
+      buffer_views.push(buffer_view);
+    }
+    self.buffer_views = buffer_views;
+    
+    self.buffers = buffer_map.order.iter()
+      .map(|&index| self.buffers[index as usize].clone()).collect();
+    
+    Ok(buffer_map.order)
+  }
+  
+  // Merges accessors that read exactly the same bytes the same way: same
+  // buffer, start, stride, component type, normalization, type, and count,
+  // even through different buffer views. Every reference is pointed at the
+  // first of each set, which takes the others' min and max if it has none,
+  // and the rest are removed. Buffer views left unused are kept. Sparse
+  // accessors, and those resolve_accessor() can't follow, are left alone.
+  // Returns how many were removed
+  pub fn dedup_accessors(&mut self) -> u32 {
+    let mut first = std::collections::HashMap::new();
+    let mut merged_into: Vec<u32> = (0..self.accessors.len() as u32).collect();
+    for resolved in self.iter_resolved_accessors() {
+      let accessor = resolved.accessor;
+      if accessor.sparse.is_some() {
+        continue;
+      }
+      let key = (resolved.buffer_index, resolved.byte_range.start,
+        resolved.stride, accessor.component_type.clone(), accessor.normalized,
+        accessor.type_.clone(), accessor.count);
+      merged_into[resolved.index as usize] = *first.entry(key)
+        .or_insert(resolved.index);
+    }
+    
+    let mut new_index = vec![0; self.accessors.len()];
+    let mut kept = 0;
+    for i in 0..self.accessors.len() {
+      let target = merged_into[i] as usize;
+      if target == i {
+        new_index[i] = kept;
+        kept += 1;
+        continue;
+      }
+      
+      new_index[i] = new_index[target];
+      if self.accessors[target].min.is_empty()
+        && self.accessors[target].max.is_empty() {
+        self.accessors[target].min = self.accessors[i].min.clone();
+        self.accessors[target].max = self.accessors[i].max.clone();
+      }
+    }
+    
+    // References to accessors that don't exist are left as they are
+    let map = |index: u32| *new_index.get(index as usize).unwrap_or(&index);
+    for primitive in self.meshes.iter_mut()
+      .flat_map(|m| m.primitives.iter_mut()) {
+      for accessor in primitive.attributes.accessors_mut() {
+        *accessor = accessor.map(map);
+      }
+      primitive.indices = primitive.indices.map(map);
+    }
+    for sampler in self.animations.iter_mut()
+      .flat_map(|a| a.samplers.iter_mut()) {
+      sampler.input = map(sampler.input);
+      sampler.output = map(sampler.output);
+    }
+    for skin in self.skins.iter_mut() {
+      skin.inverse_bind_matrices = skin.inverse_bind_matrices.map(map);
+    }
+    for node in self.nodes.iter_mut() {
+      // .unwrap() acceptable here because the mapping can't fail
+      instancing::map_instance_accessors(node, |a| Ok(map(a))).unwrap();
+    }
+    
+    let removed = self.accessors.len() as u32 - kept;
+    let mut i = 0;
+    self.accessors.retain(|_| {
+      i += 1;
+      merged_into[i - 1] as usize == i - 1
+    });
+    removed
+  }
+  
+  // Points buffer views in buffer `buffer` whose contents are byte for byte
+  // the same at a single copy, by moving their byte_offset onto the first
+  // such view. Each view keeps its own stride, target, and accessors. `bytes`
+  // is the buffer's binary contents. With `compact`, the buffer is then
+  // rebuilt with only the bytes some view covers, in their original order
+  // (overlapping views stay overlapped), and byte_length updated. Alignment
+  // is kept by only sharing between views at the same offset modulo 4, and
+  // by keeping each piece's offset modulo 4 when compacting. Fails without
+  // changing anything if a view runs past the end of `bytes`. Returns how
+  // many views were repointed
+  pub fn dedup_buffer_data(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    compact: bool,
+  ) -> Result<u32, ErrorCode> {
+    let buffer_entry = self.buffers.get_mut(buffer as usize)
+      .ok_or(ErrorCode::Index)?;
+    let views: Vec<usize> = (0..self.buffer_views.len())
+      .filter(|&i| self.buffer_views[i].buffer == buffer).collect();
+    for &i in views.iter() {
+      let view = &self.buffer_views[i];
+      if view.byte_offset as usize + view.byte_length as usize > bytes.len() {
+        return Err(ErrorCode::Index);
+      }
+    }
+    
+    let mut first = std::collections::HashMap::new();
+    let mut repointed = 0;
+    for &i in views.iter() {
+      let view = &self.buffer_views[i];
+      let start = view.byte_offset as usize;
+      let content = &bytes[start..start + view.byte_length as usize];
+      let shared = *first.entry((content, start % 4)).or_insert(start);
+      if shared != start {
+        self.buffer_views[i].byte_offset = shared as u32;
+        repointed += 1;
+      }
+    }
+    
+    if compact {
+      // Ranges covered by views, merged where they overlap, each with the
+      // views inside it
+      let mut by_start = views.clone();
+      by_start.sort_by_key(|&i| self.buffer_views[i].byte_offset);
+      let mut ranges: Vec<(usize, usize, Vec<usize>)> = Vec::new();
+      for i in by_start {
+        let view = &self.buffer_views[i];
+        let start = view.byte_offset as usize;
+        let end = start + view.byte_length as usize;
+        match ranges.last_mut() {
+          Some(range) if start < range.1 => {
+            range.1 = range.1.max(end);
+            range.2.push(i);
+          },
+          _ => ranges.push((start, end, vec![i])),
+        }
+      }
+      
+      let mut compacted = Vec::with_capacity(bytes.len());
+      for (start, end, range_views) in ranges {
+        // Keeps each range at the same offset modulo 4, so data aligned to
+        // its component size stays aligned
+        while compacted.len() % 4 != start % 4 {
+          compacted.push(0);
+        }
+        let new_start = compacted.len();
+        compacted.extend_from_slice(&bytes[start..end]);
+        for i in range_views {
+          let view = &mut self.buffer_views[i];
+          view.byte_offset = (view.byte_offset as usize - start + new_start)
+            as u32;
+        }
+      }
+      
+      *bytes = compacted;
+      buffer_entry.byte_length = bytes.len() as u32;
+    }
+    
+    Ok(repointed)
+  }
+  
+  // Sorts each scene's root nodes by index. The spec gives no meaning to
+  // their order, so this changes nothing but the written file, which then
+  // doesn't depend on the order roots were added in
+  pub fn sort_scene_roots(&mut self) {
+    for scene in self.scenes.iter_mut() {
+      scene.nodes.sort_unstable();
+    }
+  }
+  
+  // Every node once: each scene's nodes depth first, in scene order, then
+  // nodes outside any scene in index order
+  fn node_order(&self) -> Result<Vec<u32>, ErrorCode> {
+    let mut node_map = Remap::new(self.nodes.len());
+    
+    for scene in self.scenes.iter() {
+      let mut stack: Vec<u32> = scene.nodes.iter().rev().copied().collect();
+      while let Some(node) = stack.pop() {
+        if node_map.map.get(node as usize) == Some(&None) {
+          node_map.get(node)?;
+          stack.extend(self.nodes[node as usize].children.iter().rev());
+        } else if node as usize >= self.nodes.len() {
+          return Err(ErrorCode::Index);
+        }
+      }
+    }
+    
+    for i in 0..self.nodes.len() as u32 {
+      node_map.get(i)?;
+    }
+    Ok(node_map.order)
+  }
+}
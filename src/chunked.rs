@@ -0,0 +1,280 @@
+// Output in fixed-size pieces, for hosts that can't afford the whole .gltf in
+// WebAssembly memory at once. The document is kept unserialized, and
+// serialized lazily one top-level array element at a time, through a writer
+// that fills the current chunk and holds back whatever doesn't fit. Once the
+// chunk is full, serialization stops until the next chunk is asked for. So
+// memory stays at the document plus one chunk and one element, and the
+// output matches write_gltf()'s byte for byte
+
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+use crate::{ErrorCode, GLTF, POINTER, SIZE};
+
+// One top-level member of the JSON: a single value, or an array written an
+// element at a time
+struct Section {
+  key: &'static str,
+  // None for single values
+  count: Option<usize>,
+  write: fn(&GLTF, usize, &mut ChunkWriter) -> serde_json::Result<()>,
+}
+
+fn pretty<T: serde::Serialize>(writer: &mut ChunkWriter, value: &T,
+) -> serde_json::Result<()> {
+  serde_json::ser::to_writer_pretty(writer, value)
+}
+
+// GLTF's members, in the order its Serialize derive writes them and skipping
+// the same empty ones. The chunked output test catches any drift
+fn sections(gltf: &GLTF) -> Vec<Section> {
+  fn single(key: &'static str,
+    write: fn(&GLTF, usize, &mut ChunkWriter) -> serde_json::Result<()>,
+  ) -> Section {
+    Section { key, count: None, write }
+  }
+  fn array(key: &'static str, count: usize,
+    write: fn(&GLTF, usize, &mut ChunkWriter) -> serde_json::Result<()>,
+  ) -> Section {
+    Section { key, count: Some(count), write }
+  }
+  
+  let mut sections = vec![single("asset", |g, _, w| pretty(w, &g.asset))];
+  if gltf.scene.is_some() {
+    sections.push(single("scene", |g, _, w| pretty(w, &g.scene)));
+  }
+  sections.extend([
+    array("scenes", gltf.scenes.len(), |g, i, w| pretty(w, &g.scenes[i])),
+    array("nodes", gltf.nodes.len(), |g, i, w| pretty(w, &g.nodes[i])),
+    array("materials", gltf.materials.len(),
+      |g, i, w| pretty(w, &g.materials[i])),
+    array("meshes", gltf.meshes.len(), |g, i, w| pretty(w, &g.meshes[i])),
+    array("accessors", gltf.accessors.len(),
+      |g, i, w| pretty(w, &g.accessors[i])),
+    array("bufferViews", gltf.buffer_views.len(),
+      |g, i, w| pretty(w, &g.buffer_views[i])),
+    array("buffers", gltf.buffers.len(), |g, i, w| pretty(w, &g.buffers[i])),
+    array("animations", gltf.animations.len(),
+      |g, i, w| pretty(w, &g.animations[i])),
+    array("images", gltf.images.len(), |g, i, w| pretty(w, &g.images[i])),
+    array("textures", gltf.textures.len(),
+      |g, i, w| pretty(w, &g.textures[i])),
+    array("samplers", gltf.samplers.len(),
+      |g, i, w| pretty(w, &g.samplers[i])),
+    array("cameras", gltf.cameras.len(), |g, i, w| pretty(w, &g.cameras[i])),
+    array("skins", gltf.skins.len(), |g, i, w| pretty(w, &g.skins[i])),
+    array("extensionsUsed", gltf.extensions_used.len(),
+      |g, i, w| pretty(w, &g.extensions_used[i])),
+    array("extensionsRequired", gltf.extensions_required.len(),
+      |g, i, w| pretty(w, &g.extensions_required[i])),
+  ]);
+  sections.retain(|section| section.count != Some(0));
+  if gltf.extensions.is_some() {
+    sections.push(single("extensions", |g, _, w| pretty(w, &g.extensions)));
+  }
+  if gltf.extras.is_some() {
+    sections.push(single("extras", |g, _, w| pretty(w, &g.extras)));
+  }
+  sections
+}
+
+// Where serialization has got to. Each step writes one element (or single
+// value), so serialization can stop between any two
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+  Open,
+  // Element `item` of section `section`, or its value if it's single
+  Item { section: usize, item: usize },
+  Close,
+  Done,
+}
+
+// Writes into the current chunk until it's full, then holds back the rest
+// for the next. A value serialized on its own is indented to where it goes
+// in the document by adding `indent` after each newline, which is safe as
+// JSON strings never hold raw newlines
+struct ChunkWriter<'a> {
+  chunk: &'a mut Vec<u8>,
+  limit: usize,
+  held: &'a mut Vec<u8>,
+  indent: &'static str,
+}
+
+impl ChunkWriter<'_> {
+  fn put(&mut self, bytes: &[u8]) {
+    let room = self.limit.saturating_sub(self.chunk.len());
+    let taken = match self.held.is_empty() {
+      true => room.min(bytes.len()),
+      false => 0,
+    };
+    self.chunk.extend_from_slice(&bytes[..taken]);
+    self.held.extend_from_slice(&bytes[taken..]);
+  }
+}
+
+impl std::io::Write for ChunkWriter<'_> {
+  fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
+    let mut lines = buf.split(|&b| b == b'\n');
+    if let Some(first) = lines.next() {
+      self.put(first);
+    }
+    for line in lines {
+      self.put(b"\n");
+      self.put(self.indent.as_bytes());
+      self.put(line);
+    }
+    Ok(buf.len())
+  }
+  
+  fn flush(&mut self) -> Result<(), std::io::Error> {
+    Ok(())
+  }
+}
+
+// A document's .gltf output, serialized as it's read. Behind next_chunk(),
+// and usable directly by hosts that can read from Rust
+pub struct ChunkedOutput {
+  gltf: GLTF,
+  sections: Vec<Section>,
+  step: Step,
+  // Output that didn't fit in the last chunk, to start the next one
+  held: Vec<u8>,
+  // Holds the latest chunk. Reused between chunks, so its address only
+  // changes if a chunk is requested larger than any before
+  chunk: Vec<u8>,
+}
+
+impl ChunkedOutput {
+  pub fn new(mut gltf: GLTF) -> Self {
+    gltf.ensure_default_scene();
+    let sections = sections(&gltf);
+    Self {
+      gltf,
+      sections,
+      step: Step::Open,
+      held: Vec::new(),
+      chunk: Vec::new(),
+    }
+  }
+  
+  // The step after `step`
+  fn next(&self, step: Step) -> Step {
+    let item = |section: usize, item: usize| match section {
+      section if section == self.sections.len() => Step::Close,
+      section => Step::Item { section, item },
+    };
+    match step {
+      Step::Open => item(0, 0),
+      Step::Item { section, item: i } => {
+        match self.sections[section].count {
+          Some(count) if i + 1 < count => item(section, i + 1),
+          _ => item(section + 1, 0),
+        }
+      },
+      Step::Close | Step::Done => Step::Done,
+    }
+  }
+  
+  // Serializes steps into the chunk until it holds `max` bytes or the
+  // document is finished
+  fn fill(&mut self, max: usize) -> serde_json::Result<()> {
+    self.chunk.clear();
+    self.chunk.reserve(max);
+    let held_taken = max.min(self.held.len());
+    self.chunk.extend_from_slice(&self.held[..held_taken]);
+    self.held.drain(..held_taken);
+    
+    while self.chunk.len() < max && self.step != Step::Done {
+      let mut held = std::mem::take(&mut self.held);
+      let mut writer = ChunkWriter {
+        chunk: &mut self.chunk,
+        limit: max,
+        held: &mut held,
+        indent: "",
+      };
+      
+      match self.step {
+        Step::Open => writer.put(b"{"),
+        Step::Item { section, item } => {
+          let Section { key, count, write } = &self.sections[section];
+          if item == 0 {
+            let comma = if section == 0 { "" } else { "," };
+            writer.put(format!("{comma}\n  \"{key}\": ").as_bytes());
+          }
+          match count {
+            None => writer.indent = "  ",
+            Some(_) => {
+              let comma = if item == 0 { "[" } else { "," };
+              writer.put(format!("{comma}\n    ").as_bytes());
+              writer.indent = "    ";
+            },
+          }
+          write(&self.gltf, item, &mut writer)?;
+          if *count == Some(item + 1) {
+            writer.indent = "";
+            writer.put(b"\n  ]");
+          }
+        },
+        Step::Close => writer.put(b"\n}"),
+        Step::Done => {},
+      }
+      
+      self.held = held;
+      self.step = self.next(self.step);
+    }
+    Ok(())
+  }
+}
+
+impl std::io::Read for ChunkedOutput {
+  fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    self.fill(buf.len())?;
+    buf[..self.chunk.len()].copy_from_slice(&self.chunk);
+    Ok(self.chunk.len())
+  }
+}
+
+static CHUNKED_OUTPUT: Mutex<Option<ChunkedOutput>> = Mutex::new(None);
+
+// Like write_gltf(), but holds on to the document for next_chunk() to hand
+// out, instead of writing it all at once. Replaces any unfinished output
+pub fn write_gltf_chunked(gltf: GLTF) {
+  // A panic while holding the lock can't leave the Option in a bad state, so
+  // poisoning can be ignored
+  let mut output = CHUNKED_OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+  *output = Some(ChunkedOutput::new(gltf));
+  
+  POINTER.store(0, Ordering::Relaxed);
+  SIZE.store(0, Ordering::Relaxed);
+}
+
+// Serializes the next (up to) `max` bytes of output from a model generated in
+// chunked mode, and makes them available through pointer() and size().
+// Returns the number of bytes in the chunk, which is 0 once everything has
+// been handed out (or if there is no chunked output). On failure returns a
+// negated ErrorCode
+#[no_mangle]
+pub extern "C" fn next_chunk(max: i32) -> i32 {
+  if max <= 0 {
+    return -(ErrorCode::Value as i32);
+  }
+  
+  let mut output = match CHUNKED_OUTPUT.try_lock() {
+    Ok(output) => output,
+    Err(_) => return -(ErrorCode::Mutex as i32),
+  };
+  let Some(state) = output.as_mut() else { return 0 };
+  
+  // .unwrap() acceptable here because GLTF has no maps with non-string
+  // keys, which is the only way serialization can fail
+  state.fill(max as usize).unwrap();
+  
+  let written = state.chunk.len();
+  POINTER.store(state.chunk.as_ptr() as u32, Ordering::Relaxed);
+  SIZE.store(written as u32, Ordering::Relaxed);
+  
+  if written == 0 {
+    *output = None;
+  }
+  written as i32
+}
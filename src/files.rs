@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use crate::*;
+
+fn invalid_input(message: String) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+// Reverses encode_uri_path(). Malformed escapes are kept as they are
+fn decode_uri_path(uri: &str) -> String {
+  let bytes = uri.as_bytes();
+  let mut decoded = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    let escape = bytes.get(i + 1..i + 3)
+      .and_then(|hex| std::str::from_utf8(hex).ok())
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match (bytes[i], escape) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        i += 3;
+      },
+      (byte, _) => {
+        decoded.push(byte);
+        i += 1;
+      },
+    }
+  }
+  String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl GLTF {
+  // Writes the document as a pretty-printed .gltf at `path`, along with the
+  // files it refers to: each buffer without a data: URI is written next to it
+  // (named after the .gltf if it has no uri yet), and each image made with
+  // Image::external() is copied next to it. Subdirectories named in URIs are
+  // created as needed. `buffers` holds the binary contents of self.buffers.
+  // Only works natively, since WebAssembly modules don't have a filesystem
+  pub fn write_files(&self, path: &Path, buffers: &[Vec<u8>],
+  ) -> std::io::Result<()> {
+    for (i, image) in self.images.iter().enumerate() {
+      if image.validate().is_err() {
+        return Err(invalid_input(format!("images[{i}] must have either a uri \
+          or a bufferView and mimeType")));
+      }
+    }
+    
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let stem = path.file_stem().map_or(String::from("model"),
+      |stem| stem.to_string_lossy().into_owned());
+    let mut gltf = self.clone();
+    gltf.ensure_default_scene();
+    let buffer_count = gltf.buffers.len();
+    
+    for (i, buffer) in gltf.buffers.iter_mut().enumerate() {
+      if buffer.uri.starts_with("data:") {
+        continue;
+      }
+      
+      let bytes = buffers.get(i).ok_or_else(|| {
+        invalid_input(format!("no data given for buffers[{i}]"))
+      })?;
+      
+      if buffer.uri.is_empty() {
+        buffer.uri = match buffer_count {
+          1 => encode_uri_path(&format!("{stem}.bin")),
+          _ => encode_uri_path(&format!("{stem}{i}.bin")),
+        };
+      }
+      
+      let destination = directory.join(decode_uri_path(&buffer.uri));
+      if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+      }
+      std::fs::write(destination, bytes)?;
+    }
+    
+    for image in gltf.images.iter() {
+      let source = match &image.source {
+        Some(source) => source,
+        None => continue,
+      };
+      
+      let destination = directory.join(decode_uri_path(&image.uri));
+      // Writing over the source with itself would truncate it
+      let same_file = match (std::fs::canonicalize(source),
+        std::fs::canonicalize(&destination)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+      };
+      if !same_file {
+        if let Some(parent) = destination.parent() {
+          std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(source, &destination)?;
+      }
+    }
+    
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    gltf.write_to(&mut file, true)?;
+    std::io::Write::flush(&mut file)
+  }
+}
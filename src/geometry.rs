@@ -0,0 +1,624 @@
+// Processing for raw vertex and index arrays, before they are packed into
+// buffers
+
+use crate::ErrorCode;
+
+// Size of the simulated post-transform vertex cache used for scoring. Real
+// GPUs vary, but Forsyth found 32 works well across them
+const CACHE_SIZE: usize = 32;
+
+// Vertex score from Tom Forsyth's "Linear-Speed Vertex Cache Optimisation"
+fn forsyth_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+  if remaining == 0 {
+    return -1.0;
+  }
+  
+  let cache_score = match cache_position {
+    None => 0.0,
+    // The most recent triangle's vertices get a fixed score, so the next
+    // triangle isn't biased toward reusing the exact same edge
+    Some(p) if p < 3 => 0.75,
+    Some(p) => {
+      (1.0 - (p - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5)
+    },
+  };
+  
+  // Boosts vertices with few triangles left, so they get finished off rather
+  // than leaving stragglers that have to be reloaded later
+  cache_score + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+// Reorders the triangles in a triangle list to make better use of the GPU's
+// vertex cache, using Forsyth's algorithm. Each triangle keeps its vertices
+// and winding, only the order of triangles changes. `vertex_count` is the
+// number of vertices the indices refer into
+pub fn optimize_indices(indices: &mut [u32], vertex_count: usize,
+) -> Result<(), ErrorCode> {
+  if !indices.len().is_multiple_of(3) {
+    return Err(ErrorCode::Value);
+  }
+  if indices.iter().any(|&i| i as usize >= vertex_count) {
+    return Err(ErrorCode::Index);
+  }
+  
+  let triangle_count = indices.len() / 3;
+  
+  // Triangles using each vertex, stored as one flat list. The triangles for
+  // vertex v are at vertex_triangles[offsets[v]..offsets[v + 1]]
+  let mut offsets = vec![0; vertex_count + 1];
+  for &i in indices.iter() {
+    offsets[i as usize + 1] += 1;
+  }
+  for v in 0..vertex_count {
+    offsets[v + 1] += offsets[v];
+  }
+  let mut vertex_triangles = vec![0; indices.len()];
+  let mut next_slot = offsets.clone();
+  for (t, triangle) in indices.chunks(3).enumerate() {
+    for &v in triangle {
+      vertex_triangles[next_slot[v as usize]] = t;
+      next_slot[v as usize] += 1;
+    }
+  }
+  
+  let mut remaining: Vec<usize> = (0..vertex_count)
+    .map(|v| offsets[v + 1] - offsets[v]).collect();
+  let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+  let mut vertex_score: Vec<f32> = remaining.iter()
+    .map(|&r| forsyth_score(None, r)).collect();
+  let mut triangle_score: Vec<f32> = indices.chunks(3)
+    .map(|triangle| triangle.iter().map(|&v| vertex_score[v as usize]).sum())
+    .collect();
+  let mut emitted = vec![false; triangle_count];
+  
+  let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+  let mut output: Vec<u32> = Vec::with_capacity(indices.len());
+  let mut next_unemitted = 0;
+  
+  let mut best = (0..triangle_count)
+    .max_by(|&a, &b| triangle_score[a].total_cmp(&triangle_score[b]));
+  
+  while let Some(t) = best {
+    let triangle = [indices[3 * t], indices[3 * t + 1], indices[3 * t + 2]];
+    emitted[t] = true;
+    output.extend_from_slice(&triangle);
+    for &v in triangle.iter() {
+      remaining[v as usize] -= 1;
+    }
+    
+    // Move this triangle's vertices to the front of the cache. Anything
+    // pushed past the end is evicted, but still needs its score updated
+    cache.retain(|v| !triangle.contains(v));
+    cache.splice(0..0, triangle);
+    let evicted = cache.split_off(cache.len().min(CACHE_SIZE));
+    for &v in evicted.iter() {
+      cache_position[v as usize] = None;
+    }
+    for (p, &v) in cache.iter().enumerate() {
+      cache_position[v as usize] = Some(p);
+    }
+    
+    for &v in cache.iter().chain(evicted.iter()) {
+      let v = v as usize;
+      vertex_score[v] = forsyth_score(cache_position[v], remaining[v]);
+    }
+    
+    // Only triangles touching the cache can have changed score, so the next
+    // triangle is picked from them
+    best = None;
+    let mut best_score = f32::NEG_INFINITY;
+    for &v in cache.iter().chain(evicted.iter()) {
+      let v = v as usize;
+      for &t in vertex_triangles[offsets[v]..offsets[v + 1]].iter() {
+        if emitted[t] {
+          continue;
+        }
+        
+        triangle_score[t] = indices[3 * t..3 * t + 3].iter()
+          .map(|&u| vertex_score[u as usize]).sum();
+        if cache_position[v].is_some() && triangle_score[t] > best_score {
+          best = Some(t);
+          best_score = triangle_score[t];
+        }
+      }
+    }
+    
+    // Nothing left next to the cache, so start on the next unconnected piece
+    if best.is_none() {
+      while next_unemitted < triangle_count && emitted[next_unemitted] {
+        next_unemitted += 1;
+      }
+      if next_unemitted < triangle_count {
+        best = Some(next_unemitted);
+      }
+    }
+  }
+  
+  indices.copy_from_slice(&output);
+  Ok(())
+}
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+  [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+  [
+    a[1] * b[2] - a[2] * b[1],
+    a[2] * b[0] - a[0] * b[2],
+    a[0] * b[1] - a[1] * b[0],
+  ]
+}
+
+// Returns None for vectors too short to have a meaningful direction
+pub(crate) fn normalize(a: [f32; 3]) -> Option<[f32; 3]> {
+  let length = (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+  if length > f32::EPSILON {
+    Some([a[0] / length, a[1] / length, a[2] / length])
+  } else {
+    None
+  }
+}
+
+// Unit normal of triangle a, b, c with counter-clockwise front faces, as glTF
+// uses. Zero-area triangles have no normal, so they get +Y, which at least is
+// a valid unit vector
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+  normalize(cross(sub(b, a), sub(c, a))).unwrap_or([0.0, 1.0, 0.0])
+}
+
+// Vertex attribute streams, with one entry per vertex in each
+#[derive(Clone, Debug)]
+pub struct Vertices {
+  pub positions: Vec<[f32; 3]>,
+  pub normals: Option<Vec<[f32; 3]>>,
+  pub uvs: Option<Vec<[f32; 2]>>,
+}
+
+// Checks that `indices` is a triangle list into `vertex_count` vertices, and
+// that each optional attribute stream has one entry per vertex
+pub(crate) fn check_triangle_list(indices: &[u32], vertex_count: usize,
+  stream_lengths: &[Option<usize>],
+) -> Result<(), ErrorCode> {
+  if !indices.len().is_multiple_of(3) {
+    return Err(ErrorCode::Value);
+  }
+  if stream_lengths.iter().flatten().any(|&length| length != vertex_count) {
+    return Err(ErrorCode::Value);
+  }
+  if indices.iter().any(|&i| i as usize >= vertex_count) {
+    return Err(ErrorCode::Index);
+  }
+  
+  Ok(())
+}
+
+// Expands an indexed triangle list so each triangle has its own three
+// vertices, returned in draw order with no index buffer. If `flat_normals` is
+// set, normals are replaced by each triangle's face normal (even if `normals`
+// is None), giving hard edges everywhere
+pub fn unweld(positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>,
+  uvs: Option<&[[f32; 2]]>, indices: &[u32], flat_normals: bool,
+) -> Result<Vertices, ErrorCode> {
+  check_triangle_list(indices, positions.len(),
+    &[normals.map(|n| n.len()), uvs.map(|u| u.len())])?;
+  
+  let new_positions: Vec<[f32; 3]> = indices.iter()
+    .map(|&i| positions[i as usize]).collect();
+  
+  let new_normals = if flat_normals {
+    Some(new_positions.chunks(3).flat_map(|triangle| {
+      [face_normal(triangle[0], triangle[1], triangle[2]); 3]
+    }).collect())
+  } else {
+    normals.map(|normals| indices.iter().map(|&i| normals[i as usize])
+      .collect())
+  };
+  
+  let new_uvs = uvs.map(|uvs| indices.iter().map(|&i| uvs[i as usize])
+    .collect());
+  
+  Ok(Vertices {
+    positions: new_positions,
+    normals: new_normals,
+    uvs: new_uvs,
+  })
+}
+
+// Triangles (by position in the triangle list) that draw nothing useful:
+// those using a vertex twice, those whose positions are collinear to within
+// `epsilon` (compared against twice the triangle's area), and repeats of an
+// earlier triangle's vertices in any order. The first copy of a repeated
+// triangle is not reported
+pub fn find_degenerate(positions: &[[f32; 3]], indices: &[u32], epsilon: f32,
+) -> Result<Vec<u32>, ErrorCode> {
+  check_triangle_list(indices, positions.len(), &[])?;
+  
+  let mut seen = std::collections::HashSet::new();
+  let mut degenerate = Vec::new();
+  for (t, triangle) in indices.chunks(3).enumerate() {
+    let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+    
+    let mut sorted = [a, b, c];
+    sorted.sort();
+    let repeated = !seen.insert(sorted);
+    
+    let twice_area = {
+      let [x, y, z] = cross(
+        sub(positions[b as usize], positions[a as usize]),
+        sub(positions[c as usize], positions[a as usize]),
+      );
+      (x * x + y * y + z * z).sqrt()
+    };
+    
+    if a == b || b == c || c == a || twice_area <= epsilon || repeated {
+      degenerate.push(t as u32);
+    }
+  }
+  
+  Ok(degenerate)
+}
+
+// Drops vertices that no index refers to, for after triangles have been
+// removed. Every stream present is compacted the same way, keeping the
+// remaining vertices in their original order, and `indices` is rewritten to
+// point into the compacted streams
+pub fn compact_vertices(positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>,
+  uvs: Option<&[[f32; 2]]>, indices: &mut [u32],
+) -> Result<Vertices, ErrorCode> {
+  check_triangle_list(indices, positions.len(),
+    &[normals.map(|n| n.len()), uvs.map(|u| u.len())])?;
+  
+  let mut used = vec![false; positions.len()];
+  for &i in indices.iter() {
+    used[i as usize] = true;
+  }
+  
+  let kept: Vec<usize> = (0..positions.len()).filter(|&v| used[v]).collect();
+  let mut new_index = vec![0; positions.len()];
+  for (new, &old) in kept.iter().enumerate() {
+    new_index[old] = new as u32;
+  }
+  for i in indices.iter_mut() {
+    *i = new_index[*i as usize];
+  }
+  
+  Ok(Vertices {
+    positions: kept.iter().map(|&v| positions[v]).collect(),
+    normals: normals.map(|normals| kept.iter().map(|&v| normals[v]).collect()),
+    uvs: uvs.map(|uvs| kept.iter().map(|&v| uvs[v]).collect()),
+  })
+}
+
+// Expands a TriangleStrip index list into a Triangles list. Every other
+// triangle in a strip is emitted with its last two vertices swapped, as
+// (i, i + 2, i + 1), to keep the winding consistent. This is the order the
+// glTF spec defines for strips. Strips stitched together with repeated
+// indices give zero-area triangles, which find_degenerate() can pick out
+pub fn strip_to_triangles(strip_indices: &[u32]) -> Vec<u32> {
+  let mut triangles = Vec::with_capacity(3 * strip_indices.len()
+    .saturating_sub(2));
+  for (i, window) in strip_indices.windows(3).enumerate() {
+    match i % 2 {
+      0 => triangles.extend_from_slice(&[window[0], window[1], window[2]]),
+      _ => triangles.extend_from_slice(&[window[0], window[2], window[1]]),
+    }
+  }
+  triangles
+}
+
+// Breaks an index stream using primitive restart, which glTF doesn't have, at
+// each `restart` index (usually the component type's maximum, such as
+// 0xFFFF). Empty pieces, from repeated or trailing restarts, are left out.
+// Each piece can then go through strip_to_triangles() or fan_to_triangles()
+pub fn split_on_restart(indices: &[u32], restart: u32) -> Vec<Vec<u32>> {
+  indices.split(|&i| i == restart).filter(|piece| !piece.is_empty())
+    .map(|piece| piece.to_vec()).collect()
+}
+
+// Expands a TriangleFan index list into a Triangles list, following the glTF
+// spec's definition, where each triangle is two consecutive vertices and then
+// the first one
+pub fn fan_to_triangles(fan_indices: &[u32]) -> Vec<u32> {
+  let Some(&center) = fan_indices.first() else { return Vec::new() };
+  
+  let mut triangles = Vec::with_capacity(3 * fan_indices.len()
+    .saturating_sub(2));
+  for window in fan_indices[1..].windows(2) {
+    triangles.extend_from_slice(&[window[0], window[1], center]);
+  }
+  triangles
+}
+
+// Edges of a triangle list, as index pairs for a Lines primitive drawing the
+// same vertices. Each edge appears once, however many triangles share it and
+// in whichever direction, in order of first appearance and as first walked.
+// Edges from a vertex to itself, in degenerate triangles, are left out
+pub fn to_wireframe(indices: &[u32]) -> Vec<u32> {
+  let mut seen = std::collections::HashSet::new();
+  let mut lines = Vec::new();
+  for t in indices.chunks_exact(3) {
+    for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+      if a != b && seen.insert((a.min(b), a.max(b))) {
+        lines.extend_from_slice(&[a, b]);
+      }
+    }
+  }
+  lines
+}
+
+// Stands in for the opposite vertex in build_adjacency() where an edge has no
+// neighboring triangle
+pub const NO_NEIGHBOR: u32 = u32::MAX;
+
+// Index layout for GL_TRIANGLES_ADJACENCY, one entry per triangle in a
+// triangle list: each triangle's vertices at even positions, and after each
+// edge the vertex of the neighboring triangle across it that isn't on the
+// edge. So [a, b, c] becomes [a, across ab, b, across bc, c, across ca].
+// Edges on a boundary get NO_NEIGHBOR. Where more than two triangles share
+// an edge, the neighbor is the lowest numbered one that runs the edge in the
+// opposite direction (agreeing in winding), or failing that the lowest
+// numbered one at all. Trailing indices short of a whole triangle are ignored
+pub fn build_adjacency(indices: &[u32]) -> Vec<[u32; 6]> {
+  // Each undirected edge, as (low, high), with the triangles using it. Every
+  // triangle appends in order, so each list is sorted by triangle
+  let mut edge_uses = std::collections::HashMap::new();
+  for (triangle, t) in indices.chunks_exact(3).enumerate() {
+    for k in 0..3 {
+      let (from, to, opposite) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+      edge_uses.entry((from.min(to), from.max(to))).or_insert_with(Vec::new)
+        .push((triangle, from, opposite));
+    }
+  }
+  
+  indices.chunks_exact(3).enumerate().map(|(triangle, t)| {
+    let mut adjacent = [NO_NEIGHBOR; 6];
+    for k in 0..3 {
+      let (from, to) = (t[k], t[(k + 1) % 3]);
+      let uses = &edge_uses[&(from.min(to), from.max(to))];
+      let others = || uses.iter().filter(|&&(other, _, _)| other != triangle);
+      
+      adjacent[2 * k] = from;
+      adjacent[2 * k + 1] = others().find(|&&(_, start, _)| start == to)
+        .or_else(|| others().next())
+        .map_or(NO_NEIGHBOR, |&(_, _, opposite)| opposite);
+    }
+    adjacent
+  }).collect()
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Sum of squared distances to a set of planes, as a symmetric 4x4 matrix
+// stored as its upper triangle. From Garland and Heckbert's "Surface
+// Simplification Using Quadric Error Metrics"
+#[derive(Clone, Copy)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+  // Plane ax + by + cz + d = 0, with (a, b, c) a unit normal
+  fn plane([a, b, c]: [f32; 3], d: f32, weight: f64) -> Self {
+    let [a, b, c, d] = [a as f64, b as f64, c as f64, d as f64];
+    Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d]
+      .map(|x| x * weight))
+  }
+  
+  fn add(&self, other: &Self) -> Self {
+    let mut sum = self.0;
+    for (s, o) in sum.iter_mut().zip(other.0.iter()) {
+      *s += o;
+    }
+    Self(sum)
+  }
+  
+  fn error(&self, [x, y, z]: [f32; 3]) -> f64 {
+    let [x, y, z] = [x as f64, y as f64, z as f64];
+    let q = self.0;
+    q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+      + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+      + q[7] * z * z + 2.0 * q[8] * z
+      + q[9]
+  }
+}
+
+// Moving vertex `from` onto vertex `to`, removing `from`. Versions are those
+// of the two vertices when the cost was computed, so stale entries can be
+// skipped. Ordered so a BinaryHeap pops the cheapest collapse first
+struct Collapse {
+  cost: f64,
+  from: u32,
+  to: u32,
+  from_version: u32,
+  to_version: u32,
+}
+
+impl PartialEq for Collapse {
+  fn eq(&self, other: &Self) -> bool {
+    self.cost.total_cmp(&other.cost).is_eq()
+  }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Collapse {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    other.cost.total_cmp(&self.cost)
+  }
+}
+
+// Reduces a triangle list to about `target_ratio` (from 0 to 1) of its
+// triangles by quadric error metric edge collapse. Each collapse moves one
+// vertex onto a neighbor, so surviving vertices keep their original
+// positions and normals. Vertices on boundary (or non-manifold) edges are
+// never moved, so outlines and holes keep their shape, and collapses that
+// would flip a triangle or join two surfaces at a single edge are skipped.
+// The triangle count can therefore stay above the target when nothing more
+// can be collapsed safely. Unused vertices are removed from the result
+pub fn simplify(positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>,
+  indices: &[u32], target_ratio: f32,
+) -> Result<(Vertices, Vec<u32>), ErrorCode> {
+  check_triangle_list(indices, positions.len(), &[normals.map(|n| n.len())])?;
+  if !(0.0..=1.0).contains(&target_ratio) {
+    return Err(ErrorCode::Value);
+  }
+  
+  let mut triangles: Vec<[u32; 3]> = indices.chunks(3)
+    .map(|t| [t[0], t[1], t[2]]).collect();
+  let target = (triangles.len() as f32 * target_ratio).round() as usize;
+  let mut alive = vec![true; triangles.len()];
+  let mut live = triangles.len();
+  
+  let mut vertex_triangles = vec![Vec::new(); positions.len()];
+  let mut quadrics = vec![Quadric([0.0; 10]); positions.len()];
+  let mut edge_uses = std::collections::HashMap::new();
+  for (t, triangle) in triangles.iter().enumerate() {
+    let [a, b, c] = triangle.map(|v| positions[v as usize]);
+    let normal = cross(sub(b, a), sub(c, a));
+    // Weighting by area keeps slivers from dominating
+    if let Some(unit) = normalize(normal) {
+      let area = dot(normal, unit) as f64 / 2.0;
+      let quadric = Quadric::plane(unit, -dot(unit, a), area);
+      for &v in triangle {
+        quadrics[v as usize] = quadrics[v as usize].add(&quadric);
+      }
+    }
+    
+    for i in 0..3 {
+      let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+      vertex_triangles[a as usize].push(t);
+      *edge_uses.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+    }
+  }
+  
+  // Edges without exactly two triangles are boundaries or non-manifold
+  let mut locked = vec![false; positions.len()];
+  for (&(a, b), &uses) in edge_uses.iter() {
+    if uses != 2 {
+      locked[a as usize] = true;
+      locked[b as usize] = true;
+    }
+  }
+  
+  let mut version = vec![0; positions.len()];
+  let mut removed = vec![false; positions.len()];
+  let candidate = |from: u32, to: u32, quadrics: &[Quadric],
+    version: &[u32]| Collapse {
+    cost: quadrics[from as usize].add(&quadrics[to as usize])
+      .error(positions[to as usize]),
+    from,
+    to,
+    from_version: version[from as usize],
+    to_version: version[to as usize],
+  };
+  
+  let mut heap = std::collections::BinaryHeap::new();
+  for &(a, b) in edge_uses.keys() {
+    if a == b {
+      continue;
+    }
+    if !locked[a as usize] {
+      heap.push(candidate(a, b, &quadrics, &version));
+    }
+    if !locked[b as usize] {
+      heap.push(candidate(b, a, &quadrics, &version));
+    }
+  }
+  
+  // Vertices sharing a live triangle with `v`
+  let neighbors = |v: usize, triangles: &[[u32; 3]], alive: &[bool],
+    vertex_triangles: &[Vec<usize>]| {
+    let mut neighbors: Vec<u32> = vertex_triangles[v].iter()
+      .filter(|&&t| alive[t]).flat_map(|&t| triangles[t])
+      .filter(|&x| x as usize != v).collect();
+    neighbors.sort_unstable();
+    neighbors.dedup();
+    neighbors
+  };
+  
+  while live > target {
+    let Some(collapse) = heap.pop() else { break };
+    let (u, v) = (collapse.from as usize, collapse.to as usize);
+    if removed[u] || removed[v] || version[u] != collapse.from_version
+      || version[v] != collapse.to_version {
+      continue;
+    }
+    
+    let (shared, moved): (Vec<usize>, Vec<usize>) = vertex_triangles[u].iter()
+      .copied().filter(|&t| alive[t])
+      .partition(|&t| triangles[t].contains(&(v as u32)));
+    if shared.is_empty() {
+      continue;
+    }
+    
+    // Link condition: the only vertices next to both ends of the edge are
+    // the far corners of the triangles on it. Otherwise the collapse would
+    // pinch the surface
+    let u_neighbors = neighbors(u, &triangles, &alive, &vertex_triangles);
+    let v_neighbors = neighbors(v, &triangles, &alive, &vertex_triangles);
+    let common = u_neighbors.iter().filter(|x| v_neighbors.contains(x))
+      .count();
+    if common != shared.len() {
+      continue;
+    }
+    
+    let flips = moved.iter().any(|&t| {
+      let before = triangles[t].map(|x| positions[x as usize]);
+      let after = triangles[t].map(|x| match x as usize == u {
+        true => positions[v],
+        false => positions[x as usize],
+      });
+      let before = cross(sub(before[1], before[0]), sub(before[2], before[0]));
+      match normalize(cross(sub(after[1], after[0]), sub(after[2], after[0])))
+      {
+        Some(after) => dot(before, after) <= 0.0,
+        None => true,
+      }
+    });
+    if flips {
+      continue;
+    }
+    
+    for &t in shared.iter() {
+      alive[t] = false;
+      live -= 1;
+    }
+    for &t in moved.iter() {
+      for x in triangles[t].iter_mut() {
+        if *x as usize == u {
+          *x = v as u32;
+        }
+      }
+      vertex_triangles[v].push(t);
+    }
+    vertex_triangles[u].clear();
+    vertex_triangles[v].retain(|&t| alive[t]);
+    removed[u] = true;
+    quadrics[v] = quadrics[v].add(&quadrics[u]);
+    version[v] += 1;
+    
+    for x in neighbors(v, &triangles, &alive, &vertex_triangles) {
+      if !locked[v] {
+        heap.push(candidate(v as u32, x, &quadrics, &version));
+      }
+      if !locked[x as usize] {
+        heap.push(candidate(x, v as u32, &quadrics, &version));
+      }
+    }
+  }
+  
+  let mut new_indices: Vec<u32> = triangles.iter().zip(alive.iter())
+    .filter(|(_, &alive)| alive).flat_map(|(&triangle, _)| triangle)
+    .collect();
+  let vertices = compact_vertices(positions, normals, None,
+    &mut new_indices)?;
+  
+  Ok((vertices, new_indices))
+}
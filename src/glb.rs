@@ -0,0 +1,152 @@
+use crate::*;
+
+impl GLTF {
+  // A GLB file's binary chunk must be 4-byte aligned, and some viewers (the
+  // Windows 3D viewer, for one) also reject buffers or buffer views that don't
+  // end on a 4-byte boundary even though three.js accepts them. Returns a
+  // warning for each problem found. to_glb() runs this on the document it
+  // packs
+  pub fn glb_alignment_problems(&self) -> Vec<ValidationMessage> {
+    let mut problems = Vec::new();
+    let mut warn = |path: String, message: String| {
+      problems.push(ValidationMessage {
+        severity: Severity::Warning,
+        path,
+        message,
+      });
+    };
+    
+    if self.buffers.len() > 1 {
+      warn(String::from("buffers[1]"), format!("GLB files hold a single \
+        buffer, but there are {}", self.buffers.len()));
+    }
+    
+    if let Some(buffer) = self.buffers.first() {
+      if !buffer.byte_length.is_multiple_of(4) {
+        warn(String::from("buffers[0].byteLength"), format!("is {}, which \
+          is not a multiple of 4", buffer.byte_length));
+      }
+    }
+    
+    for (i, buffer_view) in self.buffer_views.iter().enumerate() {
+      // Widened so a bad offset can't overflow
+      let end = buffer_view.byte_offset as u64 + buffer_view.byte_length as u64;
+      if !end.is_multiple_of(4) {
+        warn(format!("bufferViews[{i}].byteLength"), format!("ends at byte \
+          {end}, which is not a multiple of 4"));
+      }
+    }
+    
+    problems
+  }
+  
+  // Pads `bytes`, the contents of the buffer that will become the GLB binary
+  // chunk, to a multiple of 4 bytes and updates buffers[0].byteLength to
+  // match. Returns true if padding had to be added, which callers should
+  // report as a warning since it usually means a buffer length was computed
+  // by hand and is wrong
+  pub fn pad_glb_buffer(&mut self, bytes: &mut Vec<u8>) -> bool {
+    let original_length = bytes.len();
+    pad_to_4(bytes);
+    
+    if let Some(buffer) = self.buffers.first_mut() {
+      buffer.byte_length = bytes.len() as u32;
+    }
+    
+    bytes.len() != original_length
+  }
+  
+  // Joins every buffer into one, as GLB files need, and returns its contents.
+  // `buffers` holds the current buffers' contents, in order. Each starts on
+  // a 4-byte boundary in the result, and buffer views are moved to match.
+  // The combined buffer keeps the first buffer's name, but not its uri, since
+  // the data has changed. Fails, changing nothing, if a buffer's contents are
+  // missing or shorter than its byteLength
+  pub fn consolidate_buffers(&mut self, buffers: &[&[u8]],
+  ) -> Result<Vec<u8>, ErrorCode> {
+    let short = self.buffers.iter().zip(buffers.iter())
+      .any(|(buffer, bytes)| bytes.len() < buffer.byte_length as usize);
+    if buffers.len() < self.buffers.len() || short {
+      return Err(ErrorCode::Index);
+    }
+    if self.buffer_views.iter()
+      .any(|view| view.buffer as usize >= self.buffers.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    let mut combined = Vec::new();
+    let mut starts = Vec::with_capacity(self.buffers.len());
+    for (buffer, bytes) in self.buffers.iter().zip(buffers.iter()) {
+      pad_to_4(&mut combined);
+      starts.push(combined.len() as u32);
+      combined.extend_from_slice(&bytes[..buffer.byte_length as usize]);
+    }
+    
+    for view in self.buffer_views.iter_mut() {
+      view.byte_offset += starts[view.buffer as usize];
+      view.buffer = 0;
+    }
+    
+    let mut buffer = Buffer::new();
+    if let Some(first) = self.buffers.first() {
+      buffer.name = first.name.clone();
+    }
+    buffer.byte_length = combined.len() as u32;
+    if !self.buffers.is_empty() {
+      self.buffers = vec![buffer];
+    }
+    
+    Ok(combined)
+  }
+  
+  // Packs the document and `buffers`, the contents of its buffers, into a
+  // .glb file: a 12-byte header, the JSON chunk padded with spaces, and the
+  // binary chunk padded with zeros. Several buffers are consolidated into
+  // one first. The document itself isn't changed. Also returns the
+  // consolidated document's glb_alignment_problems(), which include the
+  // binary chunk needing padding. Fails if a buffer's contents are missing
+  // or shorter than its byteLength
+  pub fn to_glb(&self, buffers: &[Vec<u8>],
+  ) -> Result<(Vec<u8>, Vec<ValidationMessage>), ErrorCode> {
+    let mut gltf = self.clone();
+    gltf.ensure_default_scene();
+    
+    let mut bin = match gltf.buffers.len() {
+      0 => Vec::new(),
+      _ => {
+        let slices: Vec<&[u8]> = buffers.iter().map(|b| b.as_slice())
+          .collect();
+        gltf.consolidate_buffers(&slices)?
+      },
+    };
+    let warnings = gltf.glb_alignment_problems();
+    gltf.pad_glb_buffer(&mut bin);
+    
+    let mut json = Vec::new();
+    // .unwrap() acceptable here because GLTF has no maps with non-string
+    // keys, which is the only way serialization can fail
+    gltf.write_to(&mut json, false).unwrap();
+    while !json.len().is_multiple_of(4) {
+      json.push(b' ');
+    }
+    
+    let has_bin = !gltf.buffers.is_empty();
+    let length = 12 + 8 + json.len() + if has_bin { 8 + bin.len() } else { 0 };
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    
+    if has_bin {
+      glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+      glb.extend_from_slice(b"BIN\0");
+      glb.extend_from_slice(&bin);
+    }
+    
+    Ok((glb, warnings))
+  }
+}
@@ -0,0 +1,144 @@
+use crate::*;
+
+pub const EXT_MESH_GPU_INSTANCING: &str = ExtMeshGpuInstancing::NAME;
+
+// Draws a node's mesh once per instance, placed by per-instance accessors
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExtMeshGpuInstancing {
+  // Accessor for each per-instance attribute, such as TRANSLATION
+  pub attributes: std::collections::BTreeMap<String, u32>,
+}
+
+impl Extension for ExtMeshGpuInstancing {
+  const NAME: &'static str = "EXT_mesh_gpu_instancing";
+}
+
+impl ExtMeshGpuInstancing {
+  pub fn new() -> Self {
+    Self { attributes: std::collections::BTreeMap::new() }
+  }
+  
+  // The extension on `node`, if it has one that's well formed
+  pub fn from_node(node: &Node) -> Option<Self> {
+    let value = node.extensions.as_ref()?.get(Self::NAME)?;
+    serde_json::from_value(value.clone()).ok()
+  }
+}
+
+// Placement of one copy of an instanced mesh, relative to its node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+  pub translation: [f64; 3],
+  // Quaternion, as x, y, z, w
+  pub rotation: [f64; 4],
+  pub scale: [f64; 3],
+}
+
+impl Instance {
+  pub fn new() -> Self {
+    Self {
+      translation: [0.0; 3],
+      rotation: [0.0, 0.0, 0.0, 1.0],
+      scale: [1.0; 3],
+    }
+  }
+}
+
+// The accessor references in a node's EXT_mesh_gpu_instancing attributes,
+// whatever their names
+pub(crate) fn instance_accessors(node: &Node) -> Vec<u32> {
+  ExtMeshGpuInstancing::from_node(node).into_iter()
+    .flat_map(|instancing| instancing.attributes.into_values()).collect()
+}
+
+// The accessor behind one of a node's EXT_mesh_gpu_instancing attributes,
+// such as TRANSLATION, if it has that attribute
+pub(crate) fn instance_attribute(node: &Node, name: &str) -> Option<u32> {
+  ExtMeshGpuInstancing::from_node(node)?.attributes.get(name).copied()
+}
+
+// Passes each accessor reference in a node's EXT_mesh_gpu_instancing
+// attributes through `map`. Fails without changing anything if `map` does
+pub(crate) fn map_instance_accessors<F>(node: &mut Node, mut map: F,
+) -> Result<(), ErrorCode>
+where F: FnMut(u32) -> Result<u32, ErrorCode> {
+  let Some(mut instancing) = ExtMeshGpuInstancing::from_node(node) else {
+    return Ok(())
+  };
+  for accessor in instancing.attributes.values_mut() {
+    *accessor = map(*accessor)?;
+  }
+  insert_extension(&mut node.extensions, &instancing)
+}
+
+impl GLTF {
+  // Adds a node drawing mesh `mesh` once per entry in `instances`, through
+  // EXT_mesh_gpu_instancing. The per-instance TRANSLATION, ROTATION, and
+  // SCALE accessors are appended to `bytes`, the binary contents of buffer
+  // `buffer`, leaving out ROTATION and SCALE if no instance needs them.
+  // Viewers without the extension draw the mesh once, at the node. The node
+  // isn't added to any scene. Fails if there are no instances, or the mesh
+  // or buffer doesn't exist. Returns the node's index
+  pub fn add_instanced_mesh(&mut self, mesh: u32, instances: &[Instance],
+    buffer: u32, bytes: &mut Vec<u8>,
+  ) -> Result<u32, ErrorCode> {
+    if instances.is_empty() {
+      return Err(ErrorCode::Value);
+    }
+    if mesh as usize >= self.meshes.len()
+      || buffer as usize >= self.buffers.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    let identity = Instance::new();
+    let rotated = instances.iter().any(|i| i.rotation != identity.rotation);
+    let scaled = instances.iter().any(|i| i.scale != identity.scale);
+    
+    let mut instancing = ExtMeshGpuInstancing::new();
+    let translations: Vec<f64> = instances.iter()
+      .flat_map(|i| i.translation).collect();
+    instancing.attributes.insert(String::from("TRANSLATION"),
+      self.push_instance_accessor(buffer, bytes, &translations, Type::VEC3)?);
+    if rotated {
+      let rotations: Vec<f64> = instances.iter()
+        .flat_map(|i| i.rotation).collect();
+      instancing.attributes.insert(String::from("ROTATION"),
+        self.push_instance_accessor(buffer, bytes, &rotations, Type::VEC4)?);
+    }
+    if scaled {
+      let scales: Vec<f64> = instances.iter().flat_map(|i| i.scale).collect();
+      instancing.attributes.insert(String::from("SCALE"),
+        self.push_instance_accessor(buffer, bytes, &scales, Type::VEC3)?);
+    }
+    
+    let mut node = Node::new();
+    node.mesh = Some(mesh);
+    self.nodes.push(node);
+    let index = self.nodes.len() as u32 - 1;
+    // .unwrap() acceptable here because the node was just added, and the
+    // extension has nothing to fail validation
+    self.attach_extension(ExtensionTarget::Node(index), &instancing, false)
+      .unwrap();
+    
+    Ok(index)
+  }
+  
+  fn push_instance_accessor(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    values: &[f64], type_: Type,
+  ) -> Result<u32, ErrorCode> {
+    let packed: Vec<u8> = values.iter()
+      .flat_map(|&x| (x as f32).to_le_bytes()).collect();
+    // Instance data is read per instance rather than per vertex, so the view
+    // gets no vertex buffer target
+    let buffer_view = self.push_buffer_view(buffer, bytes, &packed, None,
+      None)?;
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = ComponentType::Float;
+    accessor.count = (values.len() / type_.components()) as u32;
+    accessor.type_ = type_;
+    self.accessors.push(accessor);
+    Ok(self.accessors.len() as u32 - 1)
+  }
+}
@@ -0,0 +1,193 @@
+// Typed structs for KHR_lights_punctual: lights at a point or infinitely far
+// away, placed by nodes. Add them with GLTF::add_punctual_light() and place
+// them with GLTF::set_node_light()
+
+use crate::{ErrorCode, GLTF, Node};
+
+pub const NAME: &str = "KHR_lights_punctual";
+
+fn is_white(value: &[f64; 3]) -> bool {
+  *value == [1.0; 3]
+}
+
+fn is_one(value: &f64) -> bool {
+  *value == 1.0
+}
+
+fn is_zero(value: &f64) -> bool {
+  *value == 0.0
+}
+
+fn is_quarter_pi(value: &f64) -> bool {
+  *value == std::f64::consts::FRAC_PI_4
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightType {
+  // Parallel light from infinitely far away along the node's -Z axis, as
+  // from the sun. Intensity is in lux
+  Directional,
+  // Light in every direction from the node's origin. Intensity is in
+  // candela
+  Point,
+  // Light in a cone along the node's -Z axis. Intensity is in candela
+  Spot,
+}
+
+// Cone shape of a spot light. Angles are in radians from the cone's axis,
+// with the light fading out between them
+#[derive(Clone, serde::Serialize)]
+pub struct Spot {
+  #[serde(rename = "innerConeAngle")]
+  #[serde(skip_serializing_if = "is_zero")]
+  pub inner_cone_angle: f64,
+  
+  #[serde(rename = "outerConeAngle")]
+  #[serde(skip_serializing_if = "is_quarter_pi")]
+  pub outer_cone_angle: f64,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct Light {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // Linear RGB
+  #[serde(skip_serializing_if = "is_white")]
+  pub color: [f64; 3],
+  
+  #[serde(skip_serializing_if = "is_one")]
+  pub intensity: f64,
+  
+  #[serde(rename = "type")]
+  pub type_: LightType,
+  
+  // Distance at which the light reaches zero, for point and spot lights.
+  // Infinite if not given
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub range: Option<f64>,
+  
+  // Required for spot lights, and not allowed on others
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub spot: Option<Spot>,
+}
+
+impl Light {
+  pub fn directional(color: [f64; 3], intensity: f64) -> Self {
+    Self {
+      name: String::from(""),
+      color,
+      intensity,
+      type_: LightType::Directional,
+      range: None,
+      spot: None,
+    }
+  }
+  
+  pub fn point(color: [f64; 3], intensity: f64, range: Option<f64>) -> Self {
+    let mut light = Self::directional(color, intensity);
+    light.type_ = LightType::Point;
+    light.range = range;
+    light
+  }
+  
+  pub fn spot(color: [f64; 3], intensity: f64, range: Option<f64>,
+    inner_cone_angle: f64, outer_cone_angle: f64,
+  ) -> Self {
+    let mut light = Self::point(color, intensity, range);
+    light.type_ = LightType::Spot;
+    light.spot = Some(Spot { inner_cone_angle, outer_cone_angle });
+    light
+  }
+  
+  // Color components and intensity can't be negative, range must be
+  // positive and only on point and spot lights, and spot lights need
+  // 0 <= inner < outer <= pi/2
+  pub fn validate(&self) -> Result<(), ErrorCode> {
+    let spot_valid = match (self.type_, &self.spot) {
+      (LightType::Spot, Some(spot)) => (0.0..spot.outer_cone_angle)
+        .contains(&spot.inner_cone_angle)
+        && spot.outer_cone_angle <= std::f64::consts::FRAC_PI_2,
+      (LightType::Spot, None) => false,
+      (_, spot) => spot.is_none(),
+    };
+    let range_valid = match self.range {
+      Some(range) => self.type_ != LightType::Directional && range > 0.0,
+      None => true,
+    };
+    
+    if !spot_valid || !range_valid || self.intensity < 0.0
+      || self.color.iter().any(|&c| c < 0.0) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+impl GLTF {
+  // Adds `light` to the document's KHR_lights_punctual lights, and marks the
+  // extension used. The light shines only once a node uses it (see
+  // set_node_light()). Fails if the light doesn't validate. Returns the
+  // light's index
+  pub fn add_punctual_light(&mut self, light: &Light,
+  ) -> Result<u32, ErrorCode> {
+    light.validate()?;
+    
+    // .unwrap() acceptable here because Light has no maps with non-string
+    // keys, which is the only way serialization to JSON can fail
+    let value = serde_json::to_value(light).unwrap();
+    
+    let extension = self.extensions.get_or_insert_with(serde_json::Map::new)
+      .entry(NAME).or_insert_with(|| serde_json::json!({ "lights": [] }));
+    let lights = match extension.get_mut("lights") {
+      Some(serde_json::Value::Array(lights)) => lights,
+      // Existing JSON in the wrong shape, probably from add_extension()
+      _ => return Err(ErrorCode::Value),
+    };
+    lights.push(value);
+    let index = lights.len() as u32 - 1;
+    
+    self.use_extension(NAME);
+    Ok(index)
+  }
+  
+  // Number of lights added with add_punctual_light()
+  pub fn punctual_light_count(&self) -> u32 {
+    self.extensions.as_ref()
+      .and_then(|e| e.get(NAME))
+      .and_then(|e| e.get("lights"))
+      .and_then(|lights| lights.as_array())
+      .map_or(0, |lights| lights.len() as u32)
+  }
+  
+  // Type of the light at node `node`, as written in the document ("point",
+  // "spot", or "directional"), if it has one that exists
+  pub(crate) fn node_light_type(&self, node: &Node) -> Option<&str> {
+    let light = node.extensions.as_ref()
+      .and_then(|e| e.get(NAME))
+      .and_then(|e| e.get("light"))
+      .and_then(|light| light.as_u64())?;
+    self.extensions.as_ref()
+      .and_then(|e| e.get(NAME))
+      .and_then(|e| e.get("lights"))
+      .and_then(|lights| lights.get(light as usize))
+      .and_then(|light| light.get("type"))
+      .and_then(|type_| type_.as_str())
+  }
+  
+  // Places light `light`, from add_punctual_light(), at node `node`,
+  // replacing any light already there
+  pub fn set_node_light(&mut self, node: u32, light: u32,
+  ) -> Result<(), ErrorCode> {
+    if light >= self.punctual_light_count() {
+      return Err(ErrorCode::Index);
+    }
+    
+    let node = self.nodes.get_mut(node as usize).ok_or(ErrorCode::Index)?;
+    node.extensions.get_or_insert_with(serde_json::Map::new).insert(
+      String::from(NAME), serde_json::json!({ "light": light }));
+    self.use_extension(NAME);
+    Ok(())
+  }
+}
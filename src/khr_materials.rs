@@ -0,0 +1,237 @@
+// Typed structs for the KHR_materials_* extensions. Attach them with
+// GLTF::add_material_extension(), or MaterialBuilder::extension() before
+// the material is added
+
+use crate::{Extension, ErrorCode, TextureInfo, Color4};
+
+fn is_zero(value: &f64) -> bool {
+  *value == 0.0
+}
+
+fn is_one(value: &f64) -> bool {
+  *value == 1.0
+}
+
+fn is_white(value: &[f64; 3]) -> bool {
+  *value == [1.0; 3]
+}
+
+// Anisotropic highlights, as on brushed metal
+#[derive(Clone, serde::Serialize)]
+pub struct Anisotropy {
+  // From 0 to 1
+  #[serde(rename = "anisotropyStrength")]
+  #[serde(skip_serializing_if = "is_zero")]
+  pub strength: f64,
+  
+  // Direction of the anisotropy in radians, counter-clockwise from the
+  // tangent
+  #[serde(rename = "anisotropyRotation")]
+  #[serde(skip_serializing_if = "is_zero")]
+  pub rotation: f64,
+  
+  // Red and green hold a direction, blue multiplies strength
+  #[serde(rename = "anisotropyTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub texture: Option<TextureInfo>,
+}
+
+impl Anisotropy {
+  pub fn new() -> Self {
+    Self { strength: 0.0, rotation: 0.0, texture: None }
+  }
+}
+
+impl Extension for Anisotropy {
+  const NAME: &'static str = "KHR_materials_anisotropy";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    if !(0.0..=1.0).contains(&self.strength) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+// Constant shading from the base color, ignoring lights. Has no properties
+#[derive(Clone, serde::Serialize)]
+pub struct Unlit {}
+
+impl Unlit {
+  pub fn new() -> Self {
+    Self {}
+  }
+}
+
+impl Extension for Unlit {
+  const NAME: &'static str = "KHR_materials_unlit";
+}
+
+// Scales emissive_factor past 1, for emitters bright enough to bloom
+#[derive(Clone, serde::Serialize)]
+pub struct EmissiveStrength {
+  // Must not be negative
+  #[serde(rename = "emissiveStrength")]
+  #[serde(skip_serializing_if = "is_one")]
+  pub emissive_strength: f64,
+}
+
+impl EmissiveStrength {
+  pub fn new() -> Self {
+    Self { emissive_strength: 1.0 }
+  }
+}
+
+impl Extension for EmissiveStrength {
+  const NAME: &'static str = "KHR_materials_emissive_strength";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    if !(0.0..).contains(&self.emissive_strength) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+fn is_default_ior(value: &f64) -> bool {
+  *value == 1.5
+}
+
+// Index of refraction, which sets how strong reflections are (and how much
+// transmitted light bends). 1.5 matches the core spec's fixed value
+#[derive(Clone, serde::Serialize)]
+pub struct Ior {
+  // 0 (a special case for infinite), or at least 1
+  #[serde(skip_serializing_if = "is_default_ior")]
+  pub ior: f64,
+}
+
+impl Ior {
+  pub fn new() -> Self {
+    Self { ior: 1.5 }
+  }
+}
+
+impl Extension for Ior {
+  const NAME: &'static str = "KHR_materials_ior";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    if self.ior != 0.0 && !(1.0..).contains(&self.ior) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+// Light passing through the surface, as in glass and thin plastic. Unlike
+// alpha blending, reflections stay at full strength
+#[derive(Clone, serde::Serialize)]
+pub struct Transmission {
+  // From 0 (opaque) to 1 (all light not reflected passes through)
+  #[serde(rename = "transmissionFactor")]
+  #[serde(skip_serializing_if = "is_zero")]
+  pub transmission_factor: f64,
+  
+  // Red channel multiplies the factor
+  #[serde(rename = "transmissionTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub transmission_texture: Option<TextureInfo>,
+}
+
+impl Transmission {
+  pub fn new() -> Self {
+    Self { transmission_factor: 0.0, transmission_texture: None }
+  }
+}
+
+impl Extension for Transmission {
+  const NAME: &'static str = "KHR_materials_transmission";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    if !(0.0..=1.0).contains(&self.transmission_factor) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+// Spreads refracted light into colors, as in gemstones. Only has an effect
+// alongside transmission or volume
+#[derive(Clone, serde::Serialize)]
+pub struct Dispersion {
+  // 20 / Abbe number, so 0 is no dispersion and common glass is around 0.3.
+  // Must not be negative
+  #[serde(skip_serializing_if = "is_zero")]
+  pub dispersion: f64,
+}
+
+impl Dispersion {
+  pub fn new() -> Self {
+    Self { dispersion: 0.0 }
+  }
+}
+
+impl Extension for Dispersion {
+  const NAME: &'static str = "KHR_materials_dispersion";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    if !(0.0..).contains(&self.dispersion) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+// The specular-glossiness workflow, for older engines that don't understand
+// metallic-roughness. Archived by Khronos, so only use it for those engines,
+// and keep pbrMetallicRoughness filled in as the fallback
+#[derive(Clone, serde::Serialize)]
+pub struct PBRSpecularGlossiness {
+  #[serde(rename = "diffuseFactor")]
+  #[serde(skip_serializing_if = "Color4::is_default")]
+  pub diffuse_factor: Color4,
+  
+  #[serde(rename = "specularFactor")]
+  #[serde(skip_serializing_if = "is_white")]
+  pub specular_factor: [f64; 3],
+  
+  // From 0 (rough) to 1 (smooth)
+  #[serde(rename = "glossinessFactor")]
+  #[serde(skip_serializing_if = "is_one")]
+  pub glossiness_factor: f64,
+  
+  #[serde(rename = "diffuseTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diffuse_texture: Option<TextureInfo>,
+  
+  // Specular color in RGB, glossiness in alpha
+  #[serde(rename = "specularGlossinessTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub specular_glossiness_texture: Option<TextureInfo>,
+}
+
+impl PBRSpecularGlossiness {
+  pub fn new() -> Self {
+    Self {
+      diffuse_factor: Color4::new(),
+      specular_factor: [1.0; 3],
+      glossiness_factor: 1.0,
+      diffuse_texture: None,
+      specular_glossiness_texture: None,
+    }
+  }
+}
+
+impl Extension for PBRSpecularGlossiness {
+  const NAME: &'static str = "KHR_materials_pbrSpecularGlossiness";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    let Color4 { r, g, b, a } = self.diffuse_factor;
+    if [r, g, b, a, self.glossiness_factor].iter()
+      .chain(self.specular_factor.iter())
+      .any(|x| !(0.0..=1.0).contains(x)) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,88 @@
+// Typed struct for KHR_texture_transform: offsets, rotates, and scales the
+// UVs a texture reference reads with, so one shared texture can be tiled or
+// shifted per material. Attach it with set_transform() on a texture info
+// before its material is added
+
+use crate::{
+  Extension, ErrorCode, TextureInfo, NormalTextureInfo, OcclusionTextureInfo,
+};
+
+fn is_zero(value: &f64) -> bool {
+  *value == 0.0
+}
+
+fn is_zero_2(value: &[f64; 2]) -> bool {
+  *value == [0.0; 2]
+}
+
+fn is_one_2(value: &[f64; 2]) -> bool {
+  *value == [1.0; 2]
+}
+
+// Applied to UVs as scale first, then rotation, then offset
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct TextureTransform {
+  #[serde(skip_serializing_if = "is_zero_2")]
+  pub offset: [f64; 2],
+  
+  // In radians, counter-clockwise in UV space
+  #[serde(skip_serializing_if = "is_zero")]
+  pub rotation: f64,
+  
+  #[serde(skip_serializing_if = "is_one_2")]
+  pub scale: [f64; 2],
+  
+  // Replaces the texture info's texCoord, for viewers with the extension
+  #[serde(rename = "texCoord")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub tex_coord: Option<u32>,
+}
+
+impl TextureTransform {
+  pub fn new() -> Self {
+    Self { offset: [0.0; 2], rotation: 0.0, scale: [1.0; 2], tex_coord: None }
+  }
+  
+  // Repeats the texture `u` times across and `v` times down
+  pub fn tiled(u: f64, v: f64) -> Self {
+    Self { scale: [u, v], ..Self::new() }
+  }
+}
+
+impl Extension for TextureTransform {
+  const NAME: &'static str = "KHR_texture_transform";
+  
+  fn validate(&self) -> Result<(), ErrorCode> {
+    let values = [self.offset[0], self.offset[1], self.rotation,
+      self.scale[0], self.scale[1]];
+    if values.iter().any(|x| !x.is_finite()) {
+      return Err(ErrorCode::Value);
+    }
+    Ok(())
+  }
+}
+
+impl TextureInfo {
+  // Attaches `transform`, replacing any earlier one. Fails if it has a
+  // non-finite value
+  pub fn set_transform(&mut self, transform: &TextureTransform,
+  ) -> Result<(), ErrorCode> {
+    crate::insert_extension(&mut self.extensions, transform)
+  }
+}
+
+impl NormalTextureInfo {
+  // Same as TextureInfo::set_transform()
+  pub fn set_transform(&mut self, transform: &TextureTransform,
+  ) -> Result<(), ErrorCode> {
+    crate::insert_extension(&mut self.extensions, transform)
+  }
+}
+
+impl OcclusionTextureInfo {
+  // Same as TextureInfo::set_transform()
+  pub fn set_transform(&mut self, transform: &TextureTransform,
+  ) -> Result<(), ErrorCode> {
+    crate::insert_extension(&mut self.extensions, transform)
+  }
+}
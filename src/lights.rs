@@ -0,0 +1,97 @@
+use crate::*;
+
+// Environment lighting from EXT_lights_image_based: diffuse light as
+// spherical harmonics, and specular light as a prefiltered cubemap
+#[derive(Clone, serde::Serialize)]
+pub struct ImageBasedLight {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  #[serde(skip_serializing_if = "Rotation::is_default")]
+  pub rotation: Rotation,
+  
+  pub intensity: f64,
+  
+  // Third order spherical harmonics of the irradiance, an RGB value per
+  // coefficient
+  #[serde(rename = "irradianceCoefficients")]
+  pub irradiance_coefficients: [[f64; 3]; 9],
+  
+  // Width of the top mip level's cube faces, in pixels
+  #[serde(rename = "specularImageSize")]
+  pub specular_image_size: u32,
+  
+  // One entry per mip level, from largest to smallest, each holding the
+  // images for the +X, -X, +Y, -Y, +Z, and -Z cube faces
+  #[serde(rename = "specularImages")]
+  pub specular_images: Vec<[u32; 6]>,
+}
+
+impl ImageBasedLight {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      rotation: Rotation::new(),
+      intensity: 1.0,
+      irradiance_coefficients: [[0.0; 3]; 9],
+      specular_image_size: 0,
+      specular_images: Vec::new(),
+    }
+  }
+}
+
+impl GLTF {
+  // Adds `light` to the document's EXT_lights_image_based lights, and marks
+  // the extension used and required (a PBR scene without its environment
+  // would render mostly black). Fails if any specular image doesn't exist.
+  // Returns the light's index, for set_scene_image_based_light()
+  pub fn add_image_based_light(&mut self, light: &ImageBasedLight,
+  ) -> Result<u32, ErrorCode> {
+    if light.specular_images.iter().flatten()
+      .any(|&image| image as usize >= self.images.len()) {
+      return Err(ErrorCode::Index);
+    }
+    if light.specular_image_size == 0 || light.specular_images.is_empty() {
+      return Err(ErrorCode::Value);
+    }
+    
+    // .unwrap() acceptable here because ImageBasedLight has no maps with
+    // non-string keys, which is the only way serialization to JSON can fail
+    let value = serde_json::to_value(light).unwrap();
+    
+    let extension = self.extensions.get_or_insert_with(serde_json::Map::new)
+      .entry("EXT_lights_image_based")
+      .or_insert_with(|| serde_json::json!({ "lights": [] }));
+    let lights = match extension.get_mut("lights") {
+      Some(serde_json::Value::Array(lights)) => lights,
+      // Existing JSON in the wrong shape, probably from add_extension()
+      _ => return Err(ErrorCode::Value),
+    };
+    lights.push(value);
+    let index = lights.len() as u32 - 1;
+    
+    self.require_extension("EXT_lights_image_based");
+    Ok(index)
+  }
+  
+  // Lights scene `scene` with image based light `light`, from
+  // add_image_based_light()
+  pub fn set_scene_image_based_light(&mut self, scene: u32, light: u32,
+  ) -> Result<(), ErrorCode> {
+    let lights = self.extensions.as_ref()
+      .and_then(|e| e.get("EXT_lights_image_based"))
+      .and_then(|e| e.get("lights"))
+      .and_then(|lights| lights.as_array())
+      .map_or(0, |lights| lights.len());
+    if light as usize >= lights {
+      return Err(ErrorCode::Index);
+    }
+    
+    let scene = self.scenes.get_mut(scene as usize).ok_or(ErrorCode::Index)?;
+    scene.extensions.get_or_insert_with(serde_json::Map::new).insert(
+      String::from("EXT_lights_image_based"), serde_json::json!({
+        "light": light,
+      }));
+    Ok(())
+  }
+}
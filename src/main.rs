@@ -70,6 +70,28 @@ struct ArgsForInspect {
   wasm: PathBuf,
 }
 
+// Reads the message left by a failed generation, if the module exports
+// last_error_message() and last_error_message_size() and the message isn't
+// empty
+fn read_error_message(instance: &wasmtime::Instance,
+  store: &mut wasmtime::Store<()>,
+) -> Option<String> {
+  let get_pointer = instance.get_typed_func::<(), i32>(&mut *store,
+    "last_error_message").ok()?;
+  let get_size = instance.get_typed_func::<(), i32>(&mut *store,
+    "last_error_message_size").ok()?;
+  
+  let pointer = get_pointer.call(&mut *store, ()).ok()? as usize;
+  let size = get_size.call(&mut *store, ()).ok()? as usize;
+  if size == 0 {
+    return None;
+  }
+  
+  let memory = instance.get_memory(&mut *store, "memory")?;
+  let bytes = memory.data(&*store).get(pointer..pointer + size)?;
+  Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
 fn gen(args: ArgsForGen) {
   let engine = wasmtime::Engine::default();
   
@@ -200,6 +222,9 @@ fn gen(args: ArgsForGen) {
     0 => {},
     e => {
       eprintln!("Error: model generation returned error code: {}", e);
+      if let Some(message) = read_error_message(&instance, &mut store) {
+        eprintln!("{}", message);
+      }
       std::process::exit(10);
     },
   }
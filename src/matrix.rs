@@ -0,0 +1,122 @@
+// 4x4 matrices in glTF's layout: 16 values, column by column, so the
+// translation is in elements 12 to 14
+
+pub(crate) type Matrix = [f64; 16];
+
+pub(crate) const IDENTITY: Matrix = [
+  1.0, 0.0, 0.0, 0.0,
+  0.0, 1.0, 0.0, 0.0,
+  0.0, 0.0, 1.0, 0.0,
+  0.0, 0.0, 0.0, 1.0,
+];
+
+// `a` applied after `b`
+pub(crate) fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+  let mut product = [0.0; 16];
+  for column in 0..4 {
+    for row in 0..4 {
+      product[column * 4 + row] = (0..4)
+        .map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+    }
+  }
+  product
+}
+
+// Inverse of a matrix whose bottom row is 0, 0, 0, 1, as every node
+// transform is. None if it squashes space flat, as a zero scale does
+pub(crate) fn affine_inverse(m: &Matrix) -> Option<Matrix> {
+  let a = |row: usize, column: usize| m[column * 4 + row];
+  
+  let cofactors = [
+    [a(1, 1) * a(2, 2) - a(1, 2) * a(2, 1),
+      a(1, 2) * a(2, 0) - a(1, 0) * a(2, 2),
+      a(1, 0) * a(2, 1) - a(1, 1) * a(2, 0)],
+    [a(0, 2) * a(2, 1) - a(0, 1) * a(2, 2),
+      a(0, 0) * a(2, 2) - a(0, 2) * a(2, 0),
+      a(0, 1) * a(2, 0) - a(0, 0) * a(2, 1)],
+    [a(0, 1) * a(1, 2) - a(0, 2) * a(1, 1),
+      a(0, 2) * a(1, 0) - a(0, 0) * a(1, 2),
+      a(0, 0) * a(1, 1) - a(0, 1) * a(1, 0)],
+  ];
+  let determinant = a(0, 0) * cofactors[0][0] + a(0, 1) * cofactors[0][1]
+    + a(0, 2) * cofactors[0][2];
+  if determinant.abs() < 1e-12 || !determinant.is_finite() {
+    return None;
+  }
+  
+  // The inverse is the transposed cofactors over the determinant
+  let mut inverse = IDENTITY;
+  for row in 0..3 {
+    for column in 0..3 {
+      inverse[column * 4 + row] = cofactors[column][row] / determinant;
+    }
+  }
+  for row in 0..3 {
+    inverse[12 + row] = -(0..3)
+      .map(|k| inverse[k * 4 + row] * m[12 + k]).sum::<f64>();
+  }
+  Some(inverse)
+}
+
+// Splits a node transform into translation, rotation (as an x, y, z, w
+// quaternion), and scale, the reverse of Node::local_matrix(). A mirroring
+// matrix gets a negative X scale. None if the bottom row isn't 0, 0, 0, 1,
+// or the matrix is flat or sheared, since TRS can't express those
+pub(crate) fn decompose(m: &Matrix) -> Option<([f64; 3], [f64; 4], [f64; 3])>
+{
+  const TOLERANCE: f64 = 1e-6;
+  
+  if [m[3], m[7], m[11], m[15] - 1.0].iter().any(|x| x.abs() > TOLERANCE) {
+    return None;
+  }
+  
+  let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+  let cross = |a: [f64; 3], b: [f64; 3]| [
+    a[1] * b[2] - a[2] * b[1],
+    a[2] * b[0] - a[0] * b[2],
+    a[0] * b[1] - a[1] * b[0],
+  ];
+  let column = |c: usize| [m[4 * c], m[4 * c + 1], m[4 * c + 2]];
+  let columns = [column(0), column(1), column(2)];
+  
+  let determinant = dot(columns[0], cross(columns[1], columns[2]));
+  if determinant.abs() < 1e-12 || !determinant.is_finite() {
+    return None;
+  }
+  let mut scale = columns.map(|c| dot(c, c).sqrt());
+  if determinant < 0.0 {
+    scale[0] = -scale[0];
+  }
+  
+  let r = [0, 1, 2].map(|c| columns[c].map(|x| x / scale[c]));
+  for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+    if dot(r[a], r[b]).abs() > TOLERANCE {
+      return None;
+    }
+  }
+  
+  // Element at row `i`, column `j` of the rotation
+  let e = |i: usize, j: usize| r[j][i];
+  let trace = e(0, 0) + e(1, 1) + e(2, 2);
+  // Divides by the largest of the four components' magnitudes, for accuracy
+  let [x, y, z, w] = if trace > 0.0 {
+    let s = (trace + 1.0).sqrt() * 2.0;
+    [(e(2, 1) - e(1, 2)) / s, (e(0, 2) - e(2, 0)) / s,
+      (e(1, 0) - e(0, 1)) / s, s / 4.0]
+  } else if e(0, 0) > e(1, 1) && e(0, 0) > e(2, 2) {
+    let s = (1.0 + e(0, 0) - e(1, 1) - e(2, 2)).sqrt() * 2.0;
+    [s / 4.0, (e(0, 1) + e(1, 0)) / s, (e(0, 2) + e(2, 0)) / s,
+      (e(2, 1) - e(1, 2)) / s]
+  } else if e(1, 1) > e(2, 2) {
+    let s = (1.0 + e(1, 1) - e(0, 0) - e(2, 2)).sqrt() * 2.0;
+    [(e(0, 1) + e(1, 0)) / s, s / 4.0, (e(1, 2) + e(2, 1)) / s,
+      (e(0, 2) - e(2, 0)) / s]
+  } else {
+    let s = (1.0 + e(2, 2) - e(0, 0) - e(1, 1)).sqrt() * 2.0;
+    [(e(0, 2) + e(2, 0)) / s, (e(1, 2) + e(2, 1)) / s, s / 4.0,
+      (e(1, 0) - e(0, 1)) / s]
+  };
+  let length = (x * x + y * y + z * z + w * w).sqrt();
+  
+  Some(([m[12], m[13], m[14]], [x, y, z, w].map(|c| c / length), scale))
+}
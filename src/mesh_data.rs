@@ -0,0 +1,485 @@
+use crate::*;
+
+// A triangle list mesh as plain vertex and index arrays, for generating
+// geometry before it is packed into buffers. Optional streams, if present,
+// have one entry per position. See the shapes module for ready-made solids
+#[derive(Clone, Debug)]
+pub struct MeshData {
+  pub positions: Vec<[f32; 3]>,
+  pub normals: Option<Vec<[f32; 3]>>,
+  pub uvs: Option<Vec<[f32; 2]>>,
+  // Linear RGBA, written as COLOR_0
+  pub colors: Option<Vec<[f32; 4]>>,
+  // XYZ along increasing U, and W (1 or -1) giving the bitangent's side. See
+  // compute_tangents()
+  pub tangents: Option<Vec<[f32; 4]>>,
+  // Three per triangle, counter-clockwise when seen from the front
+  pub indices: Vec<u32>,
+}
+
+impl MeshData {
+  pub fn new() -> Self {
+    Self {
+      positions: Vec::new(),
+      normals: None,
+      uvs: None,
+      colors: None,
+      tangents: None,
+      indices: Vec::new(),
+    }
+  }
+  
+  // Fails if the indices don't form a triangle list into the positions, or an
+  // optional stream's length doesn't match the positions
+  pub fn check(&self) -> Result<(), ErrorCode> {
+    geometry::check_triangle_list(&self.indices, self.positions.len(), &[
+      self.normals.as_ref().map(|n| n.len()),
+      self.uvs.as_ref().map(|uv| uv.len()),
+      self.colors.as_ref().map(|c| c.len()),
+      self.tangents.as_ref().map(|t| t.len()),
+    ])
+  }
+  
+  // Appends a vertex, returning its index for push_triangle(). The first
+  // vertex decides which optional streams the mesh has, and later ones must
+  // give exactly those. Fails without changing anything otherwise, or if
+  // the mesh has tangents, which should be computed once every vertex is in
+  pub fn push_vertex(&mut self, position: [f32; 3], normal: Option<[f32; 3]>,
+    uv: Option<[f32; 2]>, color: Option<[f32; 4]>,
+  ) -> Result<u32, ErrorCode> {
+    if self.positions.is_empty() {
+      self.normals = normal.map(|_| Vec::new());
+      self.uvs = uv.map(|_| Vec::new());
+      self.colors = color.map(|_| Vec::new());
+      self.tangents = None;
+    } else if normal.is_some() != self.normals.is_some()
+      || uv.is_some() != self.uvs.is_some()
+      || color.is_some() != self.colors.is_some()
+      || self.tangents.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    
+    self.positions.push(position);
+    self.normals.iter_mut().zip(normal).for_each(|(n, normal)| n.push(normal));
+    self.uvs.iter_mut().zip(uv).for_each(|(uvs, uv)| uvs.push(uv));
+    self.colors.iter_mut().zip(color).for_each(|(c, color)| c.push(color));
+    Ok(self.positions.len() as u32 - 1)
+  }
+  
+  // Adds a triangle between existing vertices, counter-clockwise when seen
+  // from the front
+  pub fn push_triangle(&mut self, triangle: [u32; 3]) -> Result<(), ErrorCode> {
+    if triangle.iter().any(|&i| i as usize >= self.positions.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    self.indices.extend_from_slice(&triangle);
+    Ok(())
+  }
+  
+  // Adds a quad between existing vertices, counter-clockwise when seen from
+  // the front, as two triangles split along its first vertex's diagonal
+  pub fn push_quad(&mut self, [a, b, c, d]: [u32; 4],
+  ) -> Result<(), ErrorCode> {
+    if [a, b, c, d].iter().any(|&i| i as usize >= self.positions.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+    Ok(())
+  }
+  
+  // Appends `other`'s vertices and triangles, so both are drawn as one.
+  // Fails without changing anything if only one of the two has a stream,
+  // unless this mesh has no vertices yet
+  pub fn merge(&mut self, other: &MeshData) -> Result<(), ErrorCode> {
+    if self.positions.is_empty() && self.indices.is_empty() {
+      *self = other.clone();
+      return Ok(());
+    }
+    if self.normals.is_some() != other.normals.is_some()
+      || self.uvs.is_some() != other.uvs.is_some()
+      || self.colors.is_some() != other.colors.is_some()
+      || self.tangents.is_some() != other.tangents.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    
+    let offset = self.positions.len() as u32;
+    self.positions.extend_from_slice(&other.positions);
+    if let (Some(a), Some(b)) = (&mut self.normals, &other.normals) {
+      a.extend_from_slice(b);
+    }
+    if let (Some(a), Some(b)) = (&mut self.uvs, &other.uvs) {
+      a.extend_from_slice(b);
+    }
+    if let (Some(a), Some(b)) = (&mut self.colors, &other.colors) {
+      a.extend_from_slice(b);
+    }
+    if let (Some(a), Some(b)) = (&mut self.tangents, &other.tangents) {
+      a.extend_from_slice(b);
+    }
+    self.indices.extend(other.indices.iter().map(|&i| i + offset));
+    Ok(())
+  }
+  
+  // Moves every vertex by `offset`
+  pub fn translate(&mut self, offset: [f32; 3]) {
+    for position in self.positions.iter_mut() {
+      for k in 0..3 {
+        position[k] += offset[k];
+      }
+    }
+  }
+  
+  // Transforms positions by `matrix` (column by column, as from
+  // Node::local_matrix()) and normals and tangents to match. Mirroring matrices
+  // also reverse each triangle's winding, so fronts stay in front, and flip
+  // tangents' W. Fails without changing anything if the matrix squashes space
+  // flat or isn't affine
+  pub fn transform(&mut self, matrix: &[f64; 16]) -> Result<(), ErrorCode> {
+    if [matrix[3], matrix[7], matrix[11], matrix[15]] != [0.0, 0.0, 0.0, 1.0]
+    {
+      return Err(ErrorCode::Value);
+    }
+    let inverse = matrix::affine_inverse(matrix).ok_or(ErrorCode::Value)?;
+    
+    let apply = |m: &[f64; 16], v: [f32; 3], w: f64| [0, 1, 2].map(|row| {
+      (0..3).map(|k| m[k * 4 + row] * v[k] as f64).sum::<f64>()
+        + m[12 + row] * w
+    });
+    for position in self.positions.iter_mut() {
+      *position = apply(matrix, *position, 1.0).map(|x| x as f32);
+    }
+    
+    // Normals go through the inverse transpose, so they stay perpendicular
+    // to the surface under non-uniform scale
+    let mut inverse_transpose = [0.0; 16];
+    for row in 0..3 {
+      for column in 0..3 {
+        inverse_transpose[column * 4 + row] = inverse[row * 4 + column];
+      }
+    }
+    for normal in self.normals.iter_mut().flatten() {
+      let n = apply(&inverse_transpose, *normal, 0.0);
+      let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+      if length > 0.0 {
+        *normal = n.map(|x| (x / length) as f32);
+      }
+    }
+    
+    let column = |c: usize| [matrix[4 * c], matrix[4 * c + 1],
+      matrix[4 * c + 2]];
+    let [x, y, z] = [column(0), column(1), column(2)];
+    let determinant = x[0] * (y[1] * z[2] - y[2] * z[1])
+      - y[0] * (x[1] * z[2] - x[2] * z[1]) + z[0] * (x[1] * y[2] - x[2] * y[1]);
+    let handedness = determinant.signum() as f32;
+    
+    // Tangents lie along the surface, so they transform as positions do,
+    // without the translation
+    for tangent in self.tangents.iter_mut().flatten() {
+      let t = apply(matrix, [tangent[0], tangent[1], tangent[2]], 0.0);
+      let length = (t[0] * t[0] + t[1] * t[1] + t[2] * t[2]).sqrt();
+      if length > 0.0 {
+        let [x, y, z] = t.map(|x| (x / length) as f32);
+        *tangent = [x, y, z, tangent[3] * handedness];
+      }
+    }
+    
+    if determinant < 0.0 {
+      for triangle in self.indices.chunks_mut(3) {
+        triangle.swap(1, 2);
+      }
+    }
+    
+    Ok(())
+  }
+  
+  // Gives each triangle its own face's normal, for a faceted look. Vertices
+  // shared by triangles facing different ways are split, copying their
+  // other streams. Drops any tangents, as they follow the normals. Fails
+  // without changing anything if the data doesn't pass check()
+  pub fn compute_flat_normals(&mut self) -> Result<(), ErrorCode> {
+    self.check()?;
+    
+    let corner_normals: Vec<[f32; 3]> = self.face_normals().iter()
+      .flat_map(|unit| [unit.unwrap_or([0.0, 1.0, 0.0]); 3]).collect();
+    self.set_corner_normals(&corner_normals);
+    Ok(())
+  }
+  
+  // Smooths normals across edges between triangles facing within
+  // `angle_threshold_deg` degrees of each other, and keeps sharper edges
+  // creased. Vertices at the same position are smoothed together even if
+  // other streams differ, so UV seams don't show. Each face counts in
+  // proportion to its angle at the vertex, so how a surface happens to be
+  // split into triangles doesn't skew the result. Vertices on a crease are
+  // split, copying their other streams. Drops any tangents, as they follow
+  // the normals. Fails without changing anything if the data doesn't pass
+  // check() or the threshold is negative
+  pub fn compute_smooth_normals(&mut self, angle_threshold_deg: f32,
+  ) -> Result<(), ErrorCode> {
+    self.check()?;
+    if angle_threshold_deg.is_nan() || angle_threshold_deg < 0.0 {
+      return Err(ErrorCode::Value);
+    }
+    // A little slack, so faces meant to be coplanar smooth together despite
+    // rounding
+    let min_dot = angle_threshold_deg.to_radians().cos().min(1.0) - 1e-6;
+    
+    let faces = self.face_normals();
+    let angles = self.corner_angles();
+    // Corners at each position
+    let key = |position: [f32; 3]| position.map(|x| (x + 0.0).to_bits());
+    let mut around: std::collections::HashMap<[u32; 3], Vec<usize>> =
+      std::collections::HashMap::new();
+    for (corner, &v) in self.indices.iter().enumerate() {
+      around.entry(key(self.positions[v as usize])).or_default().push(corner);
+    }
+    
+    let corner_normals: Vec<[f32; 3]> = self.indices.iter().enumerate()
+      .map(|(corner, &v)| {
+        let unit = faces[corner / 3];
+        let mut sum = [0.0; 3];
+        for &other in around[&key(self.positions[v as usize])].iter() {
+          // Zero-area faces take their neighbors' normals, and add nothing
+          let Some(other_unit) = faces[other / 3] else {
+            continue;
+          };
+          if unit.is_none_or(|unit| geometry::dot(unit, other_unit) >= min_dot)
+          {
+            sum = [0, 1, 2].map(|k| sum[k] + other_unit[k] * angles[other]);
+          }
+        }
+        geometry::normalize(sum).or(unit).unwrap_or([0.0, 1.0, 0.0])
+      }).collect();
+    self.set_corner_normals(&corner_normals);
+    Ok(())
+  }
+  
+  // Each triangle's unit normal, or None if it has no area
+  fn face_normals(&self) -> Vec<Option<[f32; 3]>> {
+    self.indices.chunks(3).map(|triangle| {
+      let [a, b, c] = [0, 1, 2].map(|k| self.positions[triangle[k] as usize]);
+      geometry::normalize(geometry::cross(geometry::sub(b, a),
+        geometry::sub(c, a)))
+    }).collect()
+  }
+  
+  // Each triangle corner's angle in radians, in index order
+  fn corner_angles(&self) -> Vec<f32> {
+    self.indices.chunks(3).flat_map(|triangle| {
+      let [a, b, c] = [0, 1, 2].map(|k| self.positions[triangle[k] as usize]);
+      [(a, b, c), (b, c, a), (c, a, b)].map(|(at, next, previous)| {
+        match (geometry::normalize(geometry::sub(next, at)),
+          geometry::normalize(geometry::sub(previous, at))) {
+          (Some(u), Some(v)) => geometry::dot(u, v).clamp(-1.0, 1.0).acos(),
+          _ => 0.0,
+        }
+      })
+    }).collect()
+  }
+  
+  // Sets the normal of every triangle corner, in index order, splitting
+  // vertices whose corners need different normals. Vertices no triangle
+  // uses keep any normal they had
+  fn set_corner_normals(&mut self, corner_normals: &[[f32; 3]]) {
+    let mut normals: Vec<Option<[f32; 3]>> = vec![None; self.positions.len()];
+    // Split copies, by original vertex and normal
+    let mut copies = std::collections::HashMap::new();
+    
+    for (corner, &normal) in corner_normals.iter().enumerate() {
+      let v = self.indices[corner] as usize;
+      match normals[v] {
+        None => normals[v] = Some(normal),
+        Some(existing) if existing == normal => {},
+        Some(_) => {
+          let copy = match copies.get(&(v, normal.map(f32::to_bits))) {
+            Some(&copy) => copy,
+            None => {
+              let copy = self.copy_vertex(v);
+              normals.push(Some(normal));
+              copies.insert((v, normal.map(f32::to_bits)), copy);
+              copy
+            },
+          };
+          self.indices[corner] = copy;
+        },
+      }
+    }
+    
+    let old = self.normals.take();
+    self.normals = Some(normals.iter().enumerate().map(|(v, normal)| {
+      normal.or_else(|| old.as_ref().map(|old| old[v]))
+        .unwrap_or([0.0, 1.0, 0.0])
+    }).collect());
+    self.tangents = None;
+  }
+  
+  // Fills TANGENT from the normals and UVs, in the way MikkTSpace (the
+  // convention Blender, Unity, and most bakers use) does, so normal maps
+  // baked against it shade without seams. Each corner's tangent follows
+  // increasing U across its triangle, and vertices are split where mirrored
+  // UVs meet, copying their other streams. Tangents follow glTF's UV
+  // orientation, with V down the image and the normal map's green channel
+  // up it. Fails without changing anything if there are no normals or UVs,
+  // or the data doesn't pass check()
+  pub fn compute_tangents(&mut self) -> Result<(), ErrorCode> {
+    self.check()?;
+    let (Some(normals), Some(uvs)) = (&self.normals, &self.uvs) else {
+      return Err(ErrorCode::Value);
+    };
+    
+    let angles = self.corner_angles();
+    // Summed tangent directions at each vertex, kept apart for faces with
+    // normal and mirrored UVs
+    let mut sums = std::collections::HashMap::new();
+    let mut corner_signs = Vec::with_capacity(self.indices.len());
+    for (t, triangle) in self.indices.chunks(3).enumerate() {
+      let [p0, p1, p2] = [0, 1, 2]
+        .map(|k| self.positions[triangle[k] as usize]);
+      let [uv0, uv1, uv2] = [0, 1, 2].map(|k| uvs[triangle[k] as usize]);
+      let (e1, e2) = (geometry::sub(p1, p0), geometry::sub(p2, p0));
+      let (du1, dv1) = (uv1[0] - uv0[0], uv1[1] - uv0[1]);
+      let (du2, dv2) = (uv2[0] - uv0[0], uv2[1] - uv0[1]);
+      
+      // Directions of increasing U and V over the triangle. Faces with no
+      // UV area still get a side, but add nothing
+      let r = du1 * dv2 - du2 * dv1;
+      let (tangent, bitangent) = match r.abs() > f32::EPSILON {
+        true => ([0, 1, 2].map(|k| (e1[k] * dv2 - e2[k] * dv1) / r),
+          [0, 1, 2].map(|k| (e2[k] * du1 - e1[k] * du2) / r)),
+        false => ([0.0; 3], [0.0; 3]),
+      };
+      
+      for (k, &v) in triangle.iter().enumerate() {
+        let normal = normals[v as usize];
+        // Bitangents point up the image, toward decreasing V
+        let sign = match geometry::dot(geometry::cross(normal, tangent),
+          bitangent) > 0.0 {
+          true => -1.0,
+          false => 1.0,
+        };
+        let weight = angles[3 * t + k];
+        let sum = sums.entry((v, sign as i8)).or_insert([0.0; 3]);
+        *sum = [0, 1, 2].map(|i| sum[i] + tangent[i] * weight);
+        corner_signs.push(sign);
+      }
+    }
+    
+    let mut tangents: Vec<Option<[f32; 4]>> = vec![None; self.positions.len()];
+    let mut copies = std::collections::HashMap::new();
+    for (corner, &sign) in corner_signs.iter().enumerate() {
+      let v = self.indices[corner];
+      // .unwrap() acceptable here because check() confirmed there's a normal
+      // for every vertex
+      let normal = self.normals.as_ref().unwrap()[v as usize];
+      let tangent = orthogonal_tangent(normal, sums[&(v, sign as i8)]);
+      let tangent = [tangent[0], tangent[1], tangent[2], sign];
+      
+      let target = match tangents[v as usize] {
+        None => v,
+        Some(existing) if existing[3] == sign => v,
+        Some(_) => *copies.entry(v).or_insert_with(|| {
+          tangents.push(None);
+          self.copy_vertex(v as usize)
+        }),
+      };
+      tangents[target as usize] = Some(tangent);
+      self.indices[corner] = target;
+    }
+    
+    self.tangents = Some(tangents.iter()
+      .map(|t| t.unwrap_or([1.0, 0.0, 0.0, 1.0])).collect());
+    Ok(())
+  }
+  
+  // Appends a copy of vertex `v` from every stream, returning the copy's
+  // index
+  fn copy_vertex(&mut self, v: usize) -> u32 {
+    self.positions.push(self.positions[v]);
+    if let Some(normals) = &mut self.normals {
+      normals.push(normals[v]);
+    }
+    if let Some(uvs) = &mut self.uvs {
+      uvs.push(uvs[v]);
+    }
+    if let Some(colors) = &mut self.colors {
+      colors.push(colors[v]);
+    }
+    if let Some(tangents) = &mut self.tangents {
+      tangents.push(tangents[v]);
+    }
+    self.positions.len() as u32 - 1
+  }
+  
+  // Packs the mesh through `builder` as a triangle list primitive, for the
+  // caller to give a material and add to a mesh. Named as push_primitive()
+  // names its primitives, if the builder has debug names on. Fails without
+  // writing anything if the data doesn't pass check()
+  pub fn bake_primitive(&self, builder: &mut BufferBuilder,
+  ) -> Result<MeshPrimitive, ErrorCode> {
+    self.check()?;
+    
+    let mut primitive = builder.write_primitive(&self.positions,
+      self.normals.as_deref(), self.uvs.as_deref(), Some(&self.indices))?;
+    if let Some(colors) = &self.colors {
+      primitive.attributes.color_0 = Some(builder.push_attributes(colors)?);
+    }
+    if let Some(tangents) = &self.tangents {
+      primitive.attributes.tangent = Some(builder.push_attributes(tangents)?);
+    }
+    builder.name_next_primitive(&primitive);
+    Ok(primitive)
+  }
+  
+  // Packs the mesh into one new buffer added to `gltf`, as a mesh with one
+  // primitive drawn with `material`. Returns the mesh, for the caller to add
+  // to gltf.meshes, and the new buffer's contents
+  pub fn bake(&self, gltf: &mut GLTF, material: Option<u32>,
+  ) -> Result<(Mesh, Vec<u8>), ErrorCode> {
+    self.check()?;
+    
+    let mut bytes = Vec::new();
+    let mut builder = BufferBuilder::with_new_buffer(gltf, &mut bytes);
+    let mut primitive = self.bake_primitive(&mut builder)?;
+    primitive.material = material;
+    
+    let mut mesh = Mesh::new();
+    mesh.primitives.push(primitive);
+    Ok((mesh, bytes))
+  }
+}
+
+impl GLTF {
+  // Packs `data` into buffer `buffer`, whose binary contents are `bytes`, as
+  // a new mesh with one primitive drawn with `material`. Fails without
+  // adding anything if the data doesn't pass MeshData::check() or the buffer
+  // doesn't exist. Returns the mesh's index
+  pub fn add_mesh_data(&mut self, data: &MeshData, material: Option<u32>,
+    buffer: u32, bytes: &mut Vec<u8>,
+  ) -> Result<u32, ErrorCode> {
+    let mut builder = BufferBuilder::new(self, buffer, bytes)?;
+    let mut primitive = data.bake_primitive(&mut builder)?;
+    primitive.material = material;
+    
+    let mut mesh = Mesh::new();
+    mesh.primitives.push(primitive);
+    self.meshes.push(mesh);
+    Ok(self.meshes.len() as u32 - 1)
+  }
+}
+
+// `tangent` with any part along `normal` removed, as a unit vector. Falls
+// back to any direction perpendicular to the normal if nothing is left
+fn orthogonal_tangent(normal: [f32; 3], tangent: [f32; 3]) -> [f32; 3] {
+  let along = geometry::dot(normal, tangent);
+  let projected = [0, 1, 2].map(|k| tangent[k] - normal[k] * along);
+  geometry::normalize(projected).unwrap_or_else(|| {
+    let axis = match normal[0].abs() < 0.9 {
+      true => [1.0, 0.0, 0.0],
+      false => [0.0, 1.0, 0.0],
+    };
+    geometry::normalize(geometry::cross(axis, normal))
+      .unwrap_or([1.0, 0.0, 0.0])
+  })
+}
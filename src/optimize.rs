@@ -0,0 +1,365 @@
+use crate::*;
+
+// Which passes GLTF::optimize() runs. new() turns everything on
+#[derive(Clone, Debug)]
+pub struct OptimizeOptions {
+  // Sets each buffer's byteLength from its actual contents. Runs first, as
+  // consolidation trusts byteLength
+  pub fix_buffer_lengths: bool,
+  pub prune_orphans: bool,
+  pub dedup_materials: bool,
+  pub dedup_accessors: bool,
+  pub strip_names: bool,
+  pub consolidate_buffers: bool,
+  // Shares identical buffer view contents and drops bytes no view uses, in
+  // each buffer (see dedup_buffer_data())
+  pub compact_buffers: bool,
+  // Decimal places to round JSON floats to, if any (see round_floats())
+  pub round_floats: Option<u32>,
+}
+
+impl OptimizeOptions {
+  pub fn new() -> Self {
+    Self {
+      fix_buffer_lengths: true,
+      prune_orphans: true,
+      dedup_materials: true,
+      dedup_accessors: true,
+      strip_names: true,
+      consolidate_buffers: true,
+      compact_buffers: true,
+      round_floats: Some(6),
+    }
+  }
+}
+
+// What GLTF::optimize() changed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptimizeReport {
+  pub meshes_removed: u32,
+  pub materials_removed: u32,
+  pub accessors_removed: u32,
+  pub buffer_views_removed: u32,
+  pub buffers_removed: u32,
+  // Total size of the buffers' contents before and after
+  pub bytes_before: u64,
+  pub bytes_after: u64,
+}
+
+impl OptimizeReport {
+  pub fn bytes_saved(&self) -> u64 {
+    self.bytes_before.saturating_sub(self.bytes_after)
+  }
+}
+
+impl GLTF {
+  // Prepares a document for shipping by running the passes turned on in
+  // `opts`, in this order: fix buffer lengths, prune orphans, dedup
+  // materials, dedup accessors (pruning again after, as merged accessors
+  // can leave buffer views unused), strip names, consolidate buffers,
+  // compact buffers, round floats. `buffers` holds the buffers' contents,
+  // and is replaced by the single combined buffer if consolidating. Fails if
+  // a pass does, which can leave earlier passes applied
+  pub fn optimize(&mut self, buffers: &mut Vec<Vec<u8>>,
+    opts: OptimizeOptions,
+  ) -> Result<OptimizeReport, ErrorCode> {
+    let count = |gltf: &GLTF| [gltf.meshes.len(), gltf.materials.len(),
+      gltf.accessors.len(), gltf.buffer_views.len(), gltf.buffers.len()];
+    let before = count(self);
+    let bytes_before = buffers.iter().map(|b| b.len() as u64).sum();
+    
+    if opts.fix_buffer_lengths {
+      self.fix_buffer_lengths(buffers)?;
+    }
+    if opts.prune_orphans {
+      self.prune_orphans()?;
+    }
+    if opts.dedup_materials {
+      self.dedup_materials();
+    }
+    if opts.dedup_accessors && self.dedup_accessors() > 0
+      && opts.prune_orphans {
+      self.prune_orphans()?;
+    }
+    if opts.strip_names {
+      self.strip_names();
+    }
+    if opts.consolidate_buffers && self.buffers.len() > 1 {
+      let slices: Vec<&[u8]> = buffers.iter().map(|b| b.as_slice()).collect();
+      let combined = self.consolidate_buffers(&slices)?;
+      *buffers = vec![combined];
+    }
+    if opts.compact_buffers {
+      for (i, bytes) in buffers.iter_mut().enumerate()
+        .take(self.buffers.len()) {
+        self.dedup_buffer_data(i as u32, bytes, true)?;
+      }
+    }
+    if let Some(decimals) = opts.round_floats {
+      self.round_floats(decimals);
+    }
+    
+    let after = count(self);
+    let removed = |i: usize| before[i].saturating_sub(after[i]) as u32;
+    Ok(OptimizeReport {
+      meshes_removed: removed(0),
+      materials_removed: removed(1),
+      accessors_removed: removed(2),
+      buffer_views_removed: removed(3),
+      buffers_removed: removed(4),
+      bytes_before,
+      bytes_after: buffers.iter().map(|b| b.len() as u64).sum(),
+    })
+  }
+  
+  // Sets each buffer's byteLength to the length of its contents in
+  // `buffers`. Fails, changing nothing, if any buffer's contents are missing
+  pub fn fix_buffer_lengths(&mut self, buffers: &[Vec<u8>],
+  ) -> Result<(), ErrorCode> {
+    if buffers.len() < self.buffers.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    for (buffer, bytes) in self.buffers.iter_mut().zip(buffers.iter()) {
+      buffer.byte_length = bytes.len() as u32;
+    }
+    Ok(())
+  }
+  
+  // Removes meshes no node uses, materials no remaining primitive uses
+  // (directly, or through MSFT_lod levels of a material that is used),
+  // accessors no remaining primitive, animation, skin, or instancing node
+  // uses, and buffer views no remaining accessor or image uses, renumbering
+  // references to the rest. Nodes and buffers are kept, and buffer contents
+  // aren't touched (see dedup_buffer_data() to drop the bytes). Fails without
+  // changing anything if any reference points at an element that doesn't
+  // exist
+  pub fn prune_orphans(&mut self) -> Result<(), ErrorCode> {
+    fn mark(used: &mut [bool], index: u32) -> Result<(), ErrorCode> {
+      *used.get_mut(index as usize).ok_or(ErrorCode::Index)? = true;
+      Ok(())
+    }
+    
+    let mut used_meshes = vec![false; self.meshes.len()];
+    for mesh in self.nodes.iter().filter_map(|node| node.mesh) {
+      mark(&mut used_meshes, mesh)?;
+    }
+    
+    let mut used_materials = vec![false; self.materials.len()];
+    let mut used_accessors = vec![false; self.accessors.len()];
+    for (mesh, _) in self.meshes.iter().zip(used_meshes.iter())
+      .filter(|(_, &used)| used) {
+      for primitive in mesh.primitives.iter() {
+        if let Some(material) = primitive.material {
+          mark(&mut used_materials, material)?;
+        }
+        for accessor in primitive.attributes.named().into_iter()
+          .filter_map(|(_, a)| a).chain(primitive.indices) {
+          mark(&mut used_accessors, accessor)?;
+        }
+      }
+    }
+    // LOD levels are marked as they're found, so levels of levels are too
+    let mut lods: Vec<u32> = (0..self.materials.len() as u32)
+      .filter(|&i| used_materials[i as usize]).collect();
+    while let Some(material) = lods.pop() {
+      for lod in lod_ids(&self.materials[material as usize].extensions) {
+        if !*used_materials.get(lod as usize).ok_or(ErrorCode::Index)? {
+          used_materials[lod as usize] = true;
+          lods.push(lod);
+        }
+      }
+    }
+    for sampler in self.animations.iter().flat_map(|a| a.samplers.iter()) {
+      mark(&mut used_accessors, sampler.input)?;
+      mark(&mut used_accessors, sampler.output)?;
+    }
+    for skin in self.skins.iter() {
+      if let Some(matrices) = skin.inverse_bind_matrices {
+        mark(&mut used_accessors, matrices)?;
+      }
+    }
+    for node in self.nodes.iter() {
+      for accessor in instancing::instance_accessors(node) {
+        mark(&mut used_accessors, accessor)?;
+      }
+    }
+    
+    let mut used_views = vec![false; self.buffer_views.len()];
+    for (accessor, _) in self.accessors.iter().zip(used_accessors.iter())
+      .filter(|(_, &used)| used) {
+      if let Some(view) = accessor.buffer_view {
+        mark(&mut used_views, view)?;
+      }
+      for view in accessor.sparse.iter().flat_map(|s| s.buffer_views()) {
+        mark(&mut used_views, view)?;
+      }
+    }
+    for view in self.images.iter().filter_map(|image| image.buffer_view) {
+      mark(&mut used_views, view)?;
+    }
+    
+    // Every reference has been checked by now, so nothing below can fail
+    let remap = |used: &[bool]| {
+      let mut remap = Remap::new(used.len());
+      for i in (0..used.len()).filter(|&i| used[i]) {
+        // .unwrap() acceptable here because i is in range
+        remap.get(i as u32).unwrap();
+      }
+      remap
+    };
+    let mesh_map = remap(&used_meshes);
+    let material_map = remap(&used_materials);
+    let accessor_map = remap(&used_accessors);
+    let view_map = remap(&used_views);
+    let new = |map: &Remap, index: u32| map.map[index as usize]
+      .unwrap_or(index);
+    
+    for node in self.nodes.iter_mut() {
+      node.mesh = node.mesh.map(|mesh| new(&mesh_map, mesh));
+    }
+    self.meshes = mesh_map.order.iter()
+      .map(|&i| self.meshes[i as usize].clone()).collect();
+    for primitive in self.meshes.iter_mut()
+      .flat_map(|m| m.primitives.iter_mut()) {
+      primitive.material = primitive.material
+        .map(|material| new(&material_map, material));
+      for accessor in primitive.attributes.accessors_mut() {
+        *accessor = accessor.map(|a| new(&accessor_map, a));
+      }
+      primitive.indices = primitive.indices.map(|a| new(&accessor_map, a));
+    }
+    for sampler in self.animations.iter_mut()
+      .flat_map(|a| a.samplers.iter_mut()) {
+      sampler.input = new(&accessor_map, sampler.input);
+      sampler.output = new(&accessor_map, sampler.output);
+    }
+    for skin in self.skins.iter_mut() {
+      skin.inverse_bind_matrices = skin.inverse_bind_matrices
+        .map(|a| new(&accessor_map, a));
+    }
+    for node in self.nodes.iter_mut() {
+      // .unwrap() acceptable here because the mapping can't fail
+      instancing::map_instance_accessors(node,
+        |a| Ok(new(&accessor_map, a))).unwrap();
+    }
+    self.materials = material_map.order.iter()
+      .map(|&i| self.materials[i as usize].clone()).collect();
+    for material in self.materials.iter_mut() {
+      // .unwrap() acceptable here because the mapping can't fail
+      map_lod_ids(&mut material.extensions,
+        |lod| Ok(new(&material_map, lod))).unwrap();
+    }
+    
+    self.accessors = accessor_map.order.iter()
+      .map(|&i| self.accessors[i as usize].clone()).collect();
+    for accessor in self.accessors.iter_mut() {
+      accessor.buffer_view = accessor.buffer_view
+        .map(|view| new(&view_map, view));
+      for view in accessor.sparse.iter_mut()
+        .flat_map(|sparse| sparse.buffer_views_mut()) {
+        *view = new(&view_map, *view);
+      }
+    }
+    for image in self.images.iter_mut() {
+      image.buffer_view = image.buffer_view.map(|view| new(&view_map, view));
+    }
+    self.buffer_views = view_map.order.iter()
+      .map(|&i| self.buffer_views[i as usize].clone()).collect();
+    
+    Ok(())
+  }
+  
+  // Merges materials that would be written identically, pointing every
+  // primitive and MSFT_lod level at the first of each set and removing the
+  // rest. References to materials that don't exist are left alone. Returns
+  // how many were removed
+  pub fn dedup_materials(&mut self) -> u32 {
+    let mut first = std::collections::HashMap::new();
+    let mut kept = Remap::new(self.materials.len());
+    let mut new_index = Vec::with_capacity(self.materials.len());
+    for (i, material) in self.materials.iter().enumerate() {
+      // .unwrap() acceptable here because Material has no maps with
+      // non-string keys, which is the only way serialization can fail
+      let json = serde_json::to_vec(material).unwrap();
+      let original = *first.entry(json).or_insert(i as u32);
+      // .unwrap() acceptable here because original <= i is in range
+      new_index.push(kept.get(original).unwrap());
+    }
+    
+    for primitive in self.meshes.iter_mut()
+      .flat_map(|m| m.primitives.iter_mut()) {
+      primitive.material = primitive.material.map(|material| {
+        *new_index.get(material as usize).unwrap_or(&material)
+      });
+    }
+    
+    let removed = (self.materials.len() - kept.order.len()) as u32;
+    self.materials = kept.order.iter()
+      .map(|&i| self.materials[i as usize].clone()).collect();
+    for material in self.materials.iter_mut() {
+      // .unwrap() acceptable here because the mapping can't fail
+      map_lod_ids(&mut material.extensions,
+        |lod| Ok(*new_index.get(lod as usize).unwrap_or(&lod))).unwrap();
+    }
+    removed
+  }
+  
+  // Clears the names of accessors, buffer views, buffers, and images, which
+  // only help when reading the JSON. Names engines look things up by, on
+  // scenes, nodes, meshes, materials, textures, and animations, are kept
+  pub fn strip_names(&mut self) {
+    for accessor in self.accessors.iter_mut() {
+      accessor.name.clear();
+    }
+    for view in self.buffer_views.iter_mut() {
+      view.name.clear();
+    }
+    for buffer in self.buffers.iter_mut() {
+      buffer.name.clear();
+    }
+    for image in self.images.iter_mut() {
+      image.name.clear();
+    }
+  }
+  
+  // Rounds node transforms and material factors to `decimals` decimal places,
+  // to shorten the JSON. Accessor min and max are rounded outward (down for
+  // min, up for max) so they still bound the data, though verify_all_bounds()
+  // will then report them if `decimals` is small
+  pub fn round_floats(&mut self, decimals: u32) {
+    let scale = 10f64.powi(decimals as i32);
+    let round = |x: &mut f64| *x = (*x * scale).round() / scale;
+    
+    for node in self.nodes.iter_mut() {
+      for x in [&mut node.t.x, &mut node.t.y, &mut node.t.z, &mut node.r.x,
+        &mut node.r.y, &mut node.r.z, &mut node.r.w, &mut node.s.x,
+        &mut node.s.y, &mut node.s.z] {
+        round(x);
+      }
+      for x in node.matrix.iter_mut().flatten() {
+        round(x);
+      }
+    }
+    
+    for material in self.materials.iter_mut() {
+      let pbr = &mut material.pbr_metallic_roughness;
+      let color = &mut pbr.base_color_factor;
+      for x in [&mut color.r, &mut color.g, &mut color.b, &mut color.a,
+        &mut pbr.metallic_factor, &mut pbr.roughness_factor,
+        &mut material.alpha_cutoff] {
+        round(x);
+      }
+      material.emissive_factor.iter_mut().for_each(round);
+    }
+    
+    for accessor in self.accessors.iter_mut() {
+      for x in accessor.min.iter_mut() {
+        *x = (*x * scale).floor() / scale;
+      }
+      for x in accessor.max.iter_mut() {
+        *x = (*x * scale).ceil() / scale;
+      }
+    }
+  }
+}
@@ -1,12 +1,64 @@
+// Every glTF struct has a new() that fills in the spec defaults, which is what
+// paragen models are expected to call. Default impls would just duplicate those
+#![allow(clippy::new_without_default)]
+
 use std::sync::Mutex;
 use std::sync::atomic::{Ordering, AtomicU32};
 
+pub mod geometry;
+pub mod khr_lights_punctual;
+pub mod khr_materials;
+pub mod khr_texture_transform;
+pub mod shapes;
+
+mod axis;
+mod buffer_builder;
+mod builders;
+mod cameras;
+mod canonicalize;
+mod chunked;
+mod files;
+mod glb;
+mod instancing;
+mod lights;
+mod matrix;
+mod mesh_data;
+mod optimize;
+mod path;
+mod quantize;
+mod resolve;
+mod skins;
+mod sparse;
+mod statistics;
+mod triangles;
+mod validation;
+pub use axis::{Axis, AxisConversion};
+pub use buffer_builder::{BufferBuilder, TargetMode};
+pub use builders::{NodeBuilder, MaterialBuilder, MeshBuilder};
+pub use cameras::{Camera, CameraType, CameraPerspective, CameraOrthographic};
+pub use chunked::{ChunkedOutput, next_chunk, write_gltf_chunked};
+pub use instancing::{EXT_MESH_GPU_INSTANCING, ExtMeshGpuInstancing,
+  Instance};
+pub use lights::ImageBasedLight;
+pub use mesh_data::MeshData;
+pub use optimize::{OptimizeOptions, OptimizeReport};
+pub use path::PathTarget;
+pub use resolve::ResolvedAccessor;
+pub use skins::Skin;
+pub use sparse::{AccessorSparse, SparseIndices, SparseValues};
+pub use statistics::Statistics;
+pub use validation::{Budget, Severity, ValidationMessage};
+
 pub mod prelude {
   pub use paragen_macros::paragen;
   pub use crate::GLTF;
   pub use crate::Scene;
   pub use crate::Node;
   pub use crate::ErrorCode;
+  pub use crate::NodeBuilder;
+  pub use crate::MaterialBuilder;
+  pub use crate::MeshBuilder;
+  pub use crate::BufferBuilder;
 }
 
 pub static MUTEX_TEST: Mutex<Vec<u8>> = Mutex::new(Vec::new());
@@ -25,13 +77,54 @@ pub extern "C" fn size() -> i32 {
   SIZE.load(Ordering::Relaxed) as i32
 }
 
+// Description of the last failed generation, so hosts can show more than an
+// error code. Empty if the last generation succeeded
+static ERROR_MESSAGE: Mutex<String> = Mutex::new(String::new());
+static ERROR_POINTER: AtomicU32 = AtomicU32::new(0);
+static ERROR_SIZE: AtomicU32 = AtomicU32::new(0);
+
+// Pointer to the UTF-8 bytes of the last error message
+#[no_mangle]
+pub extern "C" fn last_error_message() -> i32 {
+  ERROR_POINTER.load(Ordering::Relaxed) as i32
+}
+
+// Length in bytes of the last error message
+#[no_mangle]
+pub extern "C" fn last_error_message_size() -> i32 {
+  ERROR_SIZE.load(Ordering::Relaxed) as i32
+}
+
+// Records why generation failed, for the host to read through
+// last_error_message(). Replaces any earlier message
+pub fn set_error_message(message: &str) {
+  // A panic while holding the lock can't leave a String in a bad state, so
+  // poisoning can be ignored
+  let mut stored = ERROR_MESSAGE.lock().unwrap_or_else(|e| e.into_inner());
+  *stored = String::from(message);
+  
+  ERROR_POINTER.store(stored.as_ptr() as u32, Ordering::Relaxed);
+  ERROR_SIZE.store(stored.len() as u32, Ordering::Relaxed);
+}
+
+pub fn clear_error_message() {
+  set_error_message("");
+}
+
 // These error codes are return from WebAssembly functions, so must use a
 // WebAssembly variable type
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(i32)]
 pub enum ErrorCode {
     None = 0,
     Mutex = 1,
     Generation = 2,
+    // An index pointed at an element (or buffer bytes) that doesn't exist
+    Index = 3,
+    // A value was outside the range allowed for it
+    Value = 4,
+    // GLTF::validate() found errors in a generated model
+    ValidationFailed = 5,
 }
 
 struct DryRunWriter {
@@ -55,6 +148,96 @@ impl std::io::Write for DryRunWriter {
   }
 }
 
+// Maps indices into a source array onto indices into a new array holding only
+// the elements that were asked for, numbered in order of first request
+struct Remap {
+  map: Vec<Option<u32>>,
+  order: Vec<u32>,
+}
+
+impl Remap {
+  fn new(len: usize) -> Self {
+    Self { map: vec![None; len], order: Vec::new() }
+  }
+  
+  fn get(&mut self, index: u32) -> Result<u32, ErrorCode> {
+    match self.map.get(index as usize) {
+      None => Err(ErrorCode::Index),
+      Some(Some(new_index)) => Ok(*new_index),
+      Some(None) => {
+        let new_index = self.order.len() as u32;
+        self.map[index as usize] = Some(new_index);
+        self.order.push(index);
+        Ok(new_index)
+      },
+    }
+  }
+  
+  fn get_option(&mut self, index: Option<u32>,
+  ) -> Result<Option<u32>, ErrorCode> {
+    index.map(|i| self.get(i)).transpose()
+  }
+}
+
+// glTF requires accessor data to be aligned to its component size, and GLB
+// chunks to 4 bytes. Padding everything to 4 covers all component types
+fn pad_to_4(bytes: &mut Vec<u8>) {
+  while !bytes.len().is_multiple_of(4) {
+    bytes.push(0);
+  }
+}
+
+// A typed glTF extension object, stored under NAME in the extensions map of
+// whatever it's attached to
+pub trait Extension: serde::Serialize {
+  const NAME: &'static str;
+  
+  // Checks the extension's values are within the ranges its spec allows
+  fn validate(&self) -> Result<(), ErrorCode> {
+    Ok(())
+  }
+}
+
+// An object that can carry extensions, for GLTF::add_extension() and
+// attach_extension()
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtensionTarget {
+  // The document itself
+  Root,
+  Asset,
+  Scene(u32),
+  Node(u32),
+  Camera(u32),
+  Skin(u32),
+  Material(u32),
+  Mesh(u32),
+  // Mesh index, primitive index
+  MeshPrimitive(u32, u32),
+  Accessor(u32),
+  BufferView(u32),
+  Buffer(u32),
+  Animation(u32),
+  Image(u32),
+  Texture(u32),
+  Sampler(u32),
+}
+
+// Serializes `extension` into `extensions` under its name, creating the map if
+// needed. Other extensions already in the map are left alone
+fn insert_extension<E: Extension>(
+  extensions: &mut Option<serde_json::Map<String, serde_json::Value>>,
+  extension: &E,
+) -> Result<(), ErrorCode> {
+  extension.validate()?;
+  
+  // .unwrap() acceptable here because extension structs have no maps with
+  // non-string keys, which is the only way serialization to JSON can fail
+  let value = serde_json::to_value(extension).unwrap();
+  extensions.get_or_insert_with(serde_json::Map::new)
+    .insert(String::from(E::NAME), value);
+  Ok(())
+}
+
 #[derive(Clone, serde::Serialize)]
 pub struct Asset {
   #[serde(skip_serializing_if = "String::is_empty")]
@@ -70,23 +253,83 @@ pub struct Asset {
   #[serde(rename = "minVersion")]
   pub min_version: String,
   
-  // pub extensions: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
   
-  // In the .gltf spec, but will have to wait for later
-  //pub extra: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 impl Asset {
   pub fn new() -> Self {
     Self {
       copyright: String::from(""),
-      generator: String::from("Paragen v0.1.0"),
+      generator: String::from(concat!("Paragen v",
+        env!("CARGO_PKG_VERSION"))),
       version: String::from("2.0"),
       min_version: String::from("2.0"),
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Credits both the application and paragen in the generator string, as
+  // `name version (Paragen x.y.z)`
+  pub fn with_app(name: &str, version: &str) -> Self {
+    let mut asset = Self::new();
+    asset.generator = format!("{name} {version} (Paragen {})",
+      env!("CARGO_PKG_VERSION"));
+    asset
+  }
+}
+
+// Green channel direction of the normal maps a model was authored with. glTF
+// itself always uses OpenGL's, so this only records where the source art
+// came from, for importers that want to check or flip it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalMapConvention {
+  // Green points up (+Y), as glTF requires
+  OpenGL,
+  // Green points down (-Y)
+  DirectX,
+}
+
+impl NormalMapConvention {
+  pub fn name(&self) -> &'static str {
+    match self {
+      NormalMapConvention::OpenGL => "OpenGL",
+      NormalMapConvention::DirectX => "DirectX",
     }
   }
 }
 
+// The extras of an object as a JSON object, for helpers that store named
+// values there. Extras that are missing (or, against the spec's advice,
+// aren't an object) are replaced with an empty object
+fn extras_object(extras: &mut Option<serde_json::Value>,
+) -> &mut serde_json::Map<String, serde_json::Value> {
+  if !matches!(extras, Some(serde_json::Value::Object(_))) {
+    *extras = Some(serde_json::Value::Object(serde_json::Map::new()));
+  }
+  
+  match extras {
+    Some(serde_json::Value::Object(map)) => map,
+    _ => unreachable!(),
+  }
+}
+
+// Stores `value` under `key` in `extras`, the extras field of any glTF
+// object, for metadata such as part numbers or the parameters a model was
+// generated from. Extras that aren't an object are replaced by one. Fails if
+// `value` can't be represented as JSON
+pub fn set_extra<T: serde::Serialize>(extras: &mut Option<serde_json::Value>,
+  key: &str, value: T,
+) -> Result<(), ErrorCode> {
+  let value = serde_json::to_value(value).map_err(|_| ErrorCode::Value)?;
+  extras_object(extras).insert(String::from(key), value);
+  Ok(())
+}
+
 #[derive(Clone, serde::Serialize)]
 pub struct GLTF {
   // Don't skip if empty...this field is mandatory per GLTF spec!
@@ -117,18 +360,44 @@ pub struct GLTF {
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub buffers: Vec<Buffer>,
   
-  // In the .gltf spec, but will have to wait for later
-  /*pub animations: ??
-  pub asset: ??
-  pub extensionsUsed: ??
-  pub extensionsRequired: ??
-  pub cameras: ??
-  pub images: ??
-  pub samplers: ??
-  pub skins: ??
-  pub textures: ??
-  pub extensions: ??
-  pub extras: ??*/
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub animations: Vec<Animation>,
+  
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub images: Vec<Image>,
+  
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub textures: Vec<Texture>,
+  
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub samplers: Vec<Sampler>,
+  
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub cameras: Vec<Camera>,
+  
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub skins: Vec<Skin>,
+  
+  #[serde(rename = "extensionsUsed")]
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub extensions_used: Vec<String>,
+  
+  // Extensions a viewer must support to load the file at all. Each must also
+  // be in extensions_used
+  #[serde(rename = "extensionsRequired")]
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub extensions_required: Vec<String>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+  
+  // Content hashes of materials added through intern_material(), for finding
+  // duplicates without comparing against every material
+  #[serde(skip)]
+  material_hashes: std::collections::HashMap<u64, Vec<u32>>,
 }
 
 impl GLTF {
@@ -143,7 +412,542 @@ impl GLTF {
       accessors: Vec::new(),
       buffer_views: Vec::new(),
       buffers: Vec::new(),
+      animations: Vec::new(),
+      images: Vec::new(),
+      textures: Vec::new(),
+      samplers: Vec::new(),
+      cameras: Vec::new(),
+      skins: Vec::new(),
+      extensions_used: Vec::new(),
+      extensions_required: Vec::new(),
+      extensions: None,
+      extras: None,
+      material_hashes: std::collections::HashMap::new(),
+    }
+  }
+  
+  // Returns the index of a material identical to `material`, adding it (as
+  // add_material() does) only if there isn't one already. Only materials
+  // previously added by this method are checked, so materials pushed
+  // directly can still be duplicated
+  pub fn intern_material(&mut self, material: Material) -> u32 {
+    let hash = material.content_hash();
+    // .unwrap() acceptable here for the same reason as in content_hash()
+    let json = serde_json::to_vec(&material).unwrap();
+    
+    let candidates = self.material_hashes.entry(hash).or_default();
+    for &index in candidates.iter() {
+      // Materials may have been edited or removed since they were interned
+      if let Some(existing) = self.materials.get(index as usize) {
+        if serde_json::to_vec(existing).unwrap() == json {
+          return index;
+        }
+      }
+    }
+    
+    let index = self.materials.len() as u32;
+    candidates.push(index);
+    self.add_material(material)
+  }
+  
+  // Adds `material`, listing any extensions it or its texture references
+  // carry in extensionsUsed. Returns its index
+  pub fn add_material(&mut self, material: Material) -> u32 {
+    let maps = std::iter::once(&material.extensions)
+      .chain(material.nested_extension_maps().into_iter().map(|(_, m)| m));
+    for name in maps.flatten().flat_map(|e| e.keys()) {
+      if !self.extensions_used.contains(name) {
+        self.extensions_used.push(name.clone());
+      }
+    }
+    
+    self.materials.push(material);
+    self.materials.len() as u32 - 1
+  }
+  
+  // Adds `name` to extensionsUsed, if it isn't already listed
+  pub fn use_extension(&mut self, name: &str) {
+    if !self.extensions_used.iter().any(|used| used == name) {
+      self.extensions_used.push(String::from(name));
+    }
+  }
+  
+  // Adds `name` to extensionsRequired, and to extensionsUsed since every
+  // required extension must also be listed there
+  pub fn require_extension(&mut self, name: &str) {
+    self.use_extension(name);
+    if !self.extensions_required.iter().any(|required| required == name) {
+      self.extensions_required.push(String::from(name));
+    }
+  }
+  
+  // Attaches raw extension JSON for extensions paragen has no struct for,
+  // replacing any previous value under `name` on that object. The name is
+  // added to extensionsUsed, and to extensionsRequired if `required` is set
+  pub fn add_extension(&mut self, target: ExtensionTarget, name: &str,
+    value: serde_json::Value, required: bool,
+  ) -> Result<(), ErrorCode> {
+    self.extensions_mut(target)?.get_or_insert_with(serde_json::Map::new)
+      .insert(String::from(name), value);
+    
+    match required {
+      true => self.require_extension(name),
+      false => self.use_extension(name),
+    }
+    Ok(())
+  }
+  
+  // Like add_extension(), for extensions paragen has a struct for. Fails
+  // without changing anything if the object doesn't exist or the extension
+  // fails its own validation
+  pub fn attach_extension<E: Extension>(&mut self, target: ExtensionTarget,
+    extension: &E, required: bool,
+  ) -> Result<(), ErrorCode> {
+    insert_extension(self.extensions_mut(target)?, extension)?;
+    
+    match required {
+      true => self.require_extension(E::NAME),
+      false => self.use_extension(E::NAME),
+    }
+    Ok(())
+  }
+  
+  // The extensions field of `target`
+  fn extensions_mut(&mut self, target: ExtensionTarget,
+  ) -> Result<&mut Option<serde_json::Map<String, serde_json::Value>>,
+    ErrorCode> {
+    fn get<T>(list: &mut [T], i: u32) -> Result<&mut T, ErrorCode> {
+      list.get_mut(i as usize).ok_or(ErrorCode::Index)
+    }
+    
+    Ok(match target {
+      ExtensionTarget::Root => &mut self.extensions,
+      ExtensionTarget::Asset => &mut self.asset.extensions,
+      ExtensionTarget::Scene(i) => &mut get(&mut self.scenes, i)?.extensions,
+      ExtensionTarget::Node(i) => &mut get(&mut self.nodes, i)?.extensions,
+      ExtensionTarget::Camera(i) => &mut get(&mut self.cameras, i)?.extensions,
+      ExtensionTarget::Skin(i) => &mut get(&mut self.skins, i)?.extensions,
+      ExtensionTarget::Material(i) => {
+        &mut get(&mut self.materials, i)?.extensions
+      },
+      ExtensionTarget::Mesh(i) => &mut get(&mut self.meshes, i)?.extensions,
+      ExtensionTarget::MeshPrimitive(m, p) => {
+        &mut get(&mut get(&mut self.meshes, m)?.primitives, p)?.extensions
+      },
+      ExtensionTarget::Accessor(i) => {
+        &mut get(&mut self.accessors, i)?.extensions
+      },
+      ExtensionTarget::BufferView(i) => {
+        &mut get(&mut self.buffer_views, i)?.extensions
+      },
+      ExtensionTarget::Buffer(i) => &mut get(&mut self.buffers, i)?.extensions,
+      ExtensionTarget::Animation(i) => {
+        &mut get(&mut self.animations, i)?.extensions
+      },
+      ExtensionTarget::Image(i) => &mut get(&mut self.images, i)?.extensions,
+      ExtensionTarget::Texture(i) => {
+        &mut get(&mut self.textures, i)?.extensions
+      },
+      ExtensionTarget::Sampler(i) => {
+        &mut get(&mut self.samplers, i)?.extensions
+      },
+    })
+  }
+  
+  // Records licensing in a machine-readable form, as a `license` object in
+  // asset.extras with the SPDX identifier, author, and source URL, and in a
+  // human-readable form in asset.copyright
+  pub fn set_license(&mut self, spdx: &str, author: &str, source_url: &str) {
+    self.asset.copyright = format!("Copyright {author}, licensed under \
+      {spdx}");
+    
+    extras_object(&mut self.asset.extras).insert(String::from("license"),
+      serde_json::json!({
+        "spdx": spdx,
+        "author": author,
+        "source": source_url,
+      }));
+  }
+  
+  // Points asset browsers at a preview image, as `thumbnail` in asset.extras.
+  // `uri` is used as is, so relative paths must already be URI-encoded
+  pub fn set_thumbnail(&mut self, uri: &str) {
+    extras_object(&mut self.asset.extras).insert(String::from("thumbnail"),
+      serde_json::Value::from(uri));
+  }
+  
+  // Records the normal map convention of the source art, as
+  // `normalMapConvention` in asset.extras
+  pub fn set_normal_map_convention(&mut self,
+    convention: NormalMapConvention,
+  ) {
+    extras_object(&mut self.asset.extras).insert(
+      String::from("normalMapConvention"),
+      serde_json::Value::from(convention.name()));
+  }
+  
+  // Reads back set_normal_map_convention(). None if unset or unrecognized
+  pub fn normal_map_convention(&self) -> Option<NormalMapConvention> {
+    let name = self.asset.extras.as_ref()?.get("normalMapConvention")?
+      .as_str()?;
+    [NormalMapConvention::OpenGL, NormalMapConvention::DirectX].into_iter()
+      .find(|convention| convention.name() == name)
+  }
+  
+  // Attaches `extension` to material `material`, replacing any previous
+  // extension of the same kind, and lists it in extensionsUsed
+  pub fn add_material_extension<E: Extension>(&mut self, material: u32,
+    extension: &E,
+  ) -> Result<(), ErrorCode> {
+    self.attach_extension(ExtensionTarget::Material(material), extension,
+      false)
+  }
+  
+  // Size of all binary data, as declared by the buffers
+  pub fn total_buffer_bytes(&self) -> u64 {
+    self.buffers.iter().map(|buffer| buffer.byte_length as u64).sum()
+  }
+  
+  // Buffer view bytes split by target: vertex data, index data, and views
+  // with no target (such as images and animations). Padding between views,
+  // and bytes no view covers, aren't counted
+  pub fn buffer_size_by_target(&self) -> (u64, u64, u64) {
+    let (mut array_buffer, mut element_array_buffer, mut other) = (0, 0, 0);
+    for buffer_view in self.buffer_views.iter() {
+      let length = buffer_view.byte_length as u64;
+      match buffer_view.target {
+        Some(Target::ArrayBuffer) => array_buffer += length,
+        Some(Target::ElementArrayBuffer) => element_array_buffer += length,
+        None => other += length,
+      }
+    }
+    (array_buffer, element_array_buffer, other)
+  }
+  
+  // True if the document needs no files besides itself: every buffer and
+  // image is either embedded as a data: URI or has no URI (GLB binary chunk
+  // or buffer view). See external_references() for what's missing
+  pub fn is_self_contained(&self) -> bool {
+    self.external_references().is_empty()
+  }
+  
+  // Buffer and image URIs that point outside the document, in buffer then
+  // image order, as written (still URI-encoded)
+  pub fn external_references(&self) -> Vec<String> {
+    self.buffers.iter().map(|buffer| &buffer.uri)
+      .chain(self.images.iter().map(|image| &image.uri))
+      .filter(|uri| !uri.is_empty() && !uri.starts_with("data:"))
+      .cloned().collect()
+  }
+  
+  // Sets byte_stride on vertex buffer views shared by several accessors, on
+  // the assumption that those accessors are interleaved: each one's elements
+  // sit side by side within a repeating stride. Views with one accessor, and
+  // index views, are left alone. Fails, changing nothing, if two accessors'
+  // elements overlap, or if an accessor would run past the end of its view
+  // with the new stride
+  pub fn infer_strides(&mut self) -> Result<(), ErrorCode> {
+    let mut users: Vec<Vec<&Accessor>> = vec![Vec::new();
+      self.buffer_views.len()];
+    for accessor in self.accessors.iter() {
+      if let Some(buffer_view) = accessor.buffer_view {
+        users.get_mut(buffer_view as usize).ok_or(ErrorCode::Index)?
+          .push(accessor);
+      }
+    }
+    
+    let mut strides = Vec::new();
+    for (i, (buffer_view, mut accessors)) in self.buffer_views.iter()
+      .zip(users).enumerate() {
+      if accessors.len() < 2
+        || buffer_view.target == Some(Target::ElementArrayBuffer) {
+        continue;
+      }
+      
+      accessors.sort_by_key(|accessor| accessor.byte_offset);
+      let (mut previous_start, mut end) = (0, 0);
+      for accessor in accessors.iter() {
+        let start = accessor.byte_offset as usize;
+        // Accessors reading exactly the same bytes aren't a conflict
+        let same_bytes = start == previous_start
+          && start + accessor.element_size() == end;
+        if start < end && !same_bytes {
+          return Err(ErrorCode::Value);
+        }
+        previous_start = start;
+        end = end.max(start + accessor.element_size());
+      }
+      
+      // Vertex attributes must start on 4-byte boundaries, and the spec caps
+      // strides at 252
+      let stride = end.next_multiple_of(4);
+      if stride > 252 {
+        return Err(ErrorCode::Value);
+      }
+      for accessor in accessors.iter() {
+        let extent = accessor.byte_offset as usize + accessor.element_size()
+          + stride * (accessor.count as usize).saturating_sub(1);
+        if extent > buffer_view.byte_length as usize {
+          return Err(ErrorCode::Index);
+        }
+      }
+      
+      strides.push((i, stride as u32));
+    }
+    
+    for (i, stride) in strides {
+      self.buffer_views[i].byte_stride = Some(stride);
+    }
+    
+    Ok(())
+  }
+  
+  // Copies node `root` and everything below it into a new document with a
+  // single scene. Only the meshes, materials, textures, images, samplers,
+  // accessors and buffer views reachable from the subtree are kept, and the
+  // bytes they use (including embedded images) are packed into a single new
+  // buffer, returned alongside the document. Cameras, KHR_lights_punctual
+  // lights, and MSFT_lod levels the subtree uses are copied too, but skins
+  // are dropped, as their joints may lie outside it, and so are other
+  // document level extensions. extensionsUsed and extensionsRequired keep
+  // only what the copy still uses. `buffers` holds the binary contents of
+  // self.buffers, in the same order
+  pub fn extract_subtree(&self, root: u32, buffers: &[Vec<u8>],
+  ) -> Result<(GLTF, Vec<u8>), ErrorCode> {
+    let mut node_map = Remap::new(self.nodes.len());
+    let mut mesh_map = Remap::new(self.meshes.len());
+    let mut camera_map = Remap::new(self.cameras.len());
+    let mut light_map = Remap::new(self.punctual_light_count() as usize);
+    let mut material_map = Remap::new(self.materials.len());
+    let mut texture_map = Remap::new(self.textures.len());
+    let mut image_map = Remap::new(self.images.len());
+    let mut sampler_map = Remap::new(self.samplers.len());
+    let mut accessor_map = Remap::new(self.accessors.len());
+    let mut buffer_view_map = Remap::new(self.buffer_views.len());
+    
+    let mut gltf = GLTF::new();
+    gltf.asset = self.asset.clone();
+    
+    // Children and LOD levels are appended to node_map.order as they are
+    // found, so this walks the whole subtree. Nodes already seen aren't
+    // queued again
+    node_map.get(root)?;
+    let mut i = 0;
+    while i < node_map.order.len() {
+      let mut node = self.nodes[node_map.order[i] as usize].clone();
+      for child in node.children.iter_mut() {
+        *child = node_map.get(*child)?;
+      }
+      node.mesh = mesh_map.get_option(node.mesh)?;
+      instancing::map_instance_accessors(&mut node,
+        |accessor| accessor_map.get(accessor))?;
+      node.camera = camera_map.get_option(node.camera)?;
+      node.skin = None;
+      map_lod_ids(&mut node.extensions, |lod| node_map.get(lod))?;
+      if let Some(light) = node.extensions.as_mut()
+        .and_then(|e| e.get_mut(khr_lights_punctual::NAME))
+        .and_then(|e| e.get_mut("light")) {
+        let index = light.as_u64().ok_or(ErrorCode::Value)?;
+        *light = serde_json::Value::from(light_map.get(index as u32)?);
+      }
+      gltf.nodes.push(node);
+      i += 1;
+    }
+    
+    for &index in mesh_map.order.iter() {
+      let mut mesh = self.meshes[index as usize].clone();
+      for primitive in mesh.primitives.iter_mut() {
+        for accessor in primitive.attributes.accessors_mut() {
+          *accessor = accessor_map.get_option(*accessor)?;
+        }
+        primitive.indices = accessor_map.get_option(primitive.indices)?;
+        primitive.material = material_map.get_option(primitive.material)?;
+      }
+      gltf.meshes.push(mesh);
+    }
+    
+    for &index in camera_map.order.iter() {
+      gltf.cameras.push(self.cameras[index as usize].clone());
+    }
+    
+    if !light_map.order.is_empty() {
+      // .unwrap() acceptable here because light_map only holds indices
+      // below punctual_light_count(), so the lights array exists
+      let lights = self.extensions.as_ref()
+        .and_then(|e| e.get(khr_lights_punctual::NAME))
+        .and_then(|e| e.get("lights"))
+        .and_then(|lights| lights.as_array()).unwrap();
+      let copied: Vec<serde_json::Value> = light_map.order.iter()
+        .map(|&index| lights[index as usize].clone()).collect();
+      gltf.extensions.get_or_insert_with(serde_json::Map::new).insert(
+        String::from(khr_lights_punctual::NAME),
+        serde_json::json!({ "lights": copied }));
+    }
+    
+    // MSFT_lod levels of materials are queued like nodes' above
+    let mut i = 0;
+    while i < material_map.order.len() {
+      let mut material = self.materials[material_map.order[i] as usize]
+        .clone();
+      material.map_textures(|texture| texture_map.get(texture))?;
+      map_lod_ids(&mut material.extensions,
+        |lod| material_map.get(lod))?;
+      gltf.materials.push(material);
+      i += 1;
     }
+    
+    for &index in texture_map.order.iter() {
+      let mut texture = self.textures[index as usize].clone();
+      texture.source = image_map.get_option(texture.source)?;
+      texture.sampler = sampler_map.get_option(texture.sampler)?;
+      // Extensions such as KHR_texture_basisu give their own image
+      for source in texture.extensions.iter_mut()
+        .flat_map(|e| e.values_mut())
+        .filter_map(|extension| extension.get_mut("source")) {
+        let index = source.as_u64().ok_or(ErrorCode::Value)?;
+        *source = serde_json::Value::from(image_map.get(index as u32)?);
+      }
+      gltf.textures.push(texture);
+    }
+    
+    for &index in image_map.order.iter() {
+      let mut image = self.images[index as usize].clone();
+      image.buffer_view = buffer_view_map.get_option(image.buffer_view)?;
+      gltf.images.push(image);
+    }
+    
+    for &index in sampler_map.order.iter() {
+      gltf.samplers.push(self.samplers[index as usize].clone());
+    }
+    
+    for &index in accessor_map.order.iter() {
+      let mut accessor = self.accessors[index as usize].clone();
+      accessor.buffer_view = buffer_view_map.get_option(accessor.buffer_view)?;
+      for view in accessor.sparse.iter_mut()
+        .flat_map(|sparse| sparse.buffer_views_mut()) {
+        *view = buffer_view_map.get(*view)?;
+      }
+      gltf.accessors.push(accessor);
+    }
+    
+    let mut bytes = Vec::new();
+    for &index in buffer_view_map.order.iter() {
+      let mut buffer_view = self.buffer_views[index as usize].clone();
+      let start = buffer_view.byte_offset as usize;
+      let end = start + buffer_view.byte_length as usize;
+      let data = buffers.get(buffer_view.buffer as usize)
+        .and_then(|buffer| buffer.get(start..end))
+        .ok_or(ErrorCode::Index)?;
+      
+      pad_to_4(&mut bytes);
+      buffer_view.buffer = 0;
+      buffer_view.byte_offset = bytes.len() as u32;
+      bytes.extend_from_slice(data);
+      gltf.buffer_views.push(buffer_view);
+    }
+    
+    if !bytes.is_empty() {
+      let mut buffer = Buffer::new();
+      buffer.byte_length = bytes.len() as u32;
+      gltf.buffers.push(buffer);
+    }
+    
+    let mut scene = Scene::new();
+    scene.nodes.push(0);
+    gltf.scenes.push(scene);
+    gltf.scene = Some(0);
+    
+    // Extensions attached to objects are only kept if a copied object still
+    // has them. Ones never attached to anything, such as
+    // KHR_mesh_quantization, change how data is read, so they all stay
+    let attached: std::collections::HashSet<&String> = self.extension_maps()
+      .into_iter().flat_map(|(_, map)| map.keys()).collect();
+    let copied: std::collections::HashSet<String> = gltf.extension_maps()
+      .into_iter().flat_map(|(_, map)| map.keys().cloned()).collect();
+    let kept = |name: &&String| copied.contains(*name)
+      || !attached.contains(*name);
+    gltf.extensions_used = self.extensions_used.iter().filter(kept)
+      .cloned().collect();
+    gltf.extensions_required = self.extensions_required.iter().filter(kept)
+      .cloned().collect();
+    
+    Ok((gltf, bytes))
+  }
+  
+  // Points every primitive in the document at material `material`
+  pub fn assign_material_to_all(&mut self, material: u32,
+  ) -> Result<(), ErrorCode> {
+    if material as usize >= self.materials.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    for mesh in self.meshes.iter_mut() {
+      for primitive in mesh.primitives.iter_mut() {
+        primitive.material = Some(material);
+      }
+    }
+    
+    Ok(())
+  }
+  
+  // Points every primitive in mesh `mesh` at material `material`
+  pub fn assign_material_to_mesh(&mut self, mesh: u32, material: u32,
+  ) -> Result<(), ErrorCode> {
+    if material as usize >= self.materials.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    let mesh = self.meshes.get_mut(mesh as usize).ok_or(ErrorCode::Index)?;
+    for primitive in mesh.primitives.iter_mut() {
+      primitive.material = Some(material);
+    }
+    
+    Ok(())
+  }
+  
+  // Points every primitive using a material that's a key in `map` at that
+  // key's value instead, across the whole document. Primitives using other
+  // materials, or none, are left alone. Fails without changing anything if
+  // any value isn't an existing material
+  pub fn remap_materials(&mut self,
+    map: &std::collections::HashMap<u32, u32>,
+  ) -> Result<(), ErrorCode> {
+    if map.values().any(|&material| material as usize >= self.materials.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    for mesh in self.meshes.iter_mut() {
+      for primitive in mesh.primitives.iter_mut() {
+        if let Some(&material) = primitive.material
+          .and_then(|old| map.get(&old)) {
+          primitive.material = Some(material);
+        }
+      }
+    }
+    
+    Ok(())
+  }
+  
+  // Appends `data` to `bytes`, the binary contents of buffer `buffer`, and adds
+  // a buffer view covering it. Returns the new buffer view's index
+  pub(crate) fn push_buffer_view(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    data: &[u8], byte_stride: Option<u32>, target: Option<Target>,
+  ) -> Result<u32, ErrorCode> {
+    let buffer_entry = self.buffers.get_mut(buffer as usize)
+      .ok_or(ErrorCode::Index)?;
+    
+    pad_to_4(bytes);
+    let mut buffer_view = BufferView::new();
+    buffer_view.buffer = buffer;
+    buffer_view.byte_offset = bytes.len() as u32;
+    buffer_view.byte_length = data.len() as u32;
+    buffer_view.byte_stride = byte_stride;
+    buffer_view.target = target;
+    bytes.extend_from_slice(data);
+    buffer_entry.byte_length = bytes.len() as u32;
+    
+    self.buffer_views.push(buffer_view);
+    Ok(self.buffer_views.len() as u32 - 1)
   }
 }
 
@@ -155,15 +959,21 @@ pub struct Scene {
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub nodes: Vec<u32>,
   
-  //pub extensions: Vec<??>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
   
-  // In the .gltf spec but not currently used:
-  //pub extras: Vec<A JSON-serializable struct>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl Scene {
   pub fn new() -> Self {
-    Self { name: String::from(""), nodes: Vec::new() }
+    Self {
+      name: String::from(""),
+      nodes: Vec::new(),
+      extensions: None,
+      extras: None,
+    }
   }
 }
 
@@ -192,6 +1002,21 @@ pub struct Rotation {
 impl Rotation {
   pub fn new() -> Self { Self { x: 0.0, y: 0.0, z: 0.0, w: 1.0 } }
   pub fn is_default(&self) -> bool { *self == Self::new() }
+  
+  // Rotates about the X axis, then Y, then Z, all fixed axes (same as
+  // Blender's XYZ Euler mode). Angles are in degrees
+  pub fn from_euler_degrees([x, y, z]: [f64; 3]) -> Self {
+    let (sx, cx) = (x.to_radians() / 2.0).sin_cos();
+    let (sy, cy) = (y.to_radians() / 2.0).sin_cos();
+    let (sz, cz) = (z.to_radians() / 2.0).sin_cos();
+    
+    Self {
+      x: sx * cy * cz - cx * sy * sz,
+      y: cx * sy * cz + sx * cy * sz,
+      z: cx * cy * sz - sx * sy * cz,
+      w: cx * cy * cz + sx * sy * sz,
+    }
+  }
 }
 
 #[derive(Clone, PartialEq)]
@@ -215,6 +1040,14 @@ pub struct Node {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub mesh: Option<u32>,
   
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub camera: Option<u32>,
+  
+  // Skin to deform the mesh with. Needs a mesh whose primitives all have
+  // JOINTS_0 and WEIGHTS_0
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub skin: Option<u32>,
+  
   #[serde(rename = "translation")]
   #[serde(skip_serializing_if = "Translation::is_default")]
   pub t: Translation,
@@ -227,18 +1060,23 @@ pub struct Node {
   #[serde(skip_serializing_if = "Scale::is_default")]
   pub s: Scale,
   
+  // The whole transform at once, column by column, in place of t, r, and s,
+  // which must then be left at their defaults. See set_matrix() and
+  // decompose_matrix(). Animated nodes can't use it
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub matrix: Option<[f64; 16]>,
+  
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub children: Vec<u32>,
   
-  //pub mesh: ??,
-  //pub extensions: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
   
   // In the .gltf spec but will have to wait for now:
-  /*pub camera: ??,
-  pub skin: ??,
-  pub matrix: ??,
-  pub weights: ??,
-  pub extras: ??,*/
+  /*pub weights: ??,*/
 }
 
 impl Node {
@@ -246,11 +1084,173 @@ impl Node {
     Self {
       name: String::from(""),
       mesh: None,
+      camera: None,
+      skin: None,
       t: Translation::new(),
       r: Rotation::new(),
       s: Scale::new(),
+      matrix: None,
       children: Vec::new(),
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Engine-defined flag for toggling nodes at runtime, kept in extras as
+  // `enabled: false` only while disabled
+  pub fn set_enabled(&mut self, enabled: bool) {
+    if !enabled {
+      extras_object(&mut self.extras).insert(String::from("enabled"),
+        serde_json::Value::Bool(false));
+      return;
+    }
+    
+    if let Some(serde_json::Value::Object(extras)) = &mut self.extras {
+      extras.remove("enabled");
+      if extras.is_empty() {
+        self.extras = None;
+      }
+    }
+  }
+  
+  // Anything but an explicit `enabled: false` counts as enabled
+  pub fn is_enabled(&self) -> bool {
+    let enabled = self.extras.as_ref().and_then(|e| e.get("enabled"));
+    enabled != Some(&serde_json::Value::Bool(false))
+  }
+  
+  // Sets translation, rotation (as Euler angles in degrees, see
+  // Rotation::from_euler_degrees()) and scale together, dropping any matrix
+  pub fn set_transform(&mut self, [x, y, z]: [f64; 3], euler_deg: [f64; 3],
+    scale: [f64; 3],
+  ) {
+    self.t = Translation { x, y, z };
+    self.r = Rotation::from_euler_degrees(euler_deg);
+    self.s = Scale { x: scale[0], y: scale[1], z: scale[2] };
+    self.matrix = None;
+  }
+  
+  // Sets the transform as one matrix, column by column, resetting
+  // translation, rotation, and scale so only the matrix is written
+  pub fn set_matrix(&mut self, matrix: [f64; 16]) {
+    self.t = Translation::new();
+    self.r = Rotation::new();
+    self.s = Scale::new();
+    self.matrix = Some(matrix);
+  }
+  
+  // Replaces the node's matrix with the same transform as translation,
+  // rotation, and scale, as animating the node requires. Does nothing if
+  // there's no matrix. Fails without changing anything if the matrix has
+  // shear, squashes space flat, or isn't affine, since TRS can't express
+  // those
+  pub fn decompose_matrix(&mut self) -> Result<(), ErrorCode> {
+    let Some(matrix) = self.matrix else {
+      return Ok(());
+    };
+    let ([tx, ty, tz], [x, y, z, w], [sx, sy, sz]) =
+      matrix::decompose(&matrix).ok_or(ErrorCode::Value)?;
+    
+    self.t = Translation { x: tx, y: ty, z: tz };
+    self.r = Rotation { x, y, z, w };
+    self.s = Scale { x: sx, y: sy, z: sz };
+    self.matrix = None;
+    Ok(())
+  }
+  
+  // An empty named node marking a point, such as a socket to attach props to
+  // at runtime. Takes its transform as set_transform() does. See
+  // GLTF::find_locators()
+  pub fn locator(name: &str, translation: [f64; 3], euler_deg: [f64; 3],
+    scale: [f64; 3],
+  ) -> Self {
+    let mut node = Self::new();
+    node.name = String::from(name);
+    node.set_transform(translation, euler_deg, scale);
+    node
+  }
+  
+  // Named, with no mesh, camera, or children
+  pub fn is_locator(&self) -> bool {
+    !self.name.is_empty() && self.mesh.is_none() && self.camera.is_none()
+      && self.children.is_empty()
+  }
+}
+
+impl GLTF {
+  // Every node that is_locator(), with its name, in index order
+  pub fn find_locators(&self) -> Vec<(u32, String)> {
+    self.nodes.iter().enumerate().filter(|(_, node)| node.is_locator())
+      .map(|(i, node)| (i as u32, node.name.clone())).collect()
+  }
+}
+
+// Lower detail versions of a node, for viewers to switch to as it takes up
+// less of the screen. The node itself is the highest detail level
+#[derive(Clone, serde::Serialize)]
+pub struct MsftLod {
+  // Nodes to use instead of this one, from highest to lowest detail
+  pub ids: Vec<u32>,
+}
+
+impl Extension for MsftLod {
+  const NAME: &'static str = "MSFT_lod";
+}
+
+// Passes each of the MSFT_lod ids in `extensions`, a node's or material's,
+// through `map`. Stops at the first failure, which can leave earlier ids
+// changed
+fn map_lod_ids<F>(
+  extensions: &mut Option<serde_json::Map<String, serde_json::Value>>,
+  mut map: F,
+) -> Result<(), ErrorCode>
+where F: FnMut(u32) -> Result<u32, ErrorCode> {
+  let ids = extensions.as_mut()
+    .and_then(|e| e.get_mut(MsftLod::NAME))
+    .and_then(|e| e.get_mut("ids"))
+    .and_then(|ids| ids.as_array_mut());
+  for id in ids.into_iter().flatten() {
+    if let Some(index) = id.as_u64() {
+      *id = serde_json::Value::from(map(index as u32)?);
+    }
+  }
+  Ok(())
+}
+
+// The MSFT_lod ids in `extensions`, a node's or material's
+fn lod_ids(extensions: &Option<serde_json::Map<String, serde_json::Value>>,
+) -> Vec<u32> {
+  extensions.as_ref()
+    .and_then(|e| e.get(MsftLod::NAME))
+    .and_then(|e| e.get("ids"))
+    .and_then(|ids| ids.as_array())
+    .into_iter().flatten()
+    .filter_map(|id| id.as_u64())
+    .map(|id| id as u32).collect()
+}
+
+impl GLTF {
+  // Gives node `node` the lower detail versions `lods`, from highest to lowest
+  // detail, using MSFT_lod. `screen_coverage` has the fraction of screen
+  // height below which each level stops being used: one for `node` itself
+  // and one for each LOD, so one more than `lods`. The LOD nodes shouldn't be
+  // placed in the scene themselves
+  pub fn set_lods(&mut self, node: u32, lods: &[u32], screen_coverage: &[f64],
+  ) -> Result<(), ErrorCode> {
+    if lods.iter().any(|&lod| lod as usize >= self.nodes.len()) {
+      return Err(ErrorCode::Index);
+    }
+    if screen_coverage.len() != lods.len() + 1 {
+      return Err(ErrorCode::Value);
     }
+    
+    let node = self.nodes.get_mut(node as usize).ok_or(ErrorCode::Index)?;
+    insert_extension(&mut node.extensions, &MsftLod { ids: lods.to_vec() })?;
+    extras_object(&mut node.extras).insert(String::from(
+      "MSFT_screencoverage"), serde_json::Value::from(screen_coverage));
+    self.use_extension(MsftLod::NAME);
+    
+    Ok(())
   }
 }
 
@@ -289,13 +1289,22 @@ pub struct PBRMetallicRoughness {
   #[serde(skip_serializing_if = "is_default_roughness_factor")]
   pub roughness_factor: f64,
   
-  //pub extensions: ??,
+  // Multiplied with base_color_factor. RGB is in sRGB
+  #[serde(rename = "baseColorTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub base_color_texture: Option<TextureInfo>,
   
-  // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,
-  pub metallicRoughnessTexture: ??,
-  pub baseColorTexture: ??,
-  */
+  // Roughness in the green channel and metalness in blue, each multiplied
+  // with its factor
+  #[serde(rename = "metallicRoughnessTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub metallic_roughness_texture: Option<TextureInfo>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl PBRMetallicRoughness {
@@ -304,6 +1313,10 @@ impl PBRMetallicRoughness {
       base_color_factor: Color4::new(),
       metallic_factor: 1.0,
       roughness_factor: 1.0,
+      base_color_texture: None,
+      metallic_roughness_texture: None,
+      extensions: None,
+      extras: None,
     }
   }
 }
@@ -329,7 +1342,7 @@ fn is_default_alpha_cutoff(value: &f64) -> bool {
 }
 
 fn is_default_double_sided(value: &bool) -> bool {
-  *value == false
+  !*value
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -357,13 +1370,26 @@ pub struct Material {
   // Not sure how to skip serializing when unused for this one
   pub pbr_metallic_roughness: PBRMetallicRoughness,
   
-  //pub extensions: ??,
+  // Tangent-space normals
+  #[serde(rename = "normalTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub normal_texture: Option<NormalTextureInfo>,
   
-  // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,
-  pub normalTexture: ??,
-  pub occlusionTexture: ??,
-  pub emissiveTexture: ??,*/
+  // Ambient occlusion in the red channel
+  #[serde(rename = "occlusionTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub occlusion_texture: Option<OcclusionTextureInfo>,
+  
+  // Multiplied with emissive_factor. In sRGB
+  #[serde(rename = "emissiveTexture")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub emissive_texture: Option<TextureInfo>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl Material {
@@ -375,17 +1401,292 @@ impl Material {
       alpha_cutoff: 0.5,
       double_sided: false,
       pbr_metallic_roughness: PBRMetallicRoughness::new(),
+      normal_texture: None,
+      occlusion_texture: None,
+      emissive_texture: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Extension maps inside the material, on its PBR block and texture
+  // references, with their JSON paths relative to the material
+  pub(crate) fn nested_extension_maps(&self,
+  ) -> Vec<(&'static str, &Option<serde_json::Map<String, serde_json::Value>>)>
+  {
+    let pbr = &self.pbr_metallic_roughness;
+    let mut maps = vec![("pbrMetallicRoughness", &pbr.extensions)];
+    let infos = [
+      ("pbrMetallicRoughness.baseColorTexture", &pbr.base_color_texture),
+      ("pbrMetallicRoughness.metallicRoughnessTexture",
+        &pbr.metallic_roughness_texture),
+      ("emissiveTexture", &self.emissive_texture),
+    ];
+    for (name, info) in infos {
+      if let Some(info) = info {
+        maps.push((name, &info.extensions));
+      }
+    }
+    if let Some(info) = &self.normal_texture {
+      maps.push(("normalTexture", &info.extensions));
+    }
+    if let Some(info) = &self.occlusion_texture {
+      maps.push(("occlusionTexture", &info.extensions));
+    }
+    maps
+  }
+  
+  // Passes every texture reference through `map`: the core texture slots,
+  // and texture infos inside the material's extensions. Those are found by
+  // the naming glTF extensions follow, as objects with an index under a key
+  // ending in "Texture". Stops at the first failure, which can leave earlier
+  // references changed
+  pub(crate) fn map_textures<F>(&mut self, mut map: F,
+  ) -> Result<(), ErrorCode>
+  where F: FnMut(u32) -> Result<u32, ErrorCode> {
+    let pbr = &mut self.pbr_metallic_roughness;
+    let indices = [
+      pbr.base_color_texture.as_mut().map(|t| &mut t.index),
+      pbr.metallic_roughness_texture.as_mut().map(|t| &mut t.index),
+      self.normal_texture.as_mut().map(|t| &mut t.index),
+      self.occlusion_texture.as_mut().map(|t| &mut t.index),
+      self.emissive_texture.as_mut().map(|t| &mut t.index),
+    ];
+    for index in indices.into_iter().flatten() {
+      *index = map(*index)?;
+    }
+    
+    for extension in self.extensions.iter_mut().flat_map(|e| e.values_mut()) {
+      map_extension_textures(extension, &mut map)?;
+    }
+    Ok(())
+  }
+  
+  // Settings for leaf and grass cards: cut out by the texture's alpha, and
+  // visible from behind since the cards are single planes
+  pub fn set_foliage(&mut self, cutoff: f64) -> Result<(), ErrorCode> {
+    // A cutoff of 0 or 1 would show or hide the whole card
+    if !(cutoff > 0.0 && cutoff < 1.0) {
+      return Err(ErrorCode::Value);
     }
+    
+    self.double_sided = true;
+    self.alpha_mode = AlphaMode::MASK;
+    self.alpha_cutoff = cutoff;
+    Ok(())
+  }
+  
+  // A floor for product shots that should only show the model's shadow: a
+  // faint, unlit, alpha blended black. glTF has no way to make a surface
+  // receive shadows without also drawing it, so this is a best-effort
+  // convention. Viewers that draw shadows show them darkening the floor,
+  // but the floor itself is still faintly visible everywhere, and viewers
+  // without shadows just show a uniform dark tint. Uses KHR_materials_unlit,
+  // so also call GLTF::use_extension(khr_materials::Unlit::NAME)
+  pub fn shadow_catcher() -> Self {
+    let mut material = Self::new();
+    material.name = String::from("Shadow Catcher");
+    material.alpha_mode = AlphaMode::BLEND;
+    material.pbr_metallic_roughness.base_color_factor = Color4 {
+      r: 0.0, g: 0.0, b: 0.0, a: 0.25,
+    };
+    material.pbr_metallic_roughness.metallic_factor = 0.0;
+    
+    // .unwrap() acceptable here because Unlit has no values to fail validation
+    insert_extension(&mut material.extensions, &khr_materials::Unlit::new())
+      .unwrap();
+    material
+  }
+  
+  // Hash of the material's JSON form, so two materials that would be written
+  // out identically hash the same
+  pub fn content_hash(&self) -> u64 {
+    use std::hash::{Hash, Hasher};
+    
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    // .unwrap() acceptable here because a Material has no maps with non-string
+    // keys, which is the only way serialization to JSON can fail
+    serde_json::to_vec(self).unwrap().hash(&mut hasher);
+    hasher.finish()
   }
 }
 
-// The fields here are in the spec in section 3.7 - Concepts / Geometry,
-// which took me a while to find
+// Material::map_textures() for one extension's JSON
+fn map_extension_textures<F>(value: &mut serde_json::Value, map: &mut F,
+) -> Result<(), ErrorCode>
+where F: FnMut(u32) -> Result<u32, ErrorCode> {
+  match value {
+    serde_json::Value::Object(object) => {
+      for (key, value) in object.iter_mut() {
+        let index = value.get("index").and_then(|index| index.as_u64());
+        match index {
+          Some(index) if key.ends_with("Texture") => {
+            value["index"] = serde_json::Value::from(map(index as u32)?);
+          },
+          _ => map_extension_textures(value, map)?,
+        }
+      }
+    },
+    serde_json::Value::Array(values) => {
+      for value in values.iter_mut() {
+        map_extension_textures(value, map)?;
+      }
+    },
+    _ => {},
+  }
+  Ok(())
+}
+
+fn is_default_tex_coord(value: &u32) -> bool {
+  *value == 0
+}
+
+// Reference from a material to a texture
 #[derive(Clone, serde::Serialize)]
-pub struct Attributes {
-  #[serde(rename = "COLOR_0")]
+pub struct TextureInfo {
+  pub index: u32,
+  
+  // Which TEXCOORD_n attribute to use
+  #[serde(rename = "texCoord")]
+  #[serde(skip_serializing_if = "is_default_tex_coord")]
+  pub tex_coord: u32,
+  
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub color_0: Option<u32>,
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl TextureInfo {
+  pub fn new(index: u32) -> Self {
+    Self { index, tex_coord: 0, extensions: None, extras: None }
+  }
+}
+
+fn is_default_scale(value: &f64) -> bool {
+  *value == 1.0
+}
+
+// Reference from a material to its normal map
+#[derive(Clone, serde::Serialize)]
+pub struct NormalTextureInfo {
+  pub index: u32,
+  
+  #[serde(rename = "texCoord")]
+  #[serde(skip_serializing_if = "is_default_tex_coord")]
+  pub tex_coord: u32,
+  
+  // Multiplies the map's X and Y, to strengthen or soften the bumps
+  #[serde(skip_serializing_if = "is_default_scale")]
+  pub scale: f64,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl NormalTextureInfo {
+  pub fn new(index: u32) -> Self {
+    Self {
+      index,
+      tex_coord: 0,
+      scale: 1.0,
+      extensions: None,
+      extras: None,
+    }
+  }
+}
+
+// Reference from a material to its occlusion map
+#[derive(Clone, serde::Serialize)]
+pub struct OcclusionTextureInfo {
+  pub index: u32,
+  
+  #[serde(rename = "texCoord")]
+  #[serde(skip_serializing_if = "is_default_tex_coord")]
+  pub tex_coord: u32,
+  
+  // How much of the occlusion to apply, from 0 (none) to 1 (all)
+  #[serde(skip_serializing_if = "is_default_scale")]
+  pub strength: f64,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl OcclusionTextureInfo {
+  pub fn new(index: u32) -> Self {
+    Self {
+      index,
+      tex_coord: 0,
+      strength: 1.0,
+      extensions: None,
+      extras: None,
+    }
+  }
+}
+
+// The vertex attributes Attributes has slots for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Semantic {
+  Color0,
+  Joints0,
+  Normal,
+  Position,
+  Tangent,
+  Texcoord0,
+  Texcoord1,
+  Texcoord2,
+  Texcoord3,
+  Weights0,
+}
+
+impl Semantic {
+  // In the same order as Attributes' fields, which is how glTF files list
+  // them
+  pub const ALL: [Semantic; 10] = [
+    Semantic::Color0,
+    Semantic::Joints0,
+    Semantic::Normal,
+    Semantic::Position,
+    Semantic::Tangent,
+    Semantic::Texcoord0,
+    Semantic::Texcoord1,
+    Semantic::Texcoord2,
+    Semantic::Texcoord3,
+    Semantic::Weights0,
+  ];
+  
+  // Attribute name as written in glTF
+  pub fn name(&self) -> &'static str {
+    match self {
+      Semantic::Color0 => "COLOR_0",
+      Semantic::Joints0 => "JOINTS_0",
+      Semantic::Normal => "NORMAL",
+      Semantic::Position => "POSITION",
+      Semantic::Tangent => "TANGENT",
+      Semantic::Texcoord0 => "TEXCOORD_0",
+      Semantic::Texcoord1 => "TEXCOORD_1",
+      Semantic::Texcoord2 => "TEXCOORD_2",
+      Semantic::Texcoord3 => "TEXCOORD_3",
+      Semantic::Weights0 => "WEIGHTS_0",
+    }
+  }
+}
+
+// The fields here are in the spec in section 3.7 - Concepts / Geometry,
+// which took me a while to find
+#[derive(Clone, serde::Serialize)]
+pub struct Attributes {
+  #[serde(rename = "COLOR_0")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub color_0: Option<u32>,
   
   #[serde(rename = "JOINTS_0")]
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -439,6 +1740,52 @@ impl Attributes {
       weights_0: None,
     }
   }
+  
+  // Accessor for attribute `semantic`, if present
+  pub fn get(&self, semantic: Semantic) -> Option<u32> {
+    match semantic {
+      Semantic::Color0 => self.color_0,
+      Semantic::Joints0 => self.joints_0,
+      Semantic::Normal => self.normal,
+      Semantic::Position => self.position,
+      Semantic::Tangent => self.tangent,
+      Semantic::Texcoord0 => self.texcoord_0,
+      Semantic::Texcoord1 => self.texcoord_1,
+      Semantic::Texcoord2 => self.texcoord_2,
+      Semantic::Texcoord3 => self.texcoord_3,
+      Semantic::Weights0 => self.weights_0,
+    }
+  }
+  
+  pub fn has(&self, semantic: Semantic) -> bool {
+    self.get(semantic).is_some()
+  }
+  
+  // Attributes that have an accessor, in Semantic::ALL order
+  pub fn present(&self) -> Vec<Semantic> {
+    Semantic::ALL.into_iter().filter(|&semantic| self.has(semantic)).collect()
+  }
+  
+  // Every accessor slot with its glTF attribute name
+  pub(crate) fn named(&self) -> [(&'static str, Option<u32>); 10] {
+    Semantic::ALL.map(|semantic| (semantic.name(), self.get(semantic)))
+  }
+  
+  // Every accessor slot, for passes that need to rewrite accessor indices
+  pub(crate) fn accessors_mut(&mut self) -> [&mut Option<u32>; 10] {
+    [
+      &mut self.color_0,
+      &mut self.joints_0,
+      &mut self.normal,
+      &mut self.position,
+      &mut self.tangent,
+      &mut self.texcoord_0,
+      &mut self.texcoord_1,
+      &mut self.texcoord_2,
+      &mut self.texcoord_3,
+      &mut self.weights_0,
+    ]
+  }
 }
 
 #[derive(Clone, PartialEq, serde_repr::Serialize_repr)]
@@ -453,6 +1800,22 @@ pub enum Mode {
   TriangleFan = 6,
 }
 
+impl Mode {
+  // Number of points, line segments, or triangles drawn from `vertices`
+  // vertices (or indices, for indexed primitives)
+  pub fn primitive_count(&self, vertices: u64) -> u64 {
+    match self {
+      Mode::Points => vertices,
+      Mode::Lines => vertices / 2,
+      // The closing segment makes one segment per vertex
+      Mode::LineLoop => if vertices < 2 { 0 } else { vertices },
+      Mode::LineStrip => vertices.saturating_sub(1),
+      Mode::Triangles => vertices / 3,
+      Mode::TriangleStrip | Mode::TriangleFan => vertices.saturating_sub(2),
+    }
+  }
+}
+
 fn is_default_mode(value: &Mode) -> bool {
   *value == Mode::Triangles
 }
@@ -470,11 +1833,19 @@ pub struct MeshPrimitive {
   #[serde(skip_serializing_if = "is_default_mode")]
   pub mode: Mode, // Default is triangles
   
-  //pub extensions: ??,
+  // Min and max corners of the POSITION data, cached by bounds(). Never
+  // written out
+  #[serde(skip)]
+  pub bounds: Option<([f64; 3], [f64; 3])>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
   
   // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,
-  pub targets: ??,*/
+  /*pub targets: ??,*/
 }
 
 impl MeshPrimitive {
@@ -484,7 +1855,62 @@ impl MeshPrimitive {
       indices: None,
       material: None,
       mode: Mode::Triangles,
+      bounds: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Axis-aligned bounding box of the POSITION data, as min and max corners.
+  // Computed from the data the first time and cached after that, so call
+  // invalidate_bounds() after editing positions. `accessors`,
+  // `buffer_views`, and `buffers` are the owning document's
+  pub fn bounds(&mut self, accessors: &[Accessor], buffer_views: &[BufferView],
+    buffers: &[Vec<u8>],
+  ) -> Result<([f64; 3], [f64; 3]), ErrorCode> {
+    if let Some(bounds) = self.bounds {
+      return Ok(bounds);
+    }
+    
+    let accessor = accessors.get(self.attributes.position.ok_or(
+      ErrorCode::Value)? as usize).ok_or(ErrorCode::Index)?;
+    if accessor.type_ != Type::VEC3 {
+      return Err(ErrorCode::Value);
     }
+    let buffer_view = buffer_views.get(accessor.buffer_view.ok_or(
+      ErrorCode::Index)? as usize).ok_or(ErrorCode::Index)?;
+    
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for (i, value) in accessor.iter_f32(buffers, buffer_view)?.enumerate() {
+      min[i % 3] = min[i % 3].min(value as f64);
+      max[i % 3] = max[i % 3].max(value as f64);
+    }
+    if accessor.count == 0 {
+      min = [0.0; 3];
+      max = [0.0; 3];
+    }
+    
+    self.bounds = Some((min, max));
+    Ok((min, max))
+  }
+  
+  pub fn invalidate_bounds(&mut self) {
+    self.bounds = None;
+  }
+  
+  // Number of points, line segments, or triangles this primitive draws, using
+  // the index count if it has indices and the POSITION count otherwise.
+  // `accessors` is the owning document's accessor list
+  pub fn primitive_count(&self, accessors: &[Accessor],
+  ) -> Result<u64, ErrorCode> {
+    let vertices = match self.indices.or(self.attributes.position) {
+      Some(index) => accessors.get(index as usize)
+        .ok_or(ErrorCode::Index)?.count as u64,
+      None => 0,
+    };
+    
+    Ok(self.mode.primitive_count(vertices))
   }
 }
 
@@ -493,29 +1919,107 @@ pub struct Mesh {
   #[serde(skip_serializing_if = "String::is_empty")]
   pub name: String,
   
-  // No serialization filter, this is required per spec
+  // No serialization filter, this is required per spec. Must also have at
+  // least one primitive, which GLTF::validate() checks
   pub primitives: Vec<MeshPrimitive>,
   
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub weights: Vec<f64>,
   
-  //pub extensions: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
   
-  // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,*/
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl Mesh {
+  // Starts with no primitives, which is not valid glTF, so at least one must be
+  // added before writing
   pub fn new() -> Self {
     Self {
       primitives: Vec::new(),
       weights: Vec::new(),
       name: String::from(""),
+      extensions: None,
+      extras: None,
     }
   }
 }
 
-#[derive(Clone, PartialEq, serde_repr::Serialize_repr)]
+impl GLTF {
+  // Axis-aligned bounding box of mesh `mesh`, as min and max corners, in the
+  // mesh's own coordinates. Uses each primitive's cached bounds where present
+  pub fn mesh_bounds(&mut self, mesh: u32, buffers: &[Vec<u8>],
+  ) -> Result<([f64; 3], [f64; 3]), ErrorCode> {
+    let mesh = self.meshes.get_mut(mesh as usize).ok_or(ErrorCode::Index)?;
+    
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for primitive in mesh.primitives.iter_mut() {
+      let (p_min, p_max) = primitive.bounds(&self.accessors,
+        &self.buffer_views, buffers)?;
+      for i in 0..3 {
+        min[i] = min[i].min(p_min[i]);
+        max[i] = max[i].max(p_max[i]);
+      }
+    }
+    
+    if mesh.primitives.is_empty() {
+      return Ok(([0.0; 3], [0.0; 3]));
+    }
+    Ok((min, max))
+  }
+  
+  // Sphere enclosing mesh `mesh`, as center and radius, in the mesh's own
+  // coordinates. Uses Ritter's algorithm, which is fast and usually within a
+  // few percent of the smallest sphere. Meshes with no vertices get a zero
+  // radius sphere at the origin
+  pub fn mesh_bounding_sphere(&self, mesh: u32, buffers: &[Vec<u8>],
+  ) -> Result<([f64; 3], f64), ErrorCode> {
+    let mesh = self.meshes.get(mesh as usize).ok_or(ErrorCode::Index)?;
+    
+    let mut points = Vec::new();
+    for primitive in mesh.primitives.iter() {
+      if primitive.attributes.position.is_some() {
+        points.extend(self.read_positions(primitive, buffers)?.iter()
+          .map(|p| p.map(|x| x as f64)));
+      }
+    }
+    
+    let Some(&first) = points.first() else {
+      return Ok(([0.0; 3], 0.0));
+    };
+    let distance_squared = |a: [f64; 3], b: [f64; 3]| {
+      (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum::<f64>()
+    };
+    let farthest_from = |from: [f64; 3]| points.iter().copied()
+      .max_by(|&a, &b| distance_squared(from, a)
+        .total_cmp(&distance_squared(from, b)))
+      .unwrap_or(from);
+    
+    // Start from a sphere on two roughly opposite points, then grow it to
+    // take in any points left outside
+    let a = farthest_from(first);
+    let b = farthest_from(a);
+    let mut center = [0, 1, 2].map(|i| (a[i] + b[i]) / 2.0);
+    let mut radius = distance_squared(a, b).sqrt() / 2.0;
+    
+    for &point in points.iter() {
+      let distance = distance_squared(center, point).sqrt();
+      if distance > radius {
+        let new_radius = (radius + distance) / 2.0;
+        let shift = (new_radius - radius) / distance;
+        center = [0, 1, 2].map(|i| center[i] + (point[i] - center[i]) * shift);
+        radius = new_radius;
+      }
+    }
+    
+    Ok((center, radius))
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, serde_repr::Serialize_repr)]
 #[repr(u16)]
 pub enum ComponentType {
   Byte = 5120,
@@ -526,7 +2030,62 @@ pub enum ComponentType {
   Float = 5126,
 }
 
-#[derive(Clone, serde::Serialize)]
+impl ComponentType {
+  // Size in bytes
+  pub fn size(&self) -> usize {
+    match self {
+      ComponentType::Byte | ComponentType::UnsignedByte => 1,
+      ComponentType::Short | ComponentType::UnsignedShort => 2,
+      ComponentType::UnsignedInt | ComponentType::Float => 4,
+    }
+  }
+  
+  // Reads one little-endian component from the start of `bytes`. Normalized
+  // integers are mapped to 0..1 or -1..1 as described in section 3.11 of the
+  // spec, where the most negative value clamps to -1
+  fn read_f32(&self, bytes: &[u8], normalized: bool) -> f32 {
+    match self {
+      ComponentType::Byte => {
+        let value = bytes[0] as i8 as f32;
+        if normalized { (value / 127.0).max(-1.0) } else { value }
+      },
+      ComponentType::UnsignedByte => {
+        let value = bytes[0] as f32;
+        if normalized { value / 255.0 } else { value }
+      },
+      ComponentType::Short => {
+        let value = i16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+        if normalized { (value / 32767.0).max(-1.0) } else { value }
+      },
+      ComponentType::UnsignedShort => {
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+        if normalized { value / 65535.0 } else { value }
+      },
+      ComponentType::UnsignedInt => {
+        let value =
+          u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if normalized { value as f32 / u32::MAX as f32 } else { value as f32 }
+      },
+      ComponentType::Float => {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+      },
+    }
+  }
+  
+  // Reads one little-endian component from the start of `bytes` as stored,
+  // ignoring normalization. Exact for every type, including UnsignedInt
+  // values too large for an f32
+  fn read_raw(&self, bytes: &[u8]) -> f64 {
+    match self {
+      ComponentType::UnsignedInt => {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+      },
+      _ => self.read_f32(bytes, false) as f64,
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum Type {
   SCALAR,
   VEC2,
@@ -537,6 +2096,19 @@ pub enum Type {
   MAT4,
 }
 
+impl Type {
+  pub fn components(&self) -> usize {
+    match self {
+      Type::SCALAR => 1,
+      Type::VEC2 => 2,
+      Type::VEC3 => 3,
+      Type::VEC4 | Type::MAT2 => 4,
+      Type::MAT3 => 9,
+      Type::MAT4 => 16,
+    }
+  }
+}
+
 #[derive(Clone, serde::Serialize)]
 pub struct Accessor {
   // Next time I modify this, I want to try out:
@@ -570,13 +2142,16 @@ pub struct Accessor {
   #[serde(skip_serializing_if = "Vec::is_empty")]
   pub min: Vec<f64>,
   
-  //pub extensions: ??,
+  // Elements replaced by values stored elsewhere. Readers here (read_f32()
+  // and the like) see only the buffer view's data, without the replacements
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sparse: Option<AccessorSparse>,
   
-  // In the .gltf spec but will have to wait for now:
-  /* pub max: ??,
-  pub min: ??,
-  pub sparse: ??,
-  pub extras: ??,*/
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl Accessor {
@@ -591,8 +2166,137 @@ impl Accessor {
       type_: Type::SCALAR,
       min: Vec::new(),
       max: Vec::new(),
+      sparse: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Size in bytes of one element, without any padding from byte_stride. Does
+  // not account for the column padding glTF requires in byte and short
+  // matrices
+  pub fn element_size(&self) -> usize {
+    self.type_.components() * self.component_type.size()
+  }
+  
+  // The bytes holding this accessor's elements, from the start of the first
+  // to the end of the last, and the distance between element starts. Fails
+  // if any element would lie outside the buffer view or buffer
+  fn element_bytes<'a>(&self, buffers: &'a [Vec<u8>], view: &BufferView,
+  ) -> Result<(&'a [u8], usize), ErrorCode> {
+    let element_size = self.element_size();
+    let stride = view.byte_stride.map_or(element_size, |s| s as usize);
+    
+    let length = match self.count as usize {
+      0 => 0,
+      count => stride * (count - 1) + element_size,
+    };
+    if self.byte_offset as usize + length > view.byte_length as usize {
+      return Err(ErrorCode::Index);
+    }
+    
+    let start = view.byte_offset as usize + self.byte_offset as usize;
+    let bytes = buffers.get(view.buffer as usize)
+      .and_then(|buffer| buffer.get(start..start + length))
+      .ok_or(ErrorCode::Index)?;
+    
+    Ok((bytes, stride))
+  }
+  
+  // Decodes each component of each element as it is iterated, so large
+  // accessors can be processed without a second copy of their data. `view` is
+  // the buffer view this accessor points at, and `buffers` the binary
+  // contents of the document's buffers. Fails up front if any element would
+  // lie outside the buffer view or buffer
+  pub fn iter_f32<'a>(&'a self, buffers: &'a [Vec<u8>], view: &'a BufferView,
+  ) -> Result<impl Iterator<Item = f32> + 'a, ErrorCode> {
+    let (bytes, stride) = self.element_bytes(buffers, view)?;
+    let component_size = self.component_type.size();
+    let components = self.type_.components();
+    
+    Ok((0..self.count as usize * components).map(move |i| {
+      let offset = (i / components) * stride
+        + (i % components) * component_size;
+      self.component_type.read_f32(&bytes[offset..], self.normalized)
+    }))
+  }
+  
+  pub fn read_f32(&self, buffers: &[Vec<u8>], view: &BufferView,
+  ) -> Result<Vec<f32>, ErrorCode> {
+    Ok(self.iter_f32(buffers, view)?.collect())
+  }
+  
+  // Decodes an index accessor, whatever its component width. Index accessors
+  // must be unsigned integer scalars, anything else is an error
+  pub fn read_indices(&self, buffers: &[Vec<u8>], view: &BufferView,
+  ) -> Result<Vec<u32>, ErrorCode> {
+    if self.type_ != Type::SCALAR {
+      return Err(ErrorCode::Value);
     }
+    
+    let (bytes, stride) = self.element_bytes(buffers, view)?;
+    let read: fn(&[u8]) -> u32 = match self.component_type {
+      ComponentType::UnsignedByte => |b| b[0] as u32,
+      ComponentType::UnsignedShort => |b| {
+        u16::from_le_bytes([b[0], b[1]]) as u32
+      },
+      ComponentType::UnsignedInt => |b| {
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+      },
+      _ => return Err(ErrorCode::Value),
+    };
+    
+    Ok((0..self.count as usize).map(|i| read(&bytes[i * stride..])).collect())
   }
+  
+  // Per-component statistics over the decoded values, for choosing
+  // quantization or spotting degenerate data such as all-zero normals
+  pub fn stats(&self, buffers: &[Vec<u8>], view: &BufferView,
+  ) -> Result<AccessorStats, ErrorCode> {
+    let components = self.type_.components();
+    let mut stats = AccessorStats {
+      min: vec![f64::INFINITY; components],
+      max: vec![f64::NEG_INFINITY; components],
+      mean: vec![0.0; components],
+      stddev: vec![0.0; components],
+    };
+    
+    // Welford's algorithm, so the values are only read once. stddev holds the
+    // running sum of squared differences until the end
+    for (i, value) in self.iter_f32(buffers, view)?.enumerate() {
+      let value = value as f64;
+      let c = i % components;
+      let n = (i / components + 1) as f64;
+      let delta = value - stats.mean[c];
+      
+      stats.min[c] = stats.min[c].min(value);
+      stats.max[c] = stats.max[c].max(value);
+      stats.mean[c] += delta / n;
+      stats.stddev[c] += delta * (value - stats.mean[c]);
+    }
+    
+    for c in 0..components {
+      if self.count == 0 {
+        stats.min[c] = 0.0;
+        stats.max[c] = 0.0;
+      } else {
+        stats.stddev[c] = (stats.stddev[c] / self.count as f64).sqrt();
+      }
+    }
+    
+    Ok(stats)
+  }
+}
+
+// Per-component statistics from Accessor::stats(). All zeroes if the accessor
+// is empty
+#[derive(Clone, Debug)]
+pub struct AccessorStats {
+  pub min: Vec<f64>,
+  pub max: Vec<f64>,
+  pub mean: Vec<f64>,
+  // Population standard deviation
+  pub stddev: Vec<f64>,
 }
 
 fn is_default_byte_offset(value: &u32) -> bool {
@@ -600,10 +2304,10 @@ fn is_default_byte_offset(value: &u32) -> bool {
 }
 
 fn is_default_normalized(value: &bool) -> bool {
-  *value == false
+  !*value
 }
 
-#[derive(Clone, PartialEq, serde_repr::Serialize_repr)]
+#[derive(Clone, Copy, PartialEq, serde_repr::Serialize_repr)]
 #[repr(u16)]
 pub enum Target {
   ArrayBuffer = 34962,
@@ -630,10 +2334,36 @@ pub struct BufferView {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub target: Option<Target>,
   
-  //pub extensions: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
   
-  // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,*/
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+// How hard a post-processor (such as gltfpack) should compress a buffer
+// view, stored as `compress` in the view's extras. glTF viewers ignore it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionHint {
+  Low,
+  Medium,
+  High,
+}
+
+impl CompressionHint {
+  pub const ALL: [CompressionHint; 3] = [
+    CompressionHint::Low,
+    CompressionHint::Medium,
+    CompressionHint::High,
+  ];
+  
+  pub fn name(&self) -> &'static str {
+    match self {
+      CompressionHint::Low => "low",
+      CompressionHint::Medium => "medium",
+      CompressionHint::High => "high",
+    }
+  }
 }
 
 impl BufferView {
@@ -645,8 +2375,21 @@ impl BufferView {
       byte_offset: 0,
       byte_stride: None,
       target: None,
+      extensions: None,
+      extras: None,
     }
   }
+  
+  pub fn set_compression_hint(&mut self, level: CompressionHint) {
+    extras_object(&mut self.extras).insert(String::from("compress"),
+      serde_json::Value::from(level.name()));
+  }
+  
+  // None if unset or unrecognized
+  pub fn compression_hint(&self) -> Option<CompressionHint> {
+    let name = self.extras.as_ref()?.get("compress")?.as_str()?;
+    CompressionHint::ALL.into_iter().find(|level| level.name() == name)
+  }
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -660,10 +2403,11 @@ pub struct Buffer {
   #[serde(skip_serializing_if = "String::is_empty")]
   pub uri: String,
   
-  //pub extensions: ??,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
   
-  // In the .gltf spec but will have to wait for now:
-  /*pub extras: ??,*/
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
 }
 
 impl Buffer {
@@ -672,19 +2416,532 @@ impl Buffer {
       name: String::from(""),
       byte_length: 0,
       uri: String::from(""),
+      extensions: None,
+      extras: None,
     }
   }
 }
 
-pub fn write_gltf(buffer: &mut Vec<u8>, gltf: GLTF) {
+#[derive(Clone, serde::Serialize)]
+pub struct Image {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // Either a data: URI, or a (URI-encoded) path relative to the .gltf file
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub uri: String,
+  
+  // Required when using buffer_view, optional with uri
+  #[serde(rename = "mimeType")]
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub mime_type: String,
+  
+  #[serde(rename = "bufferView")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub buffer_view: Option<u32>,
+  
+  // Where the file for an external image currently is, so write_files() can
+  // copy it next to the .gltf
+  #[serde(skip)]
+  pub source: Option<std::path::PathBuf>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Image {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      uri: String::from(""),
+      mime_type: String::from(""),
+      buffer_view: None,
+      source: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // An image kept in its own file, at relative path `path` both now and
+  // (after GLTF::write_files()) relative to the written .gltf
+  pub fn external(path: &str) -> Self {
+    let mut image = Self::new();
+    image.uri = encode_uri_path(path);
+    image.source = Some(std::path::PathBuf::from(path));
+    image
+  }
+  
+  // Images must get their data from exactly one of uri or buffer_view, and
+  // images in buffer views must say what format they are
+  pub fn validate(&self) -> Result<(), ErrorCode> {
+    match (self.uri.is_empty(), self.buffer_view) {
+      (false, None) => Ok(()),
+      (true, Some(_)) if !self.mime_type.is_empty() => Ok(()),
+      _ => Err(ErrorCode::Value),
+    }
+  }
+}
+
+// Percent-encodes everything in a relative path except unreserved characters
+// and separators, per RFC 3986
+fn encode_uri_path(path: &str) -> String {
+  let mut encoded = String::new();
+  for byte in path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~'
+        | b'/' => encoded.push(byte as char),
+      _ => encoded.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  encoded
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct Texture {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // Image to use, unless an extension supplies a different one
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub source: Option<u32>,
+  
+  // Without a sampler, textures repeat and filtering is up to the viewer
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sampler: Option<u32>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Texture {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      source: None,
+      sampler: None,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Uses KTX2 image `image` through KHR_texture_basisu. The plain source is
+  // cleared, since with the extension required there's no fallback to give.
+  // The extension must be listed in extensionsRequired, which
+  // GLTF::add_basisu_texture() does
+  pub fn set_basisu_source(&mut self, image: u32) {
+    self.source = None;
+    self.extensions.get_or_insert_with(serde_json::Map::new).insert(
+      String::from("KHR_texture_basisu"), serde_json::json!({
+        "source": image,
+      }));
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde_repr::Serialize_repr)]
+#[repr(u16)]
+pub enum MagFilter {
+  Nearest = 9728,
+  Linear = 9729,
+}
+
+// The mipmap variants need the image to have mipmaps, which viewers generate
+// when loading
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde_repr::Serialize_repr)]
+#[repr(u16)]
+pub enum MinFilter {
+  Nearest = 9728,
+  Linear = 9729,
+  NearestMipmapNearest = 9984,
+  LinearMipmapNearest = 9985,
+  NearestMipmapLinear = 9986,
+  LinearMipmapLinear = 9987,
+}
+
+// What happens to UVs outside 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde_repr::Serialize_repr)]
+#[repr(u16)]
+pub enum WrapMode {
+  ClampToEdge = 33071,
+  MirroredRepeat = 33648,
+  Repeat = 10497,
+}
+
+fn is_default_wrap_mode(value: &WrapMode) -> bool {
+  *value == WrapMode::Repeat
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct Sampler {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // Filters are left to the viewer if not given
+  #[serde(rename = "magFilter")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub mag_filter: Option<MagFilter>,
+  
+  #[serde(rename = "minFilter")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub min_filter: Option<MinFilter>,
+  
+  // Along U
+  #[serde(rename = "wrapS")]
+  #[serde(skip_serializing_if = "is_default_wrap_mode")]
+  pub wrap_s: WrapMode,
+  
+  // Along V
+  #[serde(rename = "wrapT")]
+  #[serde(skip_serializing_if = "is_default_wrap_mode")]
+  pub wrap_t: WrapMode,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Sampler {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      mag_filter: None,
+      min_filter: None,
+      wrap_s: WrapMode::Repeat,
+      wrap_t: WrapMode::Repeat,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Blocky, unfiltered texels, for pixel art
+  pub fn pixelated() -> Self {
+    let mut sampler = Self::new();
+    sampler.mag_filter = Some(MagFilter::Nearest);
+    sampler.min_filter = Some(MinFilter::Nearest);
+    sampler
+  }
+}
+
+impl GLTF {
+  // Adds a texture showing image `image` through sampler `sampler`, if any.
+  // Fails if either doesn't exist. Returns the texture's index
+  pub fn add_texture(&mut self, image: u32, sampler: Option<u32>,
+  ) -> Result<u32, ErrorCode> {
+    if image as usize >= self.images.len()
+      || sampler.is_some_and(|s| s as usize >= self.samplers.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    let mut texture = Texture::new();
+    texture.source = Some(image);
+    texture.sampler = sampler;
+    self.textures.push(texture);
+    Ok(self.textures.len() as u32 - 1)
+  }
+}
+
+impl GLTF {
+  // Adds a texture showing KTX2 image `image` through KHR_texture_basisu, and
+  // marks the extension required. Fails if the image doesn't exist, or says
+  // it's something other than image/ktx2. Returns the texture's index
+  pub fn add_basisu_texture(&mut self, image: u32) -> Result<u32, ErrorCode> {
+    let mime_type = &self.images.get(image as usize)
+      .ok_or(ErrorCode::Index)?.mime_type;
+    if !mime_type.is_empty() && mime_type != "image/ktx2" {
+      return Err(ErrorCode::Value);
+    }
+    
+    let mut texture = Texture::new();
+    texture.set_basisu_source(image);
+    self.textures.push(texture);
+    self.require_extension("KHR_texture_basisu");
+    
+    Ok(self.textures.len() as u32 - 1)
+  }
+}
+
+#[derive(Clone, PartialEq, serde::Serialize)]
+pub enum Interpolation {
+  LINEAR,
+  STEP,
+  CUBICSPLINE,
+}
+
+fn is_default_interpolation(value: &Interpolation) -> bool {
+  *value == Interpolation::LINEAR
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct AnimationSampler {
+  // Accessor of keyframe times
+  pub input: u32,
+  
+  #[serde(skip_serializing_if = "is_default_interpolation")]
+  pub interpolation: Interpolation,
+  
+  // Accessor of keyframe values
+  pub output: u32,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl AnimationSampler {
+  pub fn new() -> Self {
+    Self {
+      input: 0,
+      interpolation: Interpolation::LINEAR,
+      output: 0,
+      extensions: None,
+      extras: None,
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationPath {
+  Translation,
+  Rotation,
+  Scale,
+  Weights,
+  // Target is given by the KHR_animation_pointer extension instead of a node
+  Pointer,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct AnimationChannelTarget {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub node: Option<u32>,
+  
+  pub path: AnimationPath,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl AnimationChannelTarget {
+  pub fn new() -> Self {
+    Self {
+      node: None,
+      path: AnimationPath::Translation,
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Target for KHR_animation_pointer, where `pointer` is a JSON pointer to the
+  // animated property, such as
+  // `/materials/0/pbrMetallicRoughness/baseColorFactor`
+  pub fn pointer(pointer: &str) -> Self {
+    let mut extension = serde_json::Map::new();
+    extension.insert(String::from("pointer"),
+      serde_json::Value::from(pointer));
+    
+    let mut extensions = serde_json::Map::new();
+    extensions.insert(String::from("KHR_animation_pointer"),
+      serde_json::Value::Object(extension));
+    
+    Self {
+      node: None,
+      path: AnimationPath::Pointer,
+      extensions: Some(extensions),
+      extras: None,
+    }
+  }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct AnimationChannel {
+  pub sampler: u32,
+  
+  pub target: AnimationChannelTarget,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl AnimationChannel {
+  pub fn new() -> Self {
+    Self {
+      sampler: 0,
+      target: AnimationChannelTarget::new(),
+      extensions: None,
+      extras: None,
+    }
+  }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct Animation {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // No serialization filter, this is required per spec
+  pub channels: Vec<AnimationChannel>,
+  
+  // No serialization filter, this is required per spec
+  pub samplers: Vec<AnimationSampler>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Animation {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      channels: Vec::new(),
+      samplers: Vec::new(),
+      extensions: None,
+      extras: None,
+    }
+  }
+  
+  // Bakes sampled transforms of node `node` into an animation, with a LINEAR
+  // channel for each of translation, rotation (quaternions), and scale. Each
+  // sample array has one entry per time in `times`, or is empty to leave that
+  // property unanimated. Sample data is added through `builder`. The
+  // animation itself is returned for the caller to push
+  pub fn from_trs_samples(builder: &mut BufferBuilder, node: u32,
+    times: &[f32], translations: &[[f32; 3]], rotations: &[[f32; 4]],
+    scales: &[[f32; 3]],
+  ) -> Result<Self, ErrorCode> {
+    if times.is_empty() || [translations.len(), rotations.len(), scales.len()]
+      .iter().any(|&len| len != 0 && len != times.len()) {
+      return Err(ErrorCode::Value);
+    }
+    
+    let input = builder.push_times(times)?;
+    let outputs = [
+      (AnimationPath::Translation, match translations.is_empty() {
+        true => None,
+        false => Some(builder.push_samples(translations)?),
+      }),
+      (AnimationPath::Rotation, match rotations.is_empty() {
+        true => None,
+        false => Some(builder.push_samples(rotations)?),
+      }),
+      (AnimationPath::Scale, match scales.is_empty() {
+        true => None,
+        false => Some(builder.push_samples(scales)?),
+      }),
+    ];
+    
+    let mut animation = Self::new();
+    for (path, output) in outputs {
+      let Some(output) = output else { continue };
+      
+      let mut sampler = AnimationSampler::new();
+      sampler.input = input;
+      sampler.output = output;
+      animation.samplers.push(sampler);
+      
+      let mut channel = AnimationChannel::new();
+      channel.sampler = animation.samplers.len() as u32 - 1;
+      channel.target.node = Some(node);
+      channel.target.path = path;
+      animation.channels.push(channel);
+    }
+    
+    Ok(animation)
+  }
+}
+
+impl GLTF {
+  // Adds a channel to animation `animation` that drives the property at JSON
+  // pointer `pointer` from sampler `sampler`, using KHR_animation_pointer
+  pub fn add_pointer_channel(&mut self, animation: u32, sampler: u32,
+    pointer: &str,
+  ) -> Result<(), ErrorCode> {
+    if !pointer.starts_with('/') {
+      return Err(ErrorCode::Value);
+    }
+    
+    let animation = self.animations.get_mut(animation as usize)
+      .ok_or(ErrorCode::Index)?;
+    if sampler as usize >= animation.samplers.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    animation.channels.push(AnimationChannel {
+      sampler,
+      target: AnimationChannelTarget::pointer(pointer),
+      extensions: None,
+      extras: None,
+    });
+    self.use_extension("KHR_animation_pointer");
+    
+    Ok(())
+  }
+}
+
+impl GLTF {
+  // Makes the only scene the default if no default is set, since some viewers
+  // show nothing without one. With no scenes, or several, nothing changes:
+  // choosing between scenes is up to the model. Called by write_gltf() and
+  // write_files()
+  pub fn ensure_default_scene(&mut self) {
+    if self.scene.is_none() && self.scenes.len() == 1 {
+      self.scene = Some(0);
+    }
+  }
+  
+  // Writes the document as .gltf JSON to any writer, such as a file, socket,
+  // compressor, or hasher. `pretty` adds indentation and newlines
+  pub fn write_to<W: std::io::Write>(&self, w: &mut W, pretty: bool,
+  ) -> Result<(), serde_json::Error> {
+    match pretty {
+      true => serde_json::ser::to_writer_pretty(w, self),
+      false => serde_json::ser::to_writer(w, self),
+    }
+  }
+}
+
+pub fn write_gltf(buffer: &mut Vec<u8>, mut gltf: GLTF) {
+  gltf.ensure_default_scene();
+  
   let mut dry_run_writer = DryRunWriter::new();
-  serde_json::ser::to_writer_pretty(&mut dry_run_writer, &gltf).unwrap();
+  gltf.write_to(&mut dry_run_writer, true).unwrap();
   let space_required = dry_run_writer.bytes_written;
   
   buffer.reserve_exact(space_required);
-  serde_json::ser::to_writer_pretty(&mut (*buffer), &gltf).unwrap();
+  gltf.write_to(buffer, true).unwrap();
   buffer.shrink_to_fit();
   
   POINTER.store(buffer.as_ptr() as u32, Ordering::Relaxed);
   SIZE.store(buffer.len() as u32, Ordering::Relaxed);
 }
+
+// Like write_gltf(), but writes a .glb file, with `buffers` (the contents of
+// the document's buffers) in its binary chunk instead of data URIs. See
+// GLTF::to_glb(). Returns warnings for anything that isn't 4-byte aligned,
+// such as the binary chunk needing padding
+pub fn write_glb(buffer: &mut Vec<u8>, gltf: GLTF, buffers: &[Vec<u8>],
+) -> Result<Vec<ValidationMessage>, ErrorCode> {
+  let (glb, warnings) = gltf.to_glb(buffers)?;
+  *buffer = glb;
+  
+  POINTER.store(buffer.as_ptr() as u32, Ordering::Relaxed);
+  SIZE.store(buffer.len() as u32, Ordering::Relaxed);
+  Ok(warnings)
+}
@@ -0,0 +1,118 @@
+use crate::*;
+
+// An element of a GLTF document, found from a path like `nodes[3].mesh`.
+// Indices are included so tooling can point back into the document
+#[derive(Clone, Copy)]
+pub enum PathTarget<'a> {
+  // The document's own fields, such as `scene` and `extensionsUsed`, and
+  // whole top-level arrays such as `meshes`
+  Root(&'a GLTF),
+  Asset(&'a Asset),
+  Scene(u32, &'a Scene),
+  Node(u32, &'a Node),
+  Camera(u32, &'a Camera),
+  Skin(u32, &'a Skin),
+  Material(u32, &'a Material),
+  Mesh(u32, &'a Mesh),
+  // Mesh index, primitive index, primitive
+  MeshPrimitive(u32, u32, &'a MeshPrimitive),
+  Accessor(u32, &'a Accessor),
+  BufferView(u32, &'a BufferView),
+  Buffer(u32, &'a Buffer),
+  Animation(u32, &'a Animation),
+  Image(u32, &'a Image),
+  Texture(u32, &'a Texture),
+  Sampler(u32, &'a Sampler),
+}
+
+// Splits a path segment like `nodes[3]` into its name and index
+fn parse_segment(segment: &str) -> Option<(&str, Option<u32>)> {
+  let (name, index) = match segment.split_once('[') {
+    None => (segment, None),
+    Some((name, rest)) => {
+      let index = rest.strip_suffix(']')?.parse::<u32>().ok()?;
+      (name, Some(index))
+    },
+  };
+  
+  if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()
+    || c == '_') {
+    return None;
+  }
+  
+  Some((name, index))
+}
+
+impl GLTF {
+  // Finds the element addressed by `path`, in the form used by paragen's
+  // validation messages: a top-level array and index such as `meshes[2]`,
+  // optionally followed by `.primitives[i]` for meshes, and then by any field
+  // names (`nodes[3].mesh`). Field names are checked for syntax only, and
+  // resolve to the element that owns them. `asset` is also accepted, as are
+  // the document's own fields and top-level arrays without an index, which
+  // resolve to PathTarget::Root
+  pub fn resolve_path(&self, path: &str) -> Option<PathTarget<'_>> {
+    let mut segments = path.split('.');
+    
+    // .split() always yields at least one segment
+    let (name, index) = parse_segment(segments.next().unwrap())?;
+    
+    let mut target = match (name, index) {
+      ("asset", None) => PathTarget::Asset(&self.asset),
+      ("scenes", Some(i)) => PathTarget::Scene(i,
+        self.scenes.get(i as usize)?),
+      ("nodes", Some(i)) => PathTarget::Node(i, self.nodes.get(i as usize)?),
+      ("cameras", Some(i)) => PathTarget::Camera(i,
+        self.cameras.get(i as usize)?),
+      ("skins", Some(i)) => PathTarget::Skin(i, self.skins.get(i as usize)?),
+      ("materials", Some(i)) => PathTarget::Material(i,
+        self.materials.get(i as usize)?),
+      ("meshes", Some(i)) => PathTarget::Mesh(i, self.meshes.get(i as usize)?),
+      ("accessors", Some(i)) => PathTarget::Accessor(i,
+        self.accessors.get(i as usize)?),
+      ("bufferViews", Some(i)) => PathTarget::BufferView(i,
+        self.buffer_views.get(i as usize)?),
+      ("buffers", Some(i)) => PathTarget::Buffer(i,
+        self.buffers.get(i as usize)?),
+      ("animations", Some(i)) => PathTarget::Animation(i,
+        self.animations.get(i as usize)?),
+      ("images", Some(i)) => PathTarget::Image(i,
+        self.images.get(i as usize)?),
+      ("textures", Some(i)) => PathTarget::Texture(i,
+        self.textures.get(i as usize)?),
+      ("samplers", Some(i)) => PathTarget::Sampler(i,
+        self.samplers.get(i as usize)?),
+      ("extensionsUsed", Some(i)) => {
+        self.extensions_used.get(i as usize)?;
+        PathTarget::Root(self)
+      },
+      ("extensionsRequired", Some(i)) => {
+        self.extensions_required.get(i as usize)?;
+        PathTarget::Root(self)
+      },
+      ("scene" | "extensions" | "extras" | "extensionsUsed"
+        | "extensionsRequired" | "scenes" | "nodes" | "cameras" | "skins"
+        | "materials" | "meshes" | "accessors" | "bufferViews" | "buffers"
+        | "animations" | "images" | "textures" | "samplers", None) => {
+        PathTarget::Root(self)
+      },
+      _ => return None,
+    };
+    
+    let mut first_field = true;
+    for segment in segments {
+      let (name, index) = parse_segment(segment)?;
+      
+      if let (PathTarget::Mesh(m, mesh), "primitives", Some(i), true) =
+        (target, name, index, first_field) {
+        target = PathTarget::MeshPrimitive(m, i,
+          mesh.primitives.get(i as usize)?);
+        continue;
+      }
+      
+      first_field = false;
+    }
+    
+    Some(target)
+  }
+}
@@ -0,0 +1,201 @@
+use crate::*;
+
+// Inverse of the spec's decoding for normalized signed bytes, max(c/127, -1).
+// Scaling by 127 rather than 128 keeps -1 and 1 exact, and means -128 (which
+// would decode to the same -1) is never written
+fn quantize_i8(value: f32) -> i8 {
+  (value.clamp(-1.0, 1.0) * 127.0).round() as i8
+}
+
+// Same as quantize_i8(), for normalized signed shorts
+fn quantize_i16(value: f32) -> i16 {
+  (value.clamp(-1.0, 1.0) * 32767.0).round() as i16
+}
+
+// Inverse of the spec's decoding for normalized unsigned integers, c/max
+fn quantize_unsigned(value: f32, max: f32) -> u32 {
+  (value.clamp(0.0, 1.0) * max).round() as u32
+}
+
+impl GLTF {
+  // Appends `normals` to `bytes`, the binary contents of buffer `buffer`, as
+  // normalized signed bytes, and adds a VEC3 accessor reading them. Each
+  // normal is padded to 4 bytes since vertex attributes must start on 4-byte
+  // boundaries. Core glTF only allows float normals, so this marks
+  // KHR_mesh_quantization required. Returns the new accessor's index
+  pub fn push_normals_i8(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    normals: &[[f32; 3]],
+  ) -> Result<u32, ErrorCode> {
+    let mut data = Vec::with_capacity(4 * normals.len());
+    for normal in normals {
+      for &component in normal {
+        data.push(quantize_i8(component) as u8);
+      }
+      data.push(0);
+    }
+    
+    let buffer_view = self.push_buffer_view(buffer, bytes, &data, Some(4),
+      Some(Target::ArrayBuffer))?;
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = ComponentType::Byte;
+    accessor.normalized = true;
+    accessor.count = normals.len() as u32;
+    accessor.type_ = Type::VEC3;
+    self.accessors.push(accessor);
+    
+    self.require_extension("KHR_mesh_quantization");
+    Ok(self.accessors.len() as u32 - 1)
+  }
+  
+  // Appends `uvs` to `bytes`, the binary contents of buffer `buffer`, and adds
+  // a VEC2 accessor reading them. `component_type` may be Float, or
+  // UnsignedShort or UnsignedByte for normalized integers that take half or a
+  // quarter of the space. Normalized integers only cover 0 to 1, so UVs
+  // outside that (as used for wrapping textures) are clamped. Returns the new
+  // accessor's index, and true if any UV was clamped, which callers should
+  // warn about
+  pub fn push_uvs(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    uvs: &[[f32; 2]], component_type: ComponentType,
+  ) -> Result<(u32, bool), ErrorCode> {
+    let stride = match component_type {
+      ComponentType::Float | ComponentType::UnsignedShort => None,
+      // Vertex attributes must start on 4-byte boundaries
+      ComponentType::UnsignedByte => Some(4),
+      _ => return Err(ErrorCode::Value),
+    };
+    
+    let mut data = Vec::with_capacity(8 * uvs.len());
+    let mut clamped = false;
+    
+    for uv in uvs {
+      for &component in uv {
+        clamped |= !(0.0..=1.0).contains(&component);
+        
+        match component_type {
+          ComponentType::Float => {
+            data.extend_from_slice(&component.to_le_bytes());
+          },
+          ComponentType::UnsignedShort => {
+            let value = quantize_unsigned(component, 65535.0) as u16;
+            data.extend_from_slice(&value.to_le_bytes());
+          },
+          ComponentType::UnsignedByte => {
+            data.push(quantize_unsigned(component, 255.0) as u8);
+          },
+          _ => unreachable!(),
+        }
+      }
+      
+      // Only UnsignedByte UVs need padding
+      pad_to_4(&mut data);
+    }
+    
+    let integer = component_type != ComponentType::Float;
+    let buffer_view = self.push_buffer_view(buffer, bytes, &data, stride,
+      Some(Target::ArrayBuffer))?;
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = component_type;
+    accessor.normalized = integer;
+    accessor.count = uvs.len() as u32;
+    accessor.type_ = Type::VEC2;
+    self.accessors.push(accessor);
+    
+    Ok((self.accessors.len() as u32 - 1, integer && clamped))
+  }
+  
+  // Packs VEC3 values as normalized signed shorts, each padded to 8 bytes,
+  // and marks KHR_mesh_quantization required
+  fn push_vec3_i16(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    values: impl Iterator<Item = [f32; 3]>, count: usize,
+  ) -> Result<u32, ErrorCode> {
+    let mut data = Vec::with_capacity(8 * count);
+    let mut min = vec![f64::INFINITY; 3];
+    let mut max = vec![f64::NEG_INFINITY; 3];
+    for value in values {
+      for (i, &component) in value.iter().enumerate() {
+        let quantized = quantize_i16(component);
+        data.extend_from_slice(&quantized.to_le_bytes());
+        min[i] = min[i].min(quantized as f64);
+        max[i] = max[i].max(quantized as f64);
+      }
+      data.extend_from_slice(&[0, 0]);
+    }
+    
+    let buffer_view = self.push_buffer_view(buffer, bytes, &data, Some(8),
+      Some(Target::ArrayBuffer))?;
+    
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = ComponentType::Short;
+    accessor.normalized = true;
+    accessor.count = count as u32;
+    accessor.type_ = Type::VEC3;
+    // min and max are in the stored integers, not the normalized values
+    if count > 0 {
+      accessor.min = min;
+      accessor.max = max;
+    }
+    self.accessors.push(accessor);
+    
+    // Loaders without the extension can't read these, so it's required
+    self.require_extension("KHR_mesh_quantization");
+    Ok(self.accessors.len() as u32 - 1)
+  }
+  
+  // Half-precision positions for targets short on memory. glTF has no 16-bit
+  // float component type, so rather than IEEE halves these are stored as
+  // normalized signed shorts (via KHR_mesh_quantization), which give more
+  // precision than halves over the same range anyway. Normalized values only
+  // cover -1 to 1, so positions are rescaled to fit: returns the accessor's
+  // index, and the offset and scale that map the stored values back to the
+  // originals, original = stored * scale + offset. Apply those as the
+  // translation and scale of the node using the mesh (before any other
+  // transform on it)
+  pub fn push_positions_i16(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    positions: &[[f32; 3]],
+  ) -> Result<(u32, [f64; 3], [f64; 3]), ErrorCode> {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for position in positions {
+      for i in 0..3 {
+        min[i] = min[i].min(position[i]);
+        max[i] = max[i].max(position[i]);
+      }
+    }
+    if positions.iter().flatten().any(|x| !x.is_finite()) {
+      return Err(ErrorCode::Value);
+    }
+    
+    let mut offset = [0.0; 3];
+    let mut scale = [1.0; 3];
+    if !positions.is_empty() {
+      for i in 0..3 {
+        offset[i] = (min[i] + max[i]) / 2.0;
+        // Flat axes still need a nonzero scale
+        scale[i] = match (max[i] - min[i]) / 2.0 {
+          0.0 => 1.0,
+          half_extent => half_extent,
+        };
+      }
+    }
+    
+    let accessor = self.push_vec3_i16(buffer, bytes, positions.iter()
+      .map(|p| [0, 1, 2].map(|i| (p[i] - offset[i]) / scale[i])),
+      positions.len())?;
+    
+    Ok((accessor, offset.map(|x| x as f64), scale.map(|x| x as f64)))
+  }
+  
+  // Normals as normalized signed shorts, the higher precision counterpart to
+  // push_normals_i8(). See push_positions_i16() for why these aren't halves.
+  // Returns the new accessor's index
+  pub fn push_normals_i16(&mut self, buffer: u32, bytes: &mut Vec<u8>,
+    normals: &[[f32; 3]],
+  ) -> Result<u32, ErrorCode> {
+    self.push_vec3_i16(buffer, bytes, normals.iter().copied(), normals.len())
+  }
+}
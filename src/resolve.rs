@@ -0,0 +1,59 @@
+use crate::*;
+
+// An accessor together with the buffer view and buffer its data is in, from
+// GLTF::resolve_accessor()
+#[derive(Clone)]
+pub struct ResolvedAccessor<'a> {
+  pub index: u32,
+  pub accessor: &'a Accessor,
+  pub buffer_view_index: u32,
+  pub buffer_view: &'a BufferView,
+  pub buffer_index: u32,
+  pub buffer: &'a Buffer,
+  // Bytes the accessor's elements span within the buffer, from the start of
+  // the first element to the end of the last. With a stride, bytes in
+  // between may belong to other accessors
+  pub byte_range: std::ops::Range<usize>,
+  // Distance between the starts of consecutive elements
+  pub stride: usize,
+}
+
+impl GLTF {
+  // Follows accessor `index` to its buffer view and buffer. None if any of
+  // them don't exist, including accessors with no buffer view (which are all
+  // zeros, or sparse)
+  pub fn resolve_accessor(&self, index: u32) -> Option<ResolvedAccessor<'_>> {
+    let accessor = self.accessors.get(index as usize)?;
+    let buffer_view_index = accessor.buffer_view?;
+    let buffer_view = self.buffer_views.get(buffer_view_index as usize)?;
+    let buffer = self.buffers.get(buffer_view.buffer as usize)?;
+    
+    let element_size = accessor.element_size();
+    let stride = buffer_view.byte_stride.map_or(element_size, |s| s as usize);
+    let length = match accessor.count as usize {
+      0 => 0,
+      count => stride * (count - 1) + element_size,
+    };
+    let start = buffer_view.byte_offset as usize
+      + accessor.byte_offset as usize;
+    
+    Some(ResolvedAccessor {
+      index,
+      accessor,
+      buffer_view_index,
+      buffer_view,
+      buffer_index: buffer_view.buffer,
+      buffer,
+      byte_range: start..start + length,
+      stride,
+    })
+  }
+  
+  // resolve_accessor() for every accessor in order, skipping those that
+  // can't be resolved
+  pub fn iter_resolved_accessors(&self,
+  ) -> impl Iterator<Item = ResolvedAccessor<'_>> {
+    (0..self.accessors.len() as u32)
+      .filter_map(|index| self.resolve_accessor(index))
+  }
+}
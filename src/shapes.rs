@@ -0,0 +1,248 @@
+// Ready-made solids as MeshData, with normals and UVs, centered on the
+// origin with Y up. Add one to a document with GLTF::add_mesh_data().
+// Curved shapes take how many flat pieces to approximate the curve with,
+// and fail with ErrorCode::Value if that's too few to enclose any volume
+
+use std::f32::consts::{PI, TAU};
+
+use crate::{ErrorCode, MeshData};
+
+fn empty() -> MeshData {
+  MeshData {
+    positions: Vec::new(),
+    normals: Some(Vec::new()),
+    uvs: Some(Vec::new()),
+    colors: None,
+    tangents: None,
+    indices: Vec::new(),
+  }
+}
+
+// Sine and cosine of `step` steps of `steps` around a full turn. The last
+// step lands exactly on the first, so seams close without gaps
+fn around(step: u32, steps: u32) -> (f32, f32) {
+  ((step % steps) as f32 / steps as f32 * TAU).sin_cos()
+}
+
+// .unwrap() acceptable in the helpers below because every shape starts from
+// empty(), which has normals and UVs
+fn push_vertex(data: &mut MeshData, position: [f32; 3], normal: [f32; 3],
+  uv: [f32; 2],
+) {
+  data.positions.push(position);
+  data.normals.as_mut().unwrap().push(normal);
+  data.uvs.as_mut().unwrap().push(uv);
+}
+
+// Triangles joining a grid of vertices added row by row from `first`, with
+// `columns + 1` vertices per row. Rows run top to bottom and columns left to
+// right as seen from the front
+fn push_grid(data: &mut MeshData, first: u32, rows: u32, columns: u32) {
+  for row in 0..rows {
+    for column in 0..columns {
+      let top_left = first + row * (columns + 1) + column;
+      let bottom_left = top_left + columns + 1;
+      data.indices.extend_from_slice(&[top_left, bottom_left,
+        bottom_left + 1, top_left, bottom_left + 1, top_left + 1]);
+    }
+  }
+}
+
+// A flat disc facing `up` (+1 or -1 along Y) at height `y`
+fn push_cap(data: &mut MeshData, radius: f32, y: f32, up: f32,
+  segments: u32,
+) {
+  let center = data.positions.len() as u32;
+  push_vertex(data, [0.0, y, 0.0], [0.0, up, 0.0], [0.5, 0.5]);
+  for segment in 0..=segments {
+    let (sin, cos) = around(segment, segments);
+    push_vertex(data, [radius * sin, y, radius * cos], [0.0, up, 0.0],
+      [0.5 + 0.5 * sin, 0.5 + 0.5 * cos * up]);
+  }
+  for segment in 0..segments {
+    let (a, b) = (center + 1 + segment, center + 2 + segment);
+    // Going round by increasing angle is counter-clockwise seen from above
+    let triangle = if up > 0.0 { [center, a, b] } else { [center, b, a] };
+    data.indices.extend_from_slice(&triangle);
+  }
+}
+
+// A box `width` along X, `height` along Y, and `depth` along Z. Each face
+// has its own four vertices, so edges stay sharp, and its own full 0 to 1
+// UV square
+pub fn cuboid(width: f32, height: f32, depth: f32) -> MeshData {
+  let half = [width / 2.0, height / 2.0, depth / 2.0];
+  // Normal, then the directions right and up as seen from outside
+  let faces: [[[f32; 3]; 3]; 6] = [
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+  ];
+  
+  let mut data = empty();
+  for [normal, right, up] in faces {
+    let first = data.positions.len() as u32;
+    for (v, along_up) in [(0.0, 1.0), (1.0, -1.0)] {
+      for (u, along_right) in [(0.0, -1.0), (1.0, 1.0)] {
+        let position = [0, 1, 2].map(|k| half[k]
+          * (normal[k] + along_right * right[k] + along_up * up[k]));
+        push_vertex(&mut data, position, normal, [u, v]);
+      }
+    }
+    push_grid(&mut data, first, 1, 1);
+  }
+  data
+}
+
+// A square-cornered sheet in the XZ plane, `width` along X and `depth` along
+// Z, facing +Y. UV (0, 0) is at the -X, -Z corner
+pub fn plane(width: f32, depth: f32) -> MeshData {
+  let mut data = empty();
+  for v in [0.0, 1.0] {
+    for u in [0.0, 1.0] {
+      push_vertex(&mut data, [(u - 0.5) * width, 0.0, (v - 0.5) * depth],
+        [0.0, 1.0, 0.0], [u, v]);
+    }
+  }
+  push_grid(&mut data, 0, 1, 1);
+  data
+}
+
+// A sphere made of `segments` slices around Y and `rings` bands from pole
+// to pole. U runs once around, starting and ending at +Z, and V from the top
+// pole to the bottom. Needs at least 3 segments and 2 rings
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32,
+) -> Result<MeshData, ErrorCode> {
+  if segments < 3 || rings < 2 {
+    return Err(ErrorCode::Value);
+  }
+  
+  let mut data = empty();
+  for ring in 0..=rings {
+    let v = ring as f32 / rings as f32;
+    // Exact at the bottom pole too, so its vertices all meet
+    let (sin_polar, cos_polar) = match ring == rings {
+      true => (0.0, -1.0),
+      false => (v * PI).sin_cos(),
+    };
+    for segment in 0..=segments {
+      let u = segment as f32 / segments as f32;
+      let (sin, cos) = around(segment, segments);
+      let normal = [sin_polar * sin, cos_polar, sin_polar * cos];
+      push_vertex(&mut data, normal.map(|x| x * radius), normal, [u, v]);
+    }
+  }
+  
+  // The grid's triangles touching the poles have two corners at the same
+  // point, so are left out
+  push_grid(&mut data, 0, rings, segments);
+  let last_ring = rings - 1;
+  let triangles: Vec<[u32; 3]> = data.indices.chunks(3).enumerate()
+    .filter(|(t, _)| {
+      // Each grid square's lower left triangle comes first
+      let (row, lower_left) = (*t as u32 / 2 / segments, t % 2 == 0);
+      !(row == 0 && !lower_left || row == last_ring && lower_left)
+    })
+    .map(|(_, triangle)| [triangle[0], triangle[1], triangle[2]]).collect();
+  data.indices = triangles.concat();
+  
+  Ok(data)
+}
+
+// A tube with flat caps, standing `height` tall along Y, made of `segments`
+// flat sides. The side's UVs wrap once around, starting and ending at +Z,
+// and each cap's fill the 0 to 1 square. Needs at least 3 segments
+pub fn cylinder(radius: f32, height: f32, segments: u32,
+) -> Result<MeshData, ErrorCode> {
+  if segments < 3 {
+    return Err(ErrorCode::Value);
+  }
+  
+  let mut data = empty();
+  for (v, y) in [(0.0, height / 2.0), (1.0, -height / 2.0)] {
+    for segment in 0..=segments {
+      let u = segment as f32 / segments as f32;
+      let (sin, cos) = around(segment, segments);
+      push_vertex(&mut data, [radius * sin, y, radius * cos],
+        [sin, 0.0, cos], [u, v]);
+    }
+  }
+  push_grid(&mut data, 0, 1, segments);
+  
+  push_cap(&mut data, radius, height / 2.0, 1.0, segments);
+  push_cap(&mut data, radius, -height / 2.0, -1.0, segments);
+  Ok(data)
+}
+
+// A cone with its point up, `height` tall along Y, made of `segments` flat
+// sides and closed by a flat base. UVs are laid out as for cylinder(). Needs
+// at least 3 segments
+pub fn cone(radius: f32, height: f32, segments: u32,
+) -> Result<MeshData, ErrorCode> {
+  if segments < 3 {
+    return Err(ErrorCode::Value);
+  }
+  
+  // The side's normals lean up by the slope of the side
+  let slant = (radius * radius + height * height).sqrt();
+  let normal = |(sin, cos): (f32, f32)| {
+    [height * sin / slant, radius / slant, height * cos / slant]
+  };
+  
+  let mut data = empty();
+  // Each side gets its own copy of the point, with a normal halfway between
+  // its edges', so shading doesn't pinch there
+  for segment in 0..segments {
+    let u = (segment as f32 + 0.5) / segments as f32;
+    push_vertex(&mut data, [0.0, height / 2.0, 0.0],
+      normal((u * TAU).sin_cos()), [u, 0.0]);
+  }
+  for segment in 0..=segments {
+    let u = segment as f32 / segments as f32;
+    let (sin, cos) = around(segment, segments);
+    push_vertex(&mut data, [radius * sin, -height / 2.0, radius * cos],
+      normal((sin, cos)), [u, 1.0]);
+  }
+  for segment in 0..segments {
+    let base = segments + segment;
+    data.indices.extend_from_slice(&[segment, base, base + 1]);
+  }
+  
+  push_cap(&mut data, radius, -height / 2.0, -1.0, segments);
+  Ok(data)
+}
+
+// A ring around Y whose tube center is `major_radius` from the origin, with
+// a tube `minor_radius` thick. Made of `segments` pieces around the ring and
+// `sides` around the tube. U runs once around the ring, starting and ending
+// at +Z, and V once around the tube, starting at its outer edge and heading
+// down. Needs at least 3 segments and 3 sides
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32,
+) -> Result<MeshData, ErrorCode> {
+  if segments < 3 || sides < 3 {
+    return Err(ErrorCode::Value);
+  }
+  
+  let mut data = empty();
+  for side in 0..=sides {
+    let v = side as f32 / sides as f32;
+    let (sin_tube, cos_tube) = around(side, sides);
+    for segment in 0..=segments {
+      let u = segment as f32 / segments as f32;
+      let (sin, cos) = around(segment, segments);
+      let normal = [cos_tube * sin, -sin_tube, cos_tube * cos];
+      let position = [
+        major_radius * sin + minor_radius * normal[0],
+        minor_radius * normal[1],
+        major_radius * cos + minor_radius * normal[2],
+      ];
+      push_vertex(&mut data, position, normal, [u, v]);
+    }
+  }
+  push_grid(&mut data, 0, sides, segments);
+  
+  Ok(data)
+}
@@ -0,0 +1,162 @@
+use crate::*;
+use crate::matrix::Matrix;
+
+// Joints a skinned mesh's JOINTS_0 attribute refers to, by position in
+// `joints`
+#[derive(Clone, serde::Serialize)]
+pub struct Skin {
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub name: String,
+  
+  // MAT4 float accessor with one matrix per joint, taking the mesh into each
+  // joint's space. Identity matrices are assumed if not given
+  #[serde(rename = "inverseBindMatrices")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub inverse_bind_matrices: Option<u32>,
+  
+  // Common root of the joints, if one of them isn't already
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub skeleton: Option<u32>,
+  
+  pub joints: Vec<u32>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extensions: Option<serde_json::Map<String, serde_json::Value>>,
+  
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub extras: Option<serde_json::Value>,
+}
+
+impl Skin {
+  pub fn new() -> Self {
+    Self {
+      name: String::from(""),
+      inverse_bind_matrices: None,
+      skeleton: None,
+      joints: Vec::new(),
+      extensions: None,
+      extras: None,
+    }
+  }
+}
+
+impl Node {
+  // The node's translation, rotation, and scale as one matrix, in glTF's
+  // column by column layout. Nodes with a matrix return it as is
+  pub fn local_matrix(&self) -> [f64; 16] {
+    if let Some(matrix) = self.matrix {
+      return matrix;
+    }
+    
+    let Rotation { x, y, z, w } = self.r;
+    let Scale { x: sx, y: sy, z: sz } = self.s;
+    
+    [
+      (1.0 - 2.0 * (y * y + z * z)) * sx,
+      2.0 * (x * y + z * w) * sx,
+      2.0 * (x * z - y * w) * sx,
+      0.0,
+      2.0 * (x * y - z * w) * sy,
+      (1.0 - 2.0 * (x * x + z * z)) * sy,
+      2.0 * (y * z + x * w) * sy,
+      0.0,
+      2.0 * (x * z + y * w) * sz,
+      2.0 * (y * z - x * w) * sz,
+      (1.0 - 2.0 * (x * x + y * y)) * sz,
+      0.0,
+      self.t.x,
+      self.t.y,
+      self.t.z,
+      1.0,
+    ]
+  }
+}
+
+impl GLTF {
+  // Each node's transform relative to the scene root, combining its own with
+  // its ancestors'. Fails if a child doesn't exist, or if a node has several
+  // parents or is its own ancestor
+  pub fn world_matrices(&self) -> Result<Vec<[f64; 16]>, ErrorCode> {
+    let mut parents = vec![None; self.nodes.len()];
+    for (i, node) in self.nodes.iter().enumerate() {
+      for &child in node.children.iter() {
+        let parent = parents.get_mut(child as usize).ok_or(ErrorCode::Index)?;
+        if parent.is_some() {
+          return Err(ErrorCode::Value);
+        }
+        *parent = Some(i);
+      }
+    }
+    
+    let mut world: Vec<Option<Matrix>> = vec![None; self.nodes.len()];
+    for i in 0..self.nodes.len() {
+      // Climbs to the nearest ancestor already done (or the root), then
+      // works back down. A chain longer than the node count means a cycle
+      let mut chain = vec![i];
+      let mut above = matrix::IDENTITY;
+      while let Some(parent) = parents[chain[chain.len() - 1]] {
+        if let Some(matrix) = world[parent] {
+          above = matrix;
+          break;
+        }
+        if chain.len() > self.nodes.len() {
+          return Err(ErrorCode::Value);
+        }
+        chain.push(parent);
+      }
+      
+      for &node in chain.iter().rev() {
+        above = matrix::multiply(&above, &self.nodes[node].local_matrix());
+        world[node] = Some(above);
+      }
+    }
+    
+    // .unwrap() acceptable here because every node was filled in above
+    Ok(world.into_iter().map(|matrix| matrix.unwrap()).collect())
+  }
+  
+  // Adds a skin over `joints`, taking the nodes' current placement as the
+  // bind pose: the inverse of each joint's world matrix is written to an
+  // accessor, appended to `bytes`, the binary contents of buffer `buffer`.
+  // Fails if there are no joints, a node or the buffer doesn't exist, the
+  // hierarchy is broken (see world_matrices()), or a joint has zero scale.
+  // Returns the skin's index
+  pub fn add_skin(&mut self, joints: &[u32], skeleton: Option<u32>,
+    buffer: u32, bytes: &mut Vec<u8>,
+  ) -> Result<u32, ErrorCode> {
+    if joints.is_empty() {
+      return Err(ErrorCode::Value);
+    }
+    if joints.iter().chain(skeleton.iter())
+      .any(|&node| node as usize >= self.nodes.len()) {
+      return Err(ErrorCode::Index);
+    }
+    
+    let world = self.world_matrices()?;
+    let mut packed = Vec::with_capacity(joints.len() * 64);
+    for &joint in joints {
+      let inverse = matrix::affine_inverse(&world[joint as usize])
+        .ok_or(ErrorCode::Value)?;
+      for x in inverse {
+        packed.extend_from_slice(&(x as f32).to_le_bytes());
+      }
+    }
+    
+    // Inverse bind matrices aren't read by the GPU, so get no target
+    let buffer_view = self.push_buffer_view(buffer, bytes, &packed, None,
+      None)?;
+    let mut accessor = Accessor::new();
+    accessor.buffer_view = Some(buffer_view);
+    accessor.component_type = ComponentType::Float;
+    accessor.count = joints.len() as u32;
+    accessor.type_ = Type::MAT4;
+    self.accessors.push(accessor);
+    
+    let mut skin = Skin::new();
+    skin.inverse_bind_matrices = Some(self.accessors.len() as u32 - 1);
+    skin.skeleton = skeleton;
+    skin.joints = joints.to_vec();
+    self.skins.push(skin);
+    Ok(self.skins.len() as u32 - 1)
+  }
+}
@@ -0,0 +1,146 @@
+use crate::*;
+
+// Elements of an accessor replaced by other values, stored as a list of
+// element indices and a list of the new elements. The rest come from the
+// accessor's buffer view, or are zeros without one
+#[derive(Clone, serde::Serialize)]
+pub struct AccessorSparse {
+  // How many elements are replaced
+  pub count: u32,
+  
+  pub indices: SparseIndices,
+  
+  pub values: SparseValues,
+}
+
+impl AccessorSparse {
+  // The buffer views holding the indices and values, in that order
+  pub fn buffer_views(&self) -> [u32; 2] {
+    [self.indices.buffer_view, self.values.buffer_view]
+  }
+  
+  pub fn buffer_views_mut(&mut self) -> [&mut u32; 2] {
+    [&mut self.indices.buffer_view, &mut self.values.buffer_view]
+  }
+}
+
+// Which elements are replaced, in strictly increasing order
+#[derive(Clone, serde::Serialize)]
+pub struct SparseIndices {
+  #[serde(rename = "bufferView")]
+  pub buffer_view: u32,
+  
+  #[serde(rename = "byteOffset")]
+  #[serde(skip_serializing_if = "is_default_byte_offset")]
+  pub byte_offset: u32,
+  
+  // UnsignedByte, UnsignedShort, or UnsignedInt
+  #[serde(rename = "componentType")]
+  pub component_type: ComponentType,
+}
+
+// The replacement elements, tightly packed, in the accessor's component type
+#[derive(Clone, serde::Serialize)]
+pub struct SparseValues {
+  #[serde(rename = "bufferView")]
+  pub buffer_view: u32,
+  
+  #[serde(rename = "byteOffset")]
+  #[serde(skip_serializing_if = "is_default_byte_offset")]
+  pub byte_offset: u32,
+}
+
+impl GLTF {
+  // Adds a copy of float accessor `base` with the elements at the indices in
+  // `overrides` replaced by the values paired with them, as a sparse
+  // accessor. The base can have no buffer view, for all zeros, as morph
+  // targets usually do. Each override needs one value per component, and
+  // may come in any order. The indices and values are appended to buffer
+  // `buffer`, whose contents are in `buffers`. If the base has min and max,
+  // the copy gets them recomputed with the overrides applied. Fails if the
+  // base isn't float or its data is missing, or an override is out of range,
+  // repeated, or the wrong length. Returns the new accessor's index
+  pub fn add_sparse_accessor(&mut self, base: u32, overrides: &[(u32, &[f32])],
+    buffer: u32, buffers: &mut [Vec<u8>],
+  ) -> Result<u32, ErrorCode> {
+    let mut accessor = self.accessors.get(base as usize)
+      .ok_or(ErrorCode::Index)?.clone();
+    if accessor.component_type != ComponentType::Float
+      || accessor.sparse.is_some() {
+      return Err(ErrorCode::Value);
+    }
+    let components = accessor.type_.components();
+    
+    let mut overrides = overrides.to_vec();
+    overrides.sort_by_key(|&(index, _)| index);
+    if overrides.is_empty()
+      || overrides.windows(2).any(|pair| pair[0].0 == pair[1].0)
+      || overrides.iter().any(|(_, values)| values.len() != components) {
+      return Err(ErrorCode::Value);
+    }
+    if overrides.last().is_some_and(|&(index, _)| index >= accessor.count)
+      || buffer as usize >= buffers.len() {
+      return Err(ErrorCode::Index);
+    }
+    
+    // Read before anything is written, so a missing base changes nothing
+    let has_bounds = !accessor.min.is_empty() || !accessor.max.is_empty();
+    let mut elements = match (has_bounds, accessor.buffer_view) {
+      (false, _) => Vec::new(),
+      (true, Some(_)) => {
+        let (_, view) = self.accessor_and_view(base)?;
+        accessor.read_f32(buffers, view)?
+      },
+      (true, None) => vec![0.0; accessor.count as usize * components],
+    };
+    
+    let max_index = overrides.last().map_or(0, |&(index, _)| index);
+    let (indices, component_type) = match max_index {
+      0..=65535 => (overrides.iter().flat_map(|&(index, _)| {
+        (index as u16).to_le_bytes()
+      }).collect::<Vec<u8>>(), ComponentType::UnsignedShort),
+      _ => (overrides.iter().flat_map(|&(index, _)| index.to_le_bytes())
+        .collect(), ComponentType::UnsignedInt),
+    };
+    let values: Vec<u8> = overrides.iter()
+      .flat_map(|(_, values)| values.iter())
+      .flat_map(|x| x.to_le_bytes()).collect();
+    
+    // Sparse data is never bound for the GPU, so its views get no target
+    let bytes = &mut buffers[buffer as usize];
+    let indices_view = self.push_buffer_view(buffer, bytes, &indices, None,
+      None)?;
+    let values_view = self.push_buffer_view(buffer, bytes, &values, None,
+      None)?;
+    
+    if has_bounds {
+      for &(index, values) in overrides.iter() {
+        let start = index as usize * components;
+        elements[start..start + components].copy_from_slice(values);
+      }
+      let mut min = vec![f64::INFINITY; components];
+      let mut max = vec![f64::NEG_INFINITY; components];
+      for element in elements.chunks_exact(components) {
+        for (k, &x) in element.iter().enumerate() {
+          min[k] = min[k].min(x as f64);
+          max[k] = max[k].max(x as f64);
+        }
+      }
+      accessor.min = min;
+      accessor.max = max;
+    }
+    
+    accessor.name = String::from("");
+    accessor.sparse = Some(AccessorSparse {
+      count: overrides.len() as u32,
+      indices: SparseIndices {
+        buffer_view: indices_view,
+        byte_offset: 0,
+        component_type,
+      },
+      values: SparseValues { buffer_view: values_view, byte_offset: 0 },
+    });
+    self.accessors.push(accessor);
+    Ok(self.accessors.len() as u32 - 1)
+  }
+}
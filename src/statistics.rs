@@ -0,0 +1,81 @@
+use crate::*;
+
+// Summary counts for a document, from GLTF::statistics()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statistics {
+  pub nodes: u64,
+  pub meshes: u64,
+  pub primitives: u64,
+  pub triangles: u64,
+  pub vertices: u64,
+  pub materials: u64,
+  pub accessors: u64,
+  // Size of the binary data actually passed in, not the declared lengths
+  pub buffer_bytes: u64,
+  pub textures: u64,
+  pub animations: u64,
+}
+
+impl std::fmt::Display for Statistics {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let rows = [
+      ("Nodes", self.nodes),
+      ("Meshes", self.meshes),
+      ("Primitives", self.primitives),
+      ("Triangles", self.triangles),
+      ("Vertices", self.vertices),
+      ("Materials", self.materials),
+      ("Accessors", self.accessors),
+      ("Buffer bytes", self.buffer_bytes),
+      ("Textures", self.textures),
+      ("Animations", self.animations),
+    ];
+    for (i, (label, value)) in rows.iter().enumerate() {
+      if i > 0 {
+        writeln!(f)?;
+      }
+      write!(f, "{:<13} {}", format!("{label}:"), value)?;
+    }
+    Ok(())
+  }
+}
+
+impl GLTF {
+  // Triangles drawn by every triangle, strip, and fan primitive in every
+  // mesh, counting each mesh once however many nodes use it. Primitives with
+  // broken accessor references count as none
+  pub fn triangle_count(&self) -> u64 {
+    self.meshes.iter().flat_map(|m| m.primitives.iter())
+      .filter(|primitive| matches!(primitive.mode, Mode::Triangles
+        | Mode::TriangleStrip | Mode::TriangleFan))
+      .map(|primitive| primitive.primitive_count(&self.accessors).unwrap_or(0))
+      .sum()
+  }
+  
+  // Sum of the POSITION counts of every primitive in every mesh. Primitives
+  // sharing an accessor count it each time
+  pub fn vertex_count(&self) -> u64 {
+    self.meshes.iter().flat_map(|m| m.primitives.iter())
+      .filter_map(|primitive| primitive.attributes.position)
+      .filter_map(|a| self.accessors.get(a as usize))
+      .map(|accessor| accessor.count as u64)
+      .sum()
+  }
+  
+  // Counts for logging and CI reports. `buffers` holds the binary contents of
+  // the document's buffers
+  pub fn statistics(&self, buffers: &[Vec<u8>]) -> Statistics {
+    Statistics {
+      nodes: self.nodes.len() as u64,
+      meshes: self.meshes.len() as u64,
+      primitives: self.meshes.iter().map(|m| m.primitives.len() as u64).sum(),
+      triangles: self.triangle_count(),
+      vertices: self.vertex_count(),
+      materials: self.materials.len() as u64,
+      accessors: self.accessors.len() as u64,
+      buffer_bytes: buffers.iter().map(|b| b.len() as u64).sum(),
+      textures: self.textures.len() as u64,
+      animations: self.animations.len() as u64,
+    }
+  }
+}
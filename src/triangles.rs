@@ -0,0 +1,356 @@
+// Passes over the triangles of a whole document
+
+use crate::*;
+
+impl GLTF {
+  // Accessor `index` and the buffer view it reads from
+  pub(crate) fn accessor_and_view(&self, index: u32,
+  ) -> Result<(&Accessor, &BufferView), ErrorCode> {
+    let accessor = self.accessors.get(index as usize).ok_or(ErrorCode::Index)?;
+    let buffer_view = self.buffer_views.get(accessor.buffer_view
+      .ok_or(ErrorCode::Index)? as usize).ok_or(ErrorCode::Index)?;
+    Ok((accessor, buffer_view))
+  }
+  
+  // Decoded POSITION data of a primitive
+  pub(crate) fn read_positions(&self, primitive: &MeshPrimitive,
+    buffers: &[Vec<u8>],
+  ) -> Result<Vec<[f32; 3]>, ErrorCode> {
+    let (accessor, buffer_view) = self.accessor_and_view(
+      primitive.attributes.position.ok_or(ErrorCode::Value)?)?;
+    if accessor.type_ != Type::VEC3 {
+      return Err(ErrorCode::Value);
+    }
+    
+    Ok(accessor.read_f32(buffers, buffer_view)?.chunks(3)
+      .map(|p| [p[0], p[1], p[2]]).collect())
+  }
+  
+  // Triangle list of a primitive, from its indices or (for non-indexed
+  // primitives) its POSITION count
+  pub(crate) fn read_triangle_indices(&self, primitive: &MeshPrimitive,
+    buffers: &[Vec<u8>],
+  ) -> Result<Vec<u32>, ErrorCode> {
+    match primitive.indices {
+      Some(indices) => {
+        let (accessor, buffer_view) = self.accessor_and_view(indices)?;
+        accessor.read_indices(buffers, buffer_view)
+      },
+      None => {
+        let position = primitive.attributes.position.ok_or(ErrorCode::Value)?;
+        let accessor = self.accessors.get(position as usize)
+          .ok_or(ErrorCode::Index)?;
+        Ok((0..accessor.count).collect())
+      },
+    }
+  }
+  
+  // Finds triangles that draw nothing useful, as described for
+  // geometry::find_degenerate(). Returns (mesh, primitive, triangle) for each.
+  // Only primitives in Triangles mode are checked
+  pub fn find_degenerate_triangles(&self, buffers: &[Vec<u8>], epsilon: f32,
+  ) -> Result<Vec<(u32, u32, u32)>, ErrorCode> {
+    let mut found = Vec::new();
+    
+    for (m, mesh) in self.meshes.iter().enumerate() {
+      for (p, primitive) in mesh.primitives.iter().enumerate() {
+        if primitive.mode != Mode::Triangles {
+          continue;
+        }
+        
+        let positions = self.read_positions(primitive, buffers)?;
+        let indices = self.read_triangle_indices(primitive, buffers)?;
+        for t in geometry::find_degenerate(&positions, &indices, epsilon)? {
+          found.push((m as u32, p as u32, t));
+        }
+      }
+    }
+    
+    Ok(found)
+  }
+  
+  // Removes the triangles find_degenerate_triangles() reports from index
+  // buffers, keeping the rest in order. The index data is rewritten in place
+  // and the accessor's count reduced, leaving unused bytes at the end of the
+  // buffer view. Non-indexed primitives are left alone, since removing their
+  // triangles would mean rewriting every vertex attribute. Returns the number
+  // of triangles removed
+  pub fn prune_degenerate(&mut self, buffers: &mut [Vec<u8>], epsilon: f32,
+  ) -> Result<u32, ErrorCode> {
+    let mut removed = 0;
+    
+    for m in 0..self.meshes.len() {
+      for p in 0..self.meshes[m].primitives.len() {
+        let primitive = &self.meshes[m].primitives[p];
+        let index_accessor = match (primitive.indices, &primitive.mode) {
+          (Some(indices), Mode::Triangles) => indices,
+          _ => continue,
+        };
+        
+        let positions = self.read_positions(primitive, buffers)?;
+        let indices = self.read_triangle_indices(primitive, buffers)?;
+        let degenerate = geometry::find_degenerate(&positions, &indices,
+          epsilon)?;
+        if degenerate.is_empty() {
+          continue;
+        }
+        
+        let mut kept = Vec::with_capacity(indices.len());
+        let mut next_degenerate = degenerate.iter().peekable();
+        for (t, triangle) in indices.chunks(3).enumerate() {
+          if next_degenerate.peek() == Some(&&(t as u32)) {
+            next_degenerate.next();
+          } else {
+            kept.extend_from_slice(triangle);
+          }
+        }
+        
+        self.write_indices(index_accessor, &kept, buffers)?;
+        removed += degenerate.len() as u32;
+      }
+    }
+    
+    Ok(removed)
+  }
+  
+  // Overwrites the data of index accessor `index` with `indices`, which must
+  // not be longer than the accessor's current data, and sets its count
+  pub(crate) fn write_indices(&mut self, index: u32, indices: &[u32],
+    buffers: &mut [Vec<u8>],
+  ) -> Result<(), ErrorCode> {
+    let (accessor, buffer_view) = self.accessor_and_view(index)?;
+    if indices.len() > accessor.count as usize {
+      return Err(ErrorCode::Value);
+    }
+    // Checks the existing data is all there, so writing over it can't go out
+    // of bounds
+    accessor.element_bytes(buffers, buffer_view)?;
+    
+    let size = accessor.component_type.size();
+    let stride = buffer_view.byte_stride.map_or(size, |s| s as usize);
+    let start = buffer_view.byte_offset as usize
+      + accessor.byte_offset as usize;
+    let component_type = accessor.component_type.clone();
+    let bytes = buffers.get_mut(buffer_view.buffer as usize)
+      .ok_or(ErrorCode::Index)?;
+    
+    for (i, &value) in indices.iter().enumerate() {
+      let offset = start + i * stride;
+      match component_type {
+        ComponentType::UnsignedByte => bytes[offset] = value as u8,
+        ComponentType::UnsignedShort => bytes[offset..offset + 2]
+          .copy_from_slice(&(value as u16).to_le_bytes()),
+        ComponentType::UnsignedInt => bytes[offset..offset + 4]
+          .copy_from_slice(&value.to_le_bytes()),
+        _ => return Err(ErrorCode::Value),
+      }
+    }
+    
+    self.accessors[index as usize].count = indices.len() as u32;
+    Ok(())
+  }
+  
+  // Per-component min and max of accessor `index`'s data, in the form glTF
+  // wants for accessor.min and accessor.max. Integer accessors give whole
+  // numbers, and normalized ones give the stored integers rather than the
+  // 0..1 or -1..1 values they stand for, as the spec requires
+  pub fn compute_bounds(&self, index: u32, buffers: &[Vec<u8>],
+  ) -> Result<(Vec<f64>, Vec<f64>), ErrorCode> {
+    let (accessor, buffer_view) = self.accessor_and_view(index)?;
+    let (bytes, stride) = accessor.element_bytes(buffers, buffer_view)?;
+    let component_size = accessor.component_type.size();
+    let components = accessor.type_.components();
+    let mut min = vec![f64::INFINITY; components];
+    let mut max = vec![f64::NEG_INFINITY; components];
+    
+    for element in 0..accessor.count as usize {
+      for i in 0..components {
+        let value = accessor.component_type.read_raw(
+          &bytes[element * stride + i * component_size..]);
+        min[i] = min[i].min(value);
+        max[i] = max[i].max(value);
+      }
+    }
+    
+    Ok((min, max))
+  }
+  
+  // Fills in min and max on each POSITION accessor missing them, as glTF
+  // requires them there, for data written without BufferBuilder. Empty and
+  // sparse accessors are skipped. Fails without changing anything if one of
+  // the accessors can't be read. Returns how many accessors were filled
+  pub fn fill_position_bounds(&mut self, buffers: &[Vec<u8>],
+  ) -> Result<u32, ErrorCode> {
+    let mut positions: Vec<u32> = self.meshes.iter()
+      .flat_map(|mesh| mesh.primitives.iter())
+      .filter_map(|primitive| primitive.attributes.position).collect();
+    positions.sort_unstable();
+    positions.dedup();
+    
+    let mut filled = Vec::new();
+    for index in positions {
+      let accessor = self.accessors.get(index as usize)
+        .ok_or(ErrorCode::Index)?;
+      if accessor.count == 0 || accessor.sparse.is_some()
+        || !accessor.min.is_empty() && !accessor.max.is_empty() {
+        continue;
+      }
+      filled.push((index, self.compute_bounds(index, buffers)?));
+    }
+    
+    let count = filled.len() as u32;
+    for (index, (min, max)) in filled {
+      let accessor = &mut self.accessors[index as usize];
+      accessor.min = min;
+      accessor.max = max;
+    }
+    Ok(count)
+  }
+  
+  // Copies the elements of accessor `index` listed in `vertices` into a new,
+  // tightly packed buffer view in the same buffer, and adds an accessor for
+  // them. min and max are recomputed if the original had them
+  fn gather_vertices(&mut self, index: u32, vertices: &[u32],
+    buffers: &mut [Vec<u8>],
+  ) -> Result<u32, ErrorCode> {
+    let (accessor, buffer_view) = self.accessor_and_view(index)?;
+    let (bytes, stride) = accessor.element_bytes(buffers, buffer_view)?;
+    let element_size = accessor.element_size();
+    // Vertex attributes must start on 4-byte boundaries
+    let padded_size = element_size.next_multiple_of(4);
+    
+    let mut data = Vec::with_capacity(padded_size * vertices.len());
+    for &v in vertices {
+      let start = v as usize * stride;
+      data.extend_from_slice(bytes.get(start..start + element_size)
+        .ok_or(ErrorCode::Index)?);
+      data.resize(data.len() + padded_size - element_size, 0);
+    }
+    
+    let mut new_accessor = accessor.clone();
+    let buffer = buffer_view.buffer;
+    let byte_stride = match padded_size == element_size {
+      true => None,
+      false => Some(padded_size as u32),
+    };
+    let new_view = self.push_buffer_view(buffer, buffers.get_mut(
+      buffer as usize).ok_or(ErrorCode::Index)?, &data, byte_stride,
+      Some(Target::ArrayBuffer))?;
+    
+    new_accessor.name = String::from("");
+    new_accessor.buffer_view = Some(new_view);
+    new_accessor.byte_offset = 0;
+    new_accessor.count = vertices.len() as u32;
+    let had_bounds = !new_accessor.min.is_empty()
+      || !new_accessor.max.is_empty();
+    self.accessors.push(new_accessor);
+    let new_index = self.accessors.len() as u32 - 1;
+    
+    if had_bounds {
+      let (min, max) = self.compute_bounds(new_index, buffers)?;
+      self.accessors[new_index as usize].min = min;
+      self.accessors[new_index as usize].max = max;
+    }
+    
+    Ok(new_index)
+  }
+  
+  // Splits each Triangles primitive using more than 65535 vertices into
+  // several primitives that each fit 16-bit indices (65535 itself is reserved
+  // as a restart value in some APIs). Vertices shared by triangles in
+  // different parts are duplicated into each. New vertex data and indices are
+  // appended to the buffers the original attributes were in, and the
+  // original accessors are left in place. Returns the number of primitives
+  // that were split
+  pub fn split_large_primitives(&mut self, buffers: &mut [Vec<u8>],
+  ) -> Result<u32, ErrorCode> {
+    const MAX_VERTICES: usize = 65535;
+    let mut split = 0;
+    
+    for m in 0..self.meshes.len() {
+      let mut new_primitives = Vec::new();
+      let mut changed = false;
+      
+      for primitive in self.meshes[m].primitives.clone() {
+        let indices = match primitive.mode {
+          Mode::Triangles => self.read_triangle_indices(&primitive, buffers)?,
+          _ => Vec::new(),
+        };
+        let mut unique = indices.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        if unique.len() <= MAX_VERTICES {
+          new_primitives.push(primitive);
+          continue;
+        }
+        
+        // Greedily fills each part with triangles in their original order,
+        // which keeps neighboring triangles (and so shared vertices) together
+        // as far as the original order did
+        let mut parts: Vec<(Vec<u32>, Vec<u32>)> = Vec::new();
+        let mut new_index = std::collections::HashMap::new();
+        let mut vertices = Vec::new();
+        let mut part_indices = Vec::new();
+        for triangle in indices.chunks(3) {
+          let added = triangle.iter()
+            .filter(|v| !new_index.contains_key(*v)).count();
+          if vertices.len() + added > MAX_VERTICES {
+            parts.push((std::mem::take(&mut vertices),
+              std::mem::take(&mut part_indices)));
+            new_index.clear();
+          }
+          
+          for &v in triangle {
+            let i = *new_index.entry(v).or_insert_with(|| {
+              vertices.push(v);
+              vertices.len() as u32 - 1
+            });
+            part_indices.push(i);
+          }
+        }
+        parts.push((vertices, part_indices));
+        
+        for (vertices, part_indices) in parts {
+          let mut part = primitive.clone();
+          part.bounds = None;
+          
+          for (slot, original) in part.attributes.accessors_mut().into_iter()
+            .zip(primitive.attributes.clone().accessors_mut()) {
+            if let Some(original) = *original {
+              *slot = Some(self.gather_vertices(original, &vertices,
+                buffers)?);
+            }
+          }
+          
+          let data: Vec<u8> = part_indices.iter()
+            .flat_map(|&i| (i as u16).to_le_bytes()).collect();
+          let position = primitive.attributes.position
+            .ok_or(ErrorCode::Value)?;
+          let buffer = self.accessor_and_view(position)?.1.buffer;
+          let buffer_view = self.push_buffer_view(buffer, buffers.get_mut(
+            buffer as usize).ok_or(ErrorCode::Index)?, &data, None,
+            Some(Target::ElementArrayBuffer))?;
+          
+          let mut accessor = Accessor::new();
+          accessor.buffer_view = Some(buffer_view);
+          accessor.component_type = ComponentType::UnsignedShort;
+          accessor.count = part_indices.len() as u32;
+          accessor.type_ = Type::SCALAR;
+          self.accessors.push(accessor);
+          part.indices = Some(self.accessors.len() as u32 - 1);
+          
+          new_primitives.push(part);
+        }
+        
+        split += 1;
+        changed = true;
+      }
+      
+      if changed {
+        self.meshes[m].primitives = new_primitives;
+      }
+    }
+    
+    Ok(split)
+  }
+}
@@ -0,0 +1,866 @@
+use crate::*;
+
+// Ordered from least to most severe, so callers can filter with
+// `message.severity >= Severity::Warning`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  // Worth knowing, but often intended
+  Info,
+  // Allowed by the spec, but probably a mistake
+  Warning,
+  // Breaks the spec, so viewers may reject the file
+  Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationMessage {
+  pub severity: Severity,
+  // Where the problem is, in resolve_path()'s format
+  pub path: String,
+  pub message: String,
+}
+
+impl std::fmt::Display for ValidationMessage {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let severity = match self.severity {
+      Severity::Info => "info",
+      Severity::Warning => "warning",
+      Severity::Error => "error",
+    };
+    write!(f, "{severity}: {}: {}", self.path, self.message)
+  }
+}
+
+// UVs can legally go outside 0 to 1 to repeat textures, but not by this much
+// in any reasonable texture mapping
+const UV_RANGE: std::ops::RangeInclusive<f32> = -4.0..=5.0;
+
+// Platform limits for GLTF::check_budget(). None means no limit
+#[derive(Clone, Debug)]
+pub struct Budget {
+  pub triangles: Option<u64>,
+  pub vertices: Option<u64>,
+  pub textures: Option<u64>,
+  pub buffer_bytes: Option<u64>,
+  pub nodes: Option<u64>,
+}
+
+impl Budget {
+  pub fn new() -> Self {
+    Self {
+      triangles: None,
+      vertices: None,
+      textures: None,
+      buffer_bytes: None,
+      nodes: None,
+    }
+  }
+}
+
+// Splits a "major.minor" version string, as asset.version must be
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+  let (major, minor) = version.split_once('.')?;
+  let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+  if !digits(major) || !digits(minor) {
+    return None;
+  }
+  Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+fn has_repeats<T: Eq + std::hash::Hash>(items: &[T]) -> bool {
+  let mut seen = std::collections::HashSet::new();
+  !items.iter().all(|item| seen.insert(item))
+}
+
+// Collects messages while a document is checked
+struct Messages(Vec<ValidationMessage>);
+
+impl Messages {
+  fn push(&mut self, severity: Severity, path: String, message: String) {
+    self.0.push(ValidationMessage { severity, path, message });
+  }
+  
+  // Reports an error if `index` doesn't point into an array of `len`
+  // elements named `array`
+  fn check_index(&mut self, path: String, index: u32, len: usize,
+    array: &str,
+  ) {
+    if index as usize >= len {
+      self.push(Severity::Error, path, format!("{array}[{index}] does not \
+        exist, there are only {len}"));
+    }
+  }
+}
+
+impl GLTF {
+  // Checks the document for spec violations (Severity::Error) and for things
+  // that are allowed but probably unintended. Only the JSON side is checked,
+  // buffer contents are not read. Returns every message found, in document
+  // order
+  pub fn validate(&self) -> Vec<ValidationMessage> {
+    let mut messages = Messages(Vec::new());
+    
+    self.validate_structure(&mut messages);
+    self.validate_hierarchy(&mut messages);
+    self.validate_materials(&mut messages);
+    
+    messages.0
+  }
+  
+  // Fast check for the commonest ways a document breaks the glTF JSON schema:
+  // required strings and arrays left empty, numbers outside their allowed
+  // ranges, array lengths, and repeated items in arrays that must be unique.
+  // Much of the schema is already enforced by the types here, and index
+  // checks are left to validate(), so this is a pre-check for the official
+  // validator rather than a replacement
+  pub fn schema_precheck(&self) -> Vec<ValidationMessage> {
+    let mut messages = Messages(Vec::new());
+    let mut error = |path: String, message: &str| {
+      messages.push(Severity::Error, path, String::from(message));
+    };
+    
+    let version = parse_version(&self.asset.version);
+    if version.is_none() {
+      error(String::from("asset.version"), "must be major.minor, such as 2.0");
+    }
+    if !self.asset.min_version.is_empty() {
+      match parse_version(&self.asset.min_version) {
+        None => error(String::from("asset.minVersion"),
+          "must be major.minor, such as 2.0"),
+        Some(min) if version.is_some_and(|version| min > version) => {
+          error(String::from("asset.minVersion"),
+            "must not be greater than asset.version");
+        },
+        Some(_) => {},
+      }
+    }
+    
+    for (name, list) in [("extensionsUsed", &self.extensions_used),
+      ("extensionsRequired", &self.extensions_required)] {
+      if has_repeats(list) {
+        error(String::from(name), "items must be unique");
+      }
+    }
+    
+    for (i, scene) in self.scenes.iter().enumerate() {
+      if has_repeats(&scene.nodes) {
+        error(format!("scenes[{i}].nodes"), "items must be unique");
+      }
+    }
+    
+    for (i, node) in self.nodes.iter().enumerate() {
+      if has_repeats(&node.children) {
+        error(format!("nodes[{i}].children"), "items must be unique");
+      }
+      let Rotation { x, y, z, w } = node.r;
+      if [x, y, z, w].iter().any(|c| !(-1.0..=1.0).contains(c)) {
+        error(format!("nodes[{i}].rotation"),
+          "components must be from -1 to 1");
+      }
+    }
+    
+    for (i, mesh) in self.meshes.iter().enumerate() {
+      if mesh.primitives.is_empty() {
+        error(format!("meshes[{i}].primitives"), "must not be empty");
+      }
+    }
+    
+    for (i, material) in self.materials.iter().enumerate() {
+      let pbr = &material.pbr_metallic_roughness;
+      let Color4 { r, g, b, a } = pbr.base_color_factor;
+      let factors = [
+        ("pbrMetallicRoughness.baseColorFactor", vec![r, g, b, a]),
+        ("pbrMetallicRoughness.metallicFactor", vec![pbr.metallic_factor]),
+        ("pbrMetallicRoughness.roughnessFactor", vec![pbr.roughness_factor]),
+        ("emissiveFactor", material.emissive_factor.to_vec()),
+      ];
+      for (name, values) in factors {
+        if values.iter().any(|x| !(0.0..=1.0).contains(x)) {
+          error(format!("materials[{i}].{name}"), "must be from 0 to 1");
+        }
+      }
+      if !(0.0..).contains(&material.alpha_cutoff) {
+        error(format!("materials[{i}].alphaCutoff"), "must not be negative");
+      }
+    }
+    
+    for (i, accessor) in self.accessors.iter().enumerate() {
+      if accessor.count == 0 {
+        error(format!("accessors[{i}].count"), "must be at least 1");
+      }
+      if accessor.normalized && matches!(accessor.component_type,
+        ComponentType::Float | ComponentType::UnsignedInt) {
+        error(format!("accessors[{i}].normalized"), "must not be set for \
+          FLOAT or UNSIGNED_INT components");
+      }
+      for (name, bound) in [("min", &accessor.min), ("max", &accessor.max)] {
+        if !bound.is_empty() && bound.len() != accessor.type_.components() {
+          error(format!("accessors[{i}].{name}"),
+            "must have one value per component of the accessor's type");
+        }
+      }
+      if let Some(sparse) = &accessor.sparse {
+        if !(1..=accessor.count).contains(&sparse.count) {
+          error(format!("accessors[{i}].sparse.count"),
+            "must be from 1 to the accessor's count");
+        }
+        if !matches!(sparse.indices.component_type,
+          ComponentType::UnsignedByte | ComponentType::UnsignedShort
+          | ComponentType::UnsignedInt) {
+          error(format!("accessors[{i}].sparse.indices.componentType"),
+            "must be UNSIGNED_BYTE, UNSIGNED_SHORT, or UNSIGNED_INT");
+        }
+      }
+    }
+    
+    for (i, view) in self.buffer_views.iter().enumerate() {
+      if view.byte_length == 0 {
+        error(format!("bufferViews[{i}].byteLength"), "must be at least 1");
+      }
+      if let Some(stride) = view.byte_stride {
+        if !(4..=252).contains(&stride) || !stride.is_multiple_of(4) {
+          error(format!("bufferViews[{i}].byteStride"),
+            "must be a multiple of 4 from 4 to 252");
+        }
+      }
+    }
+    
+    for (i, buffer) in self.buffers.iter().enumerate() {
+      if buffer.byte_length == 0 {
+        error(format!("buffers[{i}].byteLength"), "must be at least 1");
+      }
+    }
+    
+    for (i, camera) in self.cameras.iter().enumerate() {
+      if camera.validate().is_err() {
+        error(format!("cameras[{i}]"), "must have the projection named by \
+          type, with 0 < znear < zfar (znear may be 0 if orthographic), a \
+          positive yfov and aspectRatio, and nonzero xmag and ymag");
+      }
+    }
+    
+    for (i, skin) in self.skins.iter().enumerate() {
+      if skin.joints.is_empty() {
+        error(format!("skins[{i}].joints"), "must not be empty");
+      }
+      if has_repeats(&skin.joints) {
+        error(format!("skins[{i}].joints"), "items must be unique");
+      }
+    }
+    
+    for (i, image) in self.images.iter().enumerate() {
+      if image.validate().is_err() {
+        error(format!("images[{i}]"), "must have either a uri or a \
+          bufferView and mimeType");
+      }
+    }
+    
+    for (i, animation) in self.animations.iter().enumerate() {
+      if animation.channels.is_empty() {
+        error(format!("animations[{i}].channels"), "must not be empty");
+      }
+      if animation.samplers.is_empty() {
+        error(format!("animations[{i}].samplers"), "must not be empty");
+      }
+    }
+    
+    messages.0
+  }
+  
+  // Fails with an error for each limit in `budget` the document exceeds.
+  // Triangles and vertices count each mesh once, however many nodes use it,
+  // since that's what takes memory
+  pub fn check_budget(&self, budget: Budget,
+  ) -> Result<(), Vec<ValidationMessage>> {
+    let mut messages = Messages(Vec::new());
+    
+    let metrics = [
+      ("triangles", "meshes", self.triangle_count(), budget.triangles),
+      ("vertices", "accessors", self.vertex_count(), budget.vertices),
+      ("textures", "textures", self.textures.len() as u64, budget.textures),
+      ("buffer bytes", "buffers", self.total_buffer_bytes(),
+        budget.buffer_bytes),
+      ("nodes", "nodes", self.nodes.len() as u64, budget.nodes),
+    ];
+    for (metric, path, actual, allowed) in metrics {
+      if let Some(allowed) = allowed.filter(|&allowed| actual > allowed) {
+        messages.push(Severity::Error, String::from(path), format!(
+          "{actual} {metric} is over the budget of {allowed}"));
+      }
+    }
+    
+    match messages.0.is_empty() {
+      true => Ok(()),
+      false => Err(messages.0),
+    }
+  }
+  
+  // Checks on the values in accessors, which validate() can't do without
+  // the buffers' contents. `buffers` holds the binary contents of the
+  // document's buffers, in order. Accessors validate() would report as
+  // broken are skipped
+  pub fn validate_data(&self, buffers: &[Vec<u8>]) -> Vec<ValidationMessage> {
+    let mut messages = Messages(Vec::new());
+    
+    self.validate_uvs(&mut messages, buffers);
+    self.validate_tangents(&mut messages, buffers);
+    
+    messages.0
+  }
+  
+  // Recomputes min and max for every accessor that declares them, reporting
+  // any that don't match the data. Integer values must match exactly, float
+  // ones to within a small relative tolerance, since other tools may have
+  // rounded them when writing JSON. Accessors validate() would report as
+  // broken are skipped
+  pub fn verify_all_bounds(&self, buffers: &[Vec<u8>],
+  ) -> Vec<ValidationMessage> {
+    let mut messages = Messages(Vec::new());
+    
+    for (i, accessor) in self.accessors.iter().enumerate() {
+      if accessor.min.is_empty() && accessor.max.is_empty() {
+        continue;
+      }
+      let Ok((min, max)) = self.compute_bounds(i as u32, buffers) else {
+        continue
+      };
+      
+      let tolerance = |expected: f64| match accessor.component_type {
+        ComponentType::Float => 1e-6 * expected.abs().max(1.0),
+        _ => 0.0,
+      };
+      for (name, declared, actual) in [("min", &accessor.min, &min),
+        ("max", &accessor.max, &max)] {
+        if declared.is_empty() {
+          continue;
+        }
+        
+        let matches = declared.len() == actual.len() && declared.iter()
+          .zip(actual.iter()).all(|(d, a)| (d - a).abs() <= tolerance(*a));
+        if !matches {
+          messages.push(Severity::Error, format!("accessors[{i}].{name}"),
+            format!("declared as {declared:?}, but the data gives \
+            {actual:?}"));
+        }
+      }
+    }
+    
+    messages.0
+  }
+  
+  // Non-finite UVs are errors, and ones far outside the texture warnings
+  fn validate_uvs(&self, messages: &mut Messages, buffers: &[Vec<u8>]) {
+    for (i, mesh) in self.meshes.iter().enumerate() {
+      for (j, primitive) in mesh.primitives.iter().enumerate() {
+        for (name, accessor) in primitive.attributes.named() {
+          if !name.starts_with("TEXCOORD_") {
+            continue;
+          }
+          let Some(values) = accessor
+            .and_then(|a| self.accessor_and_view(a).ok())
+            .and_then(|(a, view)| a.read_f32(buffers, view).ok()) else {
+            continue
+          };
+          
+          let path = format!("meshes[{i}].primitives[{j}].attributes.{name}");
+          if let Some(k) = values.iter().position(|x| !x.is_finite()) {
+            messages.push(Severity::Error, path, format!("UV {} is {}",
+              k / 2, values[k]));
+          } else if let Some(k) = values.iter()
+            .position(|x| !UV_RANGE.contains(x)) {
+            messages.push(Severity::Warning, path, format!("UV {} has \
+              component {}, far outside the texture", k / 2, values[k]));
+          }
+        }
+      }
+    }
+  }
+  
+  // Tangent w holds the bitangent's handedness, and must be exactly 1 or -1.
+  // Some tools write 0 or leave it unnormalized, which flips or flattens
+  // normal mapping
+  fn validate_tangents(&self, messages: &mut Messages, buffers: &[Vec<u8>]) {
+    for (i, mesh) in self.meshes.iter().enumerate() {
+      for (j, primitive) in mesh.primitives.iter().enumerate() {
+        let Some(values) = primitive.attributes.tangent
+          .and_then(|a| self.accessor_and_view(a).ok())
+          .filter(|(a, _)| a.type_ == Type::VEC4)
+          .and_then(|(a, view)| a.read_f32(buffers, view).ok()) else {
+          continue
+        };
+        
+        let bad: Vec<f32> = values.chunks_exact(4).map(|tangent| tangent[3])
+          .filter(|&w| w != 1.0 && w != -1.0).collect();
+        if let Some(first) = bad.first() {
+          messages.push(Severity::Error,
+            format!("meshes[{i}].primitives[{j}].attributes.TANGENT"),
+            format!("{} tangents have w other than 1 or -1 (first is {})",
+            bad.len(), first));
+        }
+      }
+    }
+  }
+  
+  // Each node may have only one parent, and scene roots none, or transforms
+  // would apply along more than one path. Children that don't exist are
+  // left to validate_structure()
+  fn validate_hierarchy(&self, messages: &mut Messages) {
+    let mut parents = vec![Vec::new(); self.nodes.len()];
+    for (i, node) in self.nodes.iter().enumerate() {
+      for &child in node.children.iter() {
+        if let Some(parents) = parents.get_mut(child as usize) {
+          parents.push(format!("nodes[{i}]"));
+        }
+      }
+    }
+    
+    for (i, parents) in parents.iter().enumerate() {
+      if parents.len() > 1 {
+        messages.push(Severity::Error, format!("nodes[{i}]"), format!(
+          "node is a child of more than one node: {}", parents.join(", ")));
+      }
+    }
+    
+    for (i, scene) in self.scenes.iter().enumerate() {
+      for (j, &node) in scene.nodes.iter().enumerate() {
+        let Some(parents) = parents.get(node as usize) else { continue };
+        if !parents.is_empty() {
+          messages.push(Severity::Error, format!("scenes[{i}].nodes[{j}]"),
+            format!("scene root nodes[{node}] is also a child of {}",
+            parents.join(", ")));
+        }
+      }
+    }
+  }
+  
+  // Every extensions map in the document, with its path
+  pub(crate) fn extension_maps(&self,
+  ) -> Vec<(String, &serde_json::Map<String, serde_json::Value>)> {
+    type Extensions = Option<serde_json::Map<String, serde_json::Value>>;
+    fn list<'a>(maps: &mut Vec<(String, &'a Extensions)>, name: &str,
+      slots: impl Iterator<Item = &'a Extensions>,
+    ) {
+      maps.extend(slots.enumerate()
+        .map(|(i, slot)| (format!("{name}[{i}].extensions"), slot)));
+    }
+    
+    let mut maps = vec![
+      (String::from("extensions"), &self.extensions),
+      (String::from("asset.extensions"), &self.asset.extensions),
+    ];
+    list(&mut maps, "scenes", self.scenes.iter().map(|x| &x.extensions));
+    list(&mut maps, "nodes", self.nodes.iter().map(|x| &x.extensions));
+    list(&mut maps, "cameras", self.cameras.iter().map(|x| &x.extensions));
+    list(&mut maps, "skins", self.skins.iter().map(|x| &x.extensions));
+    list(&mut maps, "materials", self.materials.iter()
+      .map(|x| &x.extensions));
+    list(&mut maps, "meshes", self.meshes.iter().map(|x| &x.extensions));
+    list(&mut maps, "accessors", self.accessors.iter()
+      .map(|x| &x.extensions));
+    list(&mut maps, "bufferViews", self.buffer_views.iter()
+      .map(|x| &x.extensions));
+    list(&mut maps, "buffers", self.buffers.iter().map(|x| &x.extensions));
+    list(&mut maps, "animations", self.animations.iter()
+      .map(|x| &x.extensions));
+    list(&mut maps, "images", self.images.iter().map(|x| &x.extensions));
+    list(&mut maps, "textures", self.textures.iter().map(|x| &x.extensions));
+    list(&mut maps, "samplers", self.samplers.iter().map(|x| &x.extensions));
+    
+    for (i, material) in self.materials.iter().enumerate() {
+      maps.extend(material.nested_extension_maps().into_iter()
+        .map(|(name, map)| (format!("materials[{i}].{name}.extensions"),
+        map)));
+    }
+    for (m, mesh) in self.meshes.iter().enumerate() {
+      for (p, primitive) in mesh.primitives.iter().enumerate() {
+        maps.push((format!("meshes[{m}].primitives[{p}].extensions"),
+          &primitive.extensions));
+      }
+    }
+    for (a, animation) in self.animations.iter().enumerate() {
+      for (c, channel) in animation.channels.iter().enumerate() {
+        maps.push((format!("animations[{a}].channels[{c}].extensions"),
+          &channel.extensions));
+        maps.push((format!("animations[{a}].channels[{c}].target.extensions"),
+          &channel.target.extensions));
+      }
+      for (s, sampler) in animation.samplers.iter().enumerate() {
+        maps.push((format!("animations[{a}].samplers[{s}].extensions"),
+          &sampler.extensions));
+      }
+    }
+    
+    maps.into_iter().filter_map(|(path, slot)| Some((path, slot.as_ref()?)))
+      .collect()
+  }
+  
+  // Indices pointing at elements that exist, and byte ranges that fit
+  fn validate_structure(&self, messages: &mut Messages) {
+    for (i, name) in self.extensions_required.iter().enumerate() {
+      if !self.extensions_used.contains(name) {
+        messages.push(Severity::Error, format!("extensionsRequired[{i}]"),
+          format!("{name} is required but not listed in extensionsUsed"));
+      }
+    }
+    
+    for (path, extensions) in self.extension_maps() {
+      for name in extensions.keys() {
+        if !self.extensions_used.contains(name) {
+          messages.push(Severity::Error, path.clone(),
+            format!("{name} is used but not listed in extensionsUsed"));
+        }
+      }
+    }
+    
+    if let Some(scene) = self.scene {
+      messages.check_index(String::from("scene"), scene, self.scenes.len(),
+        "scenes");
+    }
+    
+    for (i, scene) in self.scenes.iter().enumerate() {
+      if scene.nodes.is_empty() {
+        messages.push(Severity::Warning, format!("scenes[{i}].nodes"),
+          String::from("scene has no nodes, so it will show nothing"));
+      }
+      
+      for (j, &node) in scene.nodes.iter().enumerate() {
+        messages.check_index(format!("scenes[{i}].nodes[{j}]"), node,
+          self.nodes.len(), "nodes");
+      }
+    }
+    
+    for (i, node) in self.nodes.iter().enumerate() {
+      if let Some(mesh) = node.mesh {
+        messages.check_index(format!("nodes[{i}].mesh"), mesh,
+          self.meshes.len(), "meshes");
+      }
+      if let Some(camera) = node.camera {
+        messages.check_index(format!("nodes[{i}].camera"), camera,
+          self.cameras.len(), "cameras");
+      }
+      if let Some(skin) = node.skin {
+        messages.check_index(format!("nodes[{i}].skin"), skin,
+          self.skins.len(), "skins");
+        if node.mesh.is_none() {
+          messages.push(Severity::Error, format!("nodes[{i}].skin"),
+            String::from("nodes with a skin must also have a mesh"));
+        }
+      }
+      for (j, &child) in node.children.iter().enumerate() {
+        messages.check_index(format!("nodes[{i}].children[{j}]"), child,
+          self.nodes.len(), "nodes");
+      }
+      
+      if let Some(matrix) = &node.matrix {
+        if !node.t.is_default() || !node.r.is_default()
+          || !node.s.is_default() {
+          messages.push(Severity::Error, format!("nodes[{i}].matrix"),
+            String::from("nodes with a matrix must not also have \
+            translation, rotation, or scale"));
+        }
+        if matrix::decompose(matrix).is_none() {
+          messages.push(Severity::Error, format!("nodes[{i}].matrix"),
+            String::from("matrix must be decomposable to translation, \
+            rotation, and scale"));
+        }
+      }
+      
+      let light = node.extensions.as_ref()
+        .and_then(|e| e.get(khr_lights_punctual::NAME))
+        .map(|e| e.get("light").and_then(|light| light.as_u64()));
+      match light {
+        Some(Some(light)) => messages.check_index(format!(
+          "nodes[{i}].extensions.KHR_lights_punctual.light"), light as u32,
+          self.punctual_light_count() as usize,
+          "extensions.KHR_lights_punctual.lights"),
+        Some(None) => messages.push(Severity::Error, format!(
+          "nodes[{i}].extensions.KHR_lights_punctual"), String::from(
+          "KHR_lights_punctual must have a light index in light")),
+        None => {},
+      }
+      
+      let instancing = node.extensions.as_ref()
+        .and_then(|e| e.get(EXT_MESH_GPU_INSTANCING))
+        .and_then(|e| e.get("attributes"))
+        .and_then(|attributes| attributes.as_object());
+      for (name, accessor) in instancing.into_iter().flatten() {
+        let path = format!(
+          "nodes[{i}].extensions.EXT_mesh_gpu_instancing.attributes.{name}");
+        match accessor.as_u64() {
+          Some(accessor) => messages.check_index(path, accessor as u32,
+            self.accessors.len(), "accessors"),
+          None => messages.push(Severity::Error, path,
+            String::from("instance attributes must be accessor indices")),
+        }
+      }
+      if instancing.is_some() && node.mesh.is_none() {
+        messages.push(Severity::Error, format!(
+          "nodes[{i}].extensions.EXT_mesh_gpu_instancing"), String::from(
+          "nodes with EXT_mesh_gpu_instancing must also have a mesh"));
+      }
+      
+      let lod = node.extensions.as_ref().and_then(|e| e.get(MsftLod::NAME));
+      if let Some(lod) = lod {
+        self.validate_lod(messages, i, node, lod);
+      }
+    }
+    
+    for (i, mesh) in self.meshes.iter().enumerate() {
+      if mesh.primitives.is_empty() {
+        messages.push(Severity::Error, format!("meshes[{i}].primitives"),
+          String::from("meshes must have at least one primitive"));
+      }
+      
+      for (j, primitive) in mesh.primitives.iter().enumerate() {
+        let path = format!("meshes[{i}].primitives[{j}]");
+        
+        if let Some(material) = primitive.material {
+          messages.check_index(format!("{path}.material"), material,
+            self.materials.len(), "materials");
+        }
+        if let Some(indices) = primitive.indices {
+          messages.check_index(format!("{path}.indices"), indices,
+            self.accessors.len(), "accessors");
+        }
+        for (name, accessor) in primitive.attributes.named() {
+          if let Some(accessor) = accessor {
+            messages.check_index(format!("{path}.attributes.{name}"),
+              accessor, self.accessors.len(), "accessors");
+          }
+        }
+        
+        let position = primitive.attributes.position
+          .and_then(|a| self.accessors.get(a as usize));
+        if let Some(position) = position {
+          if position.min.is_empty() || position.max.is_empty() {
+            messages.push(Severity::Error,
+              format!("{path}.attributes.POSITION"),
+              String::from("POSITION accessors must have min and max"));
+          }
+        }
+      }
+    }
+    
+    for (i, accessor) in self.accessors.iter().enumerate() {
+      if accessor.count == 0 {
+        messages.push(Severity::Error, format!("accessors[{i}].count"),
+          String::from("accessors must have at least one element"));
+      }
+      if let Some(sparse) = &accessor.sparse {
+        for (name, view) in ["indices", "values"].iter()
+          .zip(sparse.buffer_views()) {
+          messages.check_index(format!(
+            "accessors[{i}].sparse.{name}.bufferView"), view,
+            self.buffer_views.len(), "bufferViews");
+        }
+      }
+      
+      let Some(view_index) = accessor.buffer_view else { continue };
+      messages.check_index(format!("accessors[{i}].bufferView"), view_index,
+        self.buffer_views.len(), "bufferViews");
+      let Some(view) = self.buffer_views.get(view_index as usize) else {
+        continue
+      };
+      
+      let element_size = accessor.element_size() as u64;
+      let stride = view.byte_stride.map_or(element_size, |s| s as u64);
+      let end = accessor.byte_offset as u64 + match accessor.count as u64 {
+        0 => 0,
+        count => stride * (count - 1) + element_size,
+      };
+      if end > view.byte_length as u64 {
+        messages.push(Severity::Error, format!("accessors[{i}]"), format!(
+          "elements end at byte {end} of bufferViews[{view_index}], which \
+          is only {} bytes long", view.byte_length));
+      }
+    }
+    
+    for (i, view) in self.buffer_views.iter().enumerate() {
+      messages.check_index(format!("bufferViews[{i}].buffer"), view.buffer,
+        self.buffers.len(), "buffers");
+      
+      if let Some(buffer) = self.buffers.get(view.buffer as usize) {
+        let end = view.byte_offset as u64 + view.byte_length as u64;
+        if end > buffer.byte_length as u64 {
+          messages.push(Severity::Error, format!("bufferViews[{i}]"),
+            format!("ends at byte {end} of buffers[{}], which is only {} \
+            bytes long", view.buffer, buffer.byte_length));
+        }
+      }
+      
+      if let Some(stride) = view.byte_stride {
+        if !(4..=252).contains(&stride) || !stride.is_multiple_of(4) {
+          messages.push(Severity::Error, format!("bufferViews[{i}].byteStride"),
+            format!("must be a multiple of 4 from 4 to 252, but is {stride}"));
+        }
+      }
+    }
+    
+    for (i, skin) in self.skins.iter().enumerate() {
+      for (j, &joint) in skin.joints.iter().enumerate() {
+        messages.check_index(format!("skins[{i}].joints[{j}]"), joint,
+          self.nodes.len(), "nodes");
+      }
+      if let Some(skeleton) = skin.skeleton {
+        messages.check_index(format!("skins[{i}].skeleton"), skeleton,
+          self.nodes.len(), "nodes");
+      }
+      if let Some(matrices) = skin.inverse_bind_matrices {
+        let path = format!("skins[{i}].inverseBindMatrices");
+        messages.check_index(path.clone(), matrices, self.accessors.len(),
+          "accessors");
+        if let Some(accessor) = self.accessors.get(matrices as usize) {
+          if accessor.type_ != Type::MAT4
+            || accessor.component_type != ComponentType::Float
+            || (accessor.count as usize) < skin.joints.len() {
+            messages.push(Severity::Error, path, String::from(
+              "must be a float MAT4 accessor with a matrix per joint"));
+          }
+        }
+      }
+    }
+    
+    for (i, image) in self.images.iter().enumerate() {
+      if image.validate().is_err() {
+        messages.push(Severity::Error, format!("images[{i}]"), String::from(
+          "images need exactly one of uri or bufferView, and a mimeType if \
+          using bufferView"));
+      }
+      if let Some(view) = image.buffer_view {
+        messages.check_index(format!("images[{i}].bufferView"), view,
+          self.buffer_views.len(), "bufferViews");
+      }
+    }
+    
+    for (i, texture) in self.textures.iter().enumerate() {
+      if let Some(source) = texture.source {
+        messages.check_index(format!("textures[{i}].source"), source,
+          self.images.len(), "images");
+      }
+      if let Some(sampler) = texture.sampler {
+        messages.check_index(format!("textures[{i}].sampler"), sampler,
+          self.samplers.len(), "samplers");
+      }
+    }
+    
+    for (i, material) in self.materials.iter().enumerate() {
+      let pbr = &material.pbr_metallic_roughness;
+      let references = [
+        ("pbrMetallicRoughness.baseColorTexture",
+          pbr.base_color_texture.as_ref().map(|t| t.index)),
+        ("pbrMetallicRoughness.metallicRoughnessTexture",
+          pbr.metallic_roughness_texture.as_ref().map(|t| t.index)),
+        ("normalTexture", material.normal_texture.as_ref().map(|t| t.index)),
+        ("occlusionTexture",
+          material.occlusion_texture.as_ref().map(|t| t.index)),
+        ("emissiveTexture", material.emissive_texture.as_ref()
+          .map(|t| t.index)),
+      ];
+      for (name, texture) in references {
+        if let Some(texture) = texture {
+          messages.check_index(format!("materials[{i}].{name}.index"),
+            texture, self.textures.len(), "textures");
+        }
+      }
+    }
+    
+    for (i, animation) in self.animations.iter().enumerate() {
+      for (j, sampler) in animation.samplers.iter().enumerate() {
+        let path = format!("animations[{i}].samplers[{j}]");
+        messages.check_index(format!("{path}.input"), sampler.input,
+          self.accessors.len(), "accessors");
+        messages.check_index(format!("{path}.output"), sampler.output,
+          self.accessors.len(), "accessors");
+      }
+      
+      for (j, channel) in animation.channels.iter().enumerate() {
+        let path = format!("animations[{i}].channels[{j}]");
+        messages.check_index(format!("{path}.sampler"), channel.sampler,
+          animation.samplers.len(), &format!("animations[{i}].samplers"));
+        if let Some(node) = channel.target.node {
+          messages.check_index(format!("{path}.target.node"), node,
+            self.nodes.len(), "nodes");
+          
+          let transform = matches!(channel.target.path,
+            AnimationPath::Translation | AnimationPath::Rotation
+            | AnimationPath::Scale);
+          let matrix = self.nodes.get(node as usize)
+            .is_some_and(|node| node.matrix.is_some());
+          if transform && matrix {
+            messages.push(Severity::Error, format!("{path}.target.node"),
+              String::from("animated nodes must not have a matrix"));
+          }
+        }
+      }
+    }
+  }
+  
+  // MSFT_lod's ids must be nodes, and MSFT_screencoverage (if present) must
+  // have one value per level, counting the node itself
+  fn validate_lod(&self, messages: &mut Messages, i: usize, node: &Node,
+    lod: &serde_json::Value,
+  ) {
+    let path = format!("nodes[{i}].extensions.MSFT_lod");
+    let ids: Vec<_> = match lod.get("ids").and_then(|ids| ids.as_array()) {
+      Some(ids) => ids.iter().map(|id| id.as_u64()).collect(),
+      None => {
+        messages.push(Severity::Error, path, String::from(
+          "MSFT_lod must have an array of node indices in ids"));
+        return;
+      },
+    };
+    
+    for (j, id) in ids.iter().enumerate() {
+      match id {
+        Some(id) => messages.check_index(format!("{path}.ids[{j}]"),
+          *id as u32, self.nodes.len(), "nodes"),
+        None => messages.push(Severity::Error, format!("{path}.ids[{j}]"),
+          String::from("must be a node index")),
+      }
+    }
+    
+    let coverage = node.extras.as_ref()
+      .and_then(|extras| extras.get("MSFT_screencoverage"));
+    if let Some(coverage) = coverage {
+      let length = coverage.as_array().map(|c| c.len());
+      if length != Some(ids.len() + 1) {
+        messages.push(Severity::Error, format!(
+          "nodes[{i}].extras.MSFT_screencoverage"), format!("must be an \
+          array with one value per LOD level, {}", ids.len() + 1));
+      }
+    }
+  }
+  
+  fn validate_materials(&self, messages: &mut Messages) {
+    let mut used = vec![false; self.materials.len()];
+    for primitive in self.meshes.iter().flat_map(|m| m.primitives.iter()) {
+      if let Some(material) = primitive.material {
+        if let Some(used) = used.get_mut(material as usize) {
+          *used = true;
+        }
+      }
+    }
+    
+    for (i, material) in self.materials.iter().enumerate() {
+      let path = format!("materials[{i}]");
+      
+      if material.alpha_mode != AlphaMode::MASK
+        && material.alpha_cutoff != 0.5 {
+        messages.push(Severity::Warning, format!("{path}.alphaCutoff"),
+          String::from("alphaCutoff is ignored unless alphaMode is MASK"));
+      }
+      
+      if material.alpha_mode == AlphaMode::OPAQUE
+        && material.pbr_metallic_roughness.base_color_factor.a < 1.0 {
+        messages.push(Severity::Warning, format!(
+          "{path}.pbrMetallicRoughness.baseColorFactor"), String::from(
+          "alpha is ignored when alphaMode is OPAQUE"));
+      }
+      
+      if !used[i] {
+        messages.push(Severity::Info, path, String::from(
+          "not used by any mesh primitive"));
+      }
+    }
+  }
+}
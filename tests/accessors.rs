@@ -0,0 +1,89 @@
+use paragen::*;
+
+// Two vertices with a float VEC3 position and a normalized unsigned short VEC2
+// UV interleaved in each 16-byte element, followed by unrelated bytes
+fn interleaved() -> (Vec<Vec<u8>>, BufferView, Accessor, Accessor) {
+  let positions = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]];
+  let uvs = [[0u16, 65535], [32768, 16384]];
+  
+  let mut bytes = Vec::new();
+  for (position, uv) in positions.iter().zip(uvs.iter()) {
+    for component in position {
+      bytes.extend_from_slice(&component.to_le_bytes());
+    }
+    for component in uv {
+      bytes.extend_from_slice(&component.to_le_bytes());
+    }
+  }
+  bytes.extend_from_slice(&[0xff; 16]);
+  
+  let mut view = BufferView::new();
+  view.byte_length = 32;
+  view.byte_stride = Some(16);
+  
+  let mut position = Accessor::new();
+  position.component_type = ComponentType::Float;
+  position.count = 2;
+  position.type_ = Type::VEC3;
+  
+  let mut uv = Accessor::new();
+  uv.byte_offset = 12;
+  uv.component_type = ComponentType::UnsignedShort;
+  uv.normalized = true;
+  uv.count = 2;
+  uv.type_ = Type::VEC2;
+  
+  (vec![bytes], view, position, uv)
+}
+
+#[test]
+fn read_interleaved_accessors() {
+  let (buffers, view, position, uv) = interleaved();
+  
+  assert_eq!(position.read_f32(&buffers, &view).unwrap(),
+    vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+  
+  let uvs = uv.read_f32(&buffers, &view).unwrap();
+  let expected = [0.0, 1.0, 32768.0 / 65535.0, 16384.0 / 65535.0];
+  assert_eq!(uvs.len(), expected.len());
+  for (value, expected) in uvs.iter().zip(expected.iter()) {
+    assert!((value - expected).abs() < 1e-6, "{} != {}", value, expected);
+  }
+}
+
+#[test]
+fn read_interleaved_out_of_view() {
+  let (buffers, mut view, position, uv) = interleaved();
+  
+  // Ends after the second position, but before the second UV
+  view.byte_length = 28;
+  assert!(uv.read_f32(&buffers, &view).is_err());
+  assert!(position.read_f32(&buffers, &view).is_ok());
+}
+
+#[test]
+fn infer_strides_changes_nothing_on_failure() {
+  let (_, mut view, position, uv) = interleaved();
+  view.byte_stride = None;
+  let mut gltf = GLTF::new();
+  gltf.buffer_views.push(view.clone());
+  // Too short for its accessors with any stride, so inference fails here,
+  // after the first view's stride has been worked out
+  view.byte_length = 20;
+  gltf.buffer_views.push(view);
+  for buffer_view in [0, 1] {
+    for accessor in [&position, &uv] {
+      let mut accessor = accessor.clone();
+      accessor.buffer_view = Some(buffer_view);
+      gltf.accessors.push(accessor);
+    }
+  }
+  
+  assert_eq!(gltf.infer_strides(), Err(ErrorCode::Index));
+  assert_eq!(gltf.buffer_views[0].byte_stride, None);
+  
+  gltf.buffer_views.pop();
+  gltf.accessors.truncate(2);
+  gltf.infer_strides().unwrap();
+  assert_eq!(gltf.buffer_views[0].byte_stride, Some(16));
+}
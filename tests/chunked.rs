@@ -0,0 +1,58 @@
+use std::io::Read;
+
+use paragen::*;
+
+// A document with most top-level members filled in, including strings
+// holding characters that serialize escaped
+fn document() -> GLTF {
+  let mut gltf = GLTF::new();
+  let mut bytes = Vec::new();
+  gltf.buffers.push(Buffer::new());
+  let mut builder = BufferBuilder::new(&mut gltf, 0, &mut bytes).unwrap();
+  let primitive = builder.push_primitive(&[[0.0; 3], [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0]], None, None, Some(&[0, 1, 2])).unwrap();
+  let mut mesh = Mesh::new();
+  mesh.primitives.push(primitive);
+  gltf.meshes.push(mesh);
+  gltf.buffers[0].byte_length = bytes.len() as u32;
+  gltf.buffers[0].uri = String::from("data:application/octet-stream;base64,");
+  
+  for _ in 0..3 {
+    gltf.nodes.push(Node::new());
+  }
+  gltf.nodes[0].children.extend([1, 2]);
+  gltf.nodes[1].mesh = Some(0);
+  let mut scene = Scene::new();
+  scene.nodes.push(0);
+  gltf.scenes.push(scene);
+  gltf.add_material(Material::new());
+  let light = gltf.add_punctual_light(&khr_lights_punctual::Light::point(
+    [1.0; 3], 1.0, None)).unwrap();
+  gltf.set_node_light(2, light).unwrap();
+  gltf.extras = Some(serde_json::json!({ "note": "line\nbreak \"quoted\"" }));
+  gltf
+}
+
+#[test]
+fn chunked_output_matches_write_gltf() {
+  let mut expected = Vec::new();
+  let mut gltf = document();
+  gltf.ensure_default_scene();
+  gltf.write_to(&mut expected, true).unwrap();
+  
+  for size in [1, 7, 64, 1 << 20] {
+    let mut output = ChunkedOutput::new(document());
+    let mut actual = Vec::new();
+    let mut chunk = vec![0; size];
+    loop {
+      let read = output.read(&mut chunk).unwrap();
+      if read == 0 {
+        break;
+      }
+      assert!(read == size || output.read(&mut chunk).unwrap() == 0);
+      actual.extend_from_slice(&chunk[..read]);
+    }
+    assert_eq!(String::from_utf8(actual).unwrap(),
+      String::from_utf8(expected.clone()).unwrap(), "chunk size {size}");
+  }
+}
@@ -0,0 +1,47 @@
+use paragen::geometry;
+
+// Triangle list for a `size` x `size` grid of quads, in scrambled order
+fn scrambled_grid(size: u32) -> Vec<u32> {
+  let mut triangles = Vec::new();
+  for y in 0..size {
+    for x in 0..size {
+      let v = y * (size + 1) + x;
+      triangles.push([v, v + 1, v + size + 1]);
+      triangles.push([v + 1, v + size + 2, v + size + 1]);
+    }
+  }
+  
+  // Small LCG so the test doesn't need a random number crate
+  let mut state: u64 = 12345;
+  for i in (1..triangles.len()).rev() {
+    state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+    triangles.swap(i, (state >> 33) as usize % (i + 1));
+  }
+  
+  triangles.concat()
+}
+
+fn sorted_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+  let mut triangles: Vec<[u32; 3]> = indices.chunks(3)
+    .map(|t| [t[0], t[1], t[2]]).collect();
+  triangles.sort();
+  triangles
+}
+
+#[test]
+fn optimize_indices_keeps_triangles() {
+  let size = 20;
+  let mut indices = scrambled_grid(size);
+  let before = sorted_triangles(&indices);
+  
+  geometry::optimize_indices(&mut indices, ((size + 1) * (size + 1)) as usize)
+    .unwrap();
+  
+  assert_eq!(sorted_triangles(&indices), before);
+}
+
+#[test]
+fn optimize_indices_rejects_bad_input() {
+  assert!(geometry::optimize_indices(&mut [0, 1, 2, 3], 4).is_err());
+  assert!(geometry::optimize_indices(&mut [0, 1, 5], 4).is_err());
+}
@@ -0,0 +1,25 @@
+use paragen::*;
+
+#[test]
+fn glb_warns_about_misalignment() {
+  let mut gltf = GLTF::new();
+  let mut buffer = Buffer::new();
+  buffer.byte_length = 3;
+  gltf.buffers.push(buffer);
+  let mut view = BufferView::new();
+  view.byte_length = 3;
+  gltf.buffer_views.push(view);
+  // Ends past u32::MAX, which must not overflow
+  let mut view = BufferView::new();
+  view.byte_offset = u32::MAX;
+  view.byte_length = 2;
+  gltf.buffer_views.push(view);
+  
+  let (glb, warnings) = gltf.to_glb(&[vec![1, 2, 3]]).unwrap();
+  assert!(glb.len().is_multiple_of(4));
+  assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+  let paths: Vec<&str> = warnings.iter().map(|w| w.path.as_str()).collect();
+  assert_eq!(paths, ["buffers[0].byteLength", "bufferViews[0].byteLength",
+    "bufferViews[1].byteLength"]);
+  assert!(warnings.iter().all(|w| gltf.resolve_path(&w.path).is_some()));
+}
@@ -0,0 +1,30 @@
+use paragen::*;
+
+#[test]
+fn instancing_accessors_follow_canonicalize() {
+  let mut gltf = GLTF::new();
+  let mut bytes = Vec::new();
+  gltf.buffers.push(Buffer::new());
+  gltf.meshes.push(Mesh::new());
+  // Unused, so canonicalize() moves it after the instance accessors
+  gltf.accessors.push(Accessor::new());
+  
+  let mut instance = Instance::new();
+  instance.rotation = [0.0, 0.0, 1.0, 0.0];
+  let node = gltf.add_instanced_mesh(0, &[Instance::new(), instance], 0,
+    &mut bytes).unwrap();
+  assert!(gltf.extensions_used.iter()
+    .any(|name| name == ExtMeshGpuInstancing::NAME));
+  
+  gltf.canonicalize().unwrap();
+  
+  let instancing = ExtMeshGpuInstancing::from_node(
+    &gltf.nodes[node as usize]).unwrap();
+  assert_eq!(instancing.attributes.len(), 2);
+  let translation = instancing.attributes["TRANSLATION"];
+  let rotation = instancing.attributes["ROTATION"];
+  assert!(translation < 2 && rotation < 2);
+  assert!(gltf.accessors[translation as usize].type_ == Type::VEC3);
+  assert!(gltf.accessors[rotation as usize].type_ == Type::VEC4);
+  assert_eq!(gltf.accessors[2].count, 0);
+}
@@ -0,0 +1,40 @@
+use paragen::*;
+
+// Material `material`'s MSFT_lod ids
+fn lods(gltf: &GLTF, material: usize) -> serde_json::Value {
+  gltf.materials[material].extensions.as_ref().unwrap()["MSFT_lod"]["ids"]
+    .clone()
+}
+
+#[test]
+fn optimize_keeps_material_lods() {
+  let mut gltf = GLTF::new();
+  
+  // 0 is unused, 1 and 3 are the same, and 2 is only an LOD level of 1
+  for roughness in [0.1, 0.2, 0.3, 0.2] {
+    let mut material = Material::new();
+    material.pbr_metallic_roughness.roughness_factor = roughness;
+    gltf.add_material(material);
+  }
+  gltf.add_material_extension(1, &MsftLod { ids: vec![2] }).unwrap();
+  gltf.add_material_extension(3, &MsftLod { ids: vec![2] }).unwrap();
+  
+  let mut primitive = MeshPrimitive::new();
+  primitive.material = Some(1);
+  let mut mesh = Mesh::new();
+  mesh.primitives.push(primitive.clone());
+  primitive.material = Some(3);
+  mesh.primitives.push(primitive);
+  gltf.meshes.push(mesh);
+  gltf.nodes.push(NodeBuilder::new().mesh(0).build());
+  
+  let mut buffers = Vec::new();
+  let report = gltf.optimize(&mut buffers, OptimizeOptions::new()).unwrap();
+  
+  assert_eq!(report.materials_removed, 2);
+  assert_eq!(gltf.materials.len(), 2);
+  assert_eq!(gltf.meshes[0].primitives[0].material, Some(0));
+  assert_eq!(gltf.meshes[0].primitives[1].material, Some(0));
+  assert_eq!(lods(&gltf, 0), serde_json::json!([1]));
+  assert_eq!(gltf.materials[1].pbr_metallic_roughness.roughness_factor, 0.3);
+}
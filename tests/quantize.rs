@@ -0,0 +1,27 @@
+use paragen::*;
+
+#[test]
+fn byte_normals_require_mesh_quantization() {
+  let mut gltf = GLTF::new();
+  let mut bytes = Vec::new();
+  gltf.buffers.push(Buffer::new());
+  
+  gltf.push_normals_i8(0, &mut bytes, &[[0.0, 1.0, 0.0]]).unwrap();
+  
+  for list in [&gltf.extensions_used, &gltf.extensions_required] {
+    assert!(list.iter().any(|name| name == "KHR_mesh_quantization"));
+  }
+}
+
+#[test]
+fn short_positions_require_mesh_quantization() {
+  let mut gltf = GLTF::new();
+  let mut bytes = Vec::new();
+  gltf.buffers.push(Buffer::new());
+  
+  gltf.push_positions_i16(0, &mut bytes, &[[0.0; 3], [1.0; 3]]).unwrap();
+  
+  for list in [&gltf.extensions_used, &gltf.extensions_required] {
+    assert!(list.iter().any(|name| name == "KHR_mesh_quantization"));
+  }
+}
@@ -0,0 +1,96 @@
+use paragen::*;
+
+// A textured mesh on node `node`, drawn with a new material whose base color
+// and anisotropy textures are new textures of new images
+fn textured_node(gltf: &mut GLTF, bytes: &mut Vec<u8>, node: u32,
+  name: &str,
+) {
+  let mut builder = BufferBuilder::new(gltf, 0, bytes).unwrap();
+  let mut primitive = builder.push_primitive(&[[0.0; 3], [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0]], None, Some(&[[0.0; 2]; 3]), Some(&[0, 1, 2])).unwrap();
+  
+  let mut textures = Vec::new();
+  for suffix in ["color", "anisotropy"] {
+    let mut image = Image::new();
+    image.uri = format!("{name}_{suffix}.png");
+    gltf.images.push(image);
+    gltf.samplers.push(Sampler::pixelated());
+    textures.push(gltf.add_texture(gltf.images.len() as u32 - 1,
+      Some(gltf.samplers.len() as u32 - 1)).unwrap());
+  }
+  
+  let mut material = Material::new();
+  material.pbr_metallic_roughness.base_color_texture =
+    Some(TextureInfo::new(textures[0]));
+  let material = gltf.add_material(material);
+  let mut anisotropy = khr_materials::Anisotropy::new();
+  anisotropy.texture = Some(TextureInfo::new(textures[1]));
+  gltf.add_material_extension(material, &anisotropy).unwrap();
+  
+  primitive.material = Some(material);
+  let mut mesh = Mesh::new();
+  mesh.primitives.push(primitive);
+  gltf.meshes.push(mesh);
+  gltf.nodes[node as usize].mesh = Some(gltf.meshes.len() as u32 - 1);
+}
+
+#[test]
+fn extract_subtree_remaps_references() {
+  let mut gltf = GLTF::new();
+  let mut bytes = Vec::new();
+  gltf.buffers.push(Buffer::new());
+  
+  // Node 0 is left out, so everything it uses shifts what node 1's subtree
+  // (1, its child 2, and its LOD level 3) uses
+  for _ in 0..4 {
+    gltf.nodes.push(Node::new());
+  }
+  gltf.nodes[1].children.push(2);
+  textured_node(&mut gltf, &mut bytes, 0, "other");
+  textured_node(&mut gltf, &mut bytes, 2, "kept");
+  textured_node(&mut gltf, &mut bytes, 3, "lod");
+  gltf.set_lods(1, &[3], &[0.5, 0.1]).unwrap();
+  
+  let other = gltf.add_punctual_light(&khr_lights_punctual::Light::point(
+    [1.0; 3], 1.0, None)).unwrap();
+  gltf.set_node_light(0, other).unwrap();
+  let kept = gltf.add_punctual_light(&khr_lights_punctual::Light::spot(
+    [1.0; 3], 1.0, None, 0.1, 0.5)).unwrap();
+  gltf.set_node_light(1, kept).unwrap();
+  
+  gltf.materials[0].extensions.get_or_insert_with(Default::default)
+    .insert(String::from("KHR_materials_unlit"), serde_json::json!({}));
+  gltf.use_extension("KHR_materials_unlit");
+  gltf.buffers[0].byte_length = bytes.len() as u32;
+  
+  let (subtree, _) = gltf.extract_subtree(1, &[bytes]).unwrap();
+  
+  let errors: Vec<String> = subtree.validate().into_iter()
+    .filter(|message| message.severity == Severity::Error)
+    .map(|message| message.to_string()).collect();
+  assert_eq!(errors, Vec::<String>::new());
+  
+  assert_eq!(subtree.nodes.len(), 3);
+  assert_eq!(subtree.materials.len(), 2);
+  assert_eq!(subtree.textures.len(), 4);
+  assert_eq!(subtree.images.len(), 4);
+  assert_eq!(subtree.samplers.len(), 4);
+  assert_eq!(subtree.punctual_light_count(), 1);
+  
+  let material = &subtree.materials[subtree.meshes[0].primitives[0].material
+    .unwrap() as usize];
+  let texture = &subtree.textures[material.pbr_metallic_roughness
+    .base_color_texture.as_ref().unwrap().index as usize];
+  assert_eq!(subtree.images[texture.source.unwrap() as usize].uri,
+    "kept_color.png");
+  let anisotropy = &material.extensions.as_ref().unwrap()
+    ["KHR_materials_anisotropy"]["anisotropyTexture"]["index"];
+  let texture = &subtree.textures[anisotropy.as_u64().unwrap() as usize];
+  assert_eq!(subtree.images[texture.source.unwrap() as usize].uri,
+    "kept_anisotropy.png");
+  
+  let lod = &subtree.nodes[0].extensions.as_ref().unwrap()["MSFT_lod"]["ids"];
+  assert_eq!(lod, &serde_json::json!([2]));
+  assert!(!subtree.extensions_used.iter()
+    .any(|name| name == "KHR_materials_unlit"));
+}
@@ -0,0 +1,89 @@
+use paragen::*;
+
+// A document broken in as many ways as validate() reports
+fn broken() -> GLTF {
+  let mut gltf = GLTF::new();
+  gltf.scene = Some(4);
+  gltf.scenes.push(Scene::new());
+  let mut scene = Scene::new();
+  scene.nodes.push(9);
+  gltf.scenes.push(scene);
+  gltf.extensions_required.push(String::from("EXT_unknown"));
+  
+  let unlisted = || {
+    let mut map = serde_json::Map::new();
+    map.insert(String::from("EXT_unlisted"), serde_json::json!({}));
+    Some(map)
+  };
+  gltf.extensions = unlisted();
+  gltf.asset.extensions = unlisted();
+  
+  let mut node = Node::new();
+  node.mesh = Some(7);
+  node.camera = Some(7);
+  node.skin = Some(7);
+  node.children.push(7);
+  node.extensions = unlisted();
+  gltf.nodes.push(node);
+  
+  gltf.meshes.push(Mesh::new());
+  let mut primitive = MeshPrimitive::new();
+  primitive.material = Some(7);
+  primitive.indices = Some(7);
+  primitive.extensions = unlisted();
+  let mut mesh = Mesh::new();
+  mesh.primitives.push(primitive);
+  gltf.meshes.push(mesh);
+  
+  let mut accessor = Accessor::new();
+  accessor.buffer_view = Some(7);
+  gltf.accessors.push(accessor);
+  let mut view = BufferView::new();
+  view.buffer = 7;
+  view.byte_stride = Some(3);
+  gltf.buffer_views.push(view);
+  
+  let mut skin = Skin::new();
+  skin.joints.push(7);
+  skin.inverse_bind_matrices = Some(7);
+  gltf.skins.push(skin);
+  gltf.animations.push(Animation::new());
+  gltf
+}
+
+#[test]
+fn validation_paths_resolve() {
+  let gltf = broken();
+  let mut messages = gltf.validate();
+  assert!(messages.len() > 20);
+  
+  let mut budget = Budget::new();
+  budget.triangles = Some(0);
+  budget.vertices = Some(0);
+  budget.textures = Some(0);
+  budget.buffer_bytes = Some(0);
+  budget.nodes = Some(0);
+  let budget_messages = gltf.check_budget(budget).unwrap_err();
+  assert!(!budget_messages.is_empty());
+  messages.extend(budget_messages);
+  
+  let unresolved: Vec<String> = messages.iter()
+    .filter(|message| gltf.resolve_path(&message.path).is_none())
+    .map(|message| message.to_string()).collect();
+  assert_eq!(unresolved, Vec::<String>::new());
+}
+
+#[test]
+fn budget_counts_textures() {
+  let mut gltf = GLTF::new();
+  gltf.images.push(Image::new());
+  gltf.textures.push(Texture::new());
+  gltf.textures.push(Texture::new());
+  
+  let mut budget = Budget::new();
+  budget.textures = Some(1);
+  let messages = gltf.check_budget(budget).unwrap_err();
+  assert_eq!(messages.len(), 1);
+  assert_eq!(messages[0].path, "textures");
+  assert_eq!(messages[0].message, "2 textures is over the budget of 1");
+}